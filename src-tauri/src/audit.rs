@@ -0,0 +1,320 @@
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::types::{AuditExport, AuditIssue, AuditLog, AuditLogQuery, AuditVerification};
+
+/// `prev_hash` of the first entry in the chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Environment variable holding a hex HMAC key for signing entries. The key
+/// deliberately lives outside the database so whoever can rewrite the
+/// database cannot re-sign a forged chain.
+pub const SIGNING_KEY_ENV: &str = "CLAIMSENSE_AUDIT_SIGNING_KEY";
+
+/// Settings key recording the first sequence sealed with the signing key.
+/// Entries from there on must carry a signature; the value is itself signed
+/// so it can't be moved forward to excuse stripped signatures.
+pub const SIGNING_START_KEY: &str = "audit_signing_start";
+
+pub fn signing_key_from_env() -> Option<Vec<u8>> {
+    std::env::var(SIGNING_KEY_ENV)
+        .ok()
+        .and_then(|value| hex::decode(value.trim()).ok())
+        .filter(|key| !key.is_empty())
+}
+
+/// Hash over every stored field of the entry plus the previous entry's hash.
+/// Fields are serialized as a JSON array so the encoding is unambiguous.
+pub fn compute_entry_hash(log: &AuditLog) -> String {
+    let canonical = serde_json::json!([
+        log.sequence,
+        log.id.to_string(),
+        log.user_id.to_string(),
+        log.action,
+        log.resource_type,
+        log.resource_id.map(|id| id.to_string()),
+        log.details,
+        log.created_at.to_rfc3339(),
+        log.prev_hash,
+    ]);
+
+    hex::encode(Sha256::digest(canonical.to_string().as_bytes()))
+}
+
+pub fn sign(entry_hash: &str, key: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key)
+        .expect("HMAC accepts keys of any length");
+    mac.update(entry_hash.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn signing_start_marker(sequence: i64, key: &[u8]) -> String {
+    format!("{}:{}", sequence, sign(&format!("signing-start:{}", sequence), key))
+}
+
+/// The sequence from which signatures are required, or `None` when the
+/// marker was tampered with.
+fn parse_signing_start(marker: &str, key: &[u8]) -> Option<i64> {
+    let (sequence, _) = marker.split_once(':')?;
+    let sequence: i64 = sequence.parse().ok()?;
+    (signing_start_marker(sequence, key) == marker).then_some(sequence)
+}
+
+/// Fills in the chain fields of a new entry appended after `previous`.
+pub fn seal(log: &mut AuditLog, previous: Option<(i64, String)>, signing_key: Option<&[u8]>) {
+    let (last_sequence, prev_hash) = previous.unwrap_or((0, GENESIS_HASH.to_string()));
+    log.sequence = last_sequence + 1;
+    log.prev_hash = prev_hash;
+    log.entry_hash = compute_entry_hash(log);
+    log.signature = signing_key.map(|key| sign(&log.entry_hash, key));
+}
+
+/// Walks the chain in sequence order. Gaps in the sequence reveal deleted
+/// entries, hash mismatches reveal edited ones, and a broken link reveals
+/// entries that were removed and the chain re-stitched. Truncation of the
+/// newest entries can only be caught by comparing `head_hash` against a
+/// previously recorded value.
+///
+/// With a signing key, every entry from `signing_start` on must be signed;
+/// without a valid marker that is every entry.
+pub fn verify_chain(entries: &[AuditLog], signing_key: Option<&[u8]>, signing_start: Option<&str>) -> AuditVerification {
+    let mut issues = Vec::new();
+    let signed_from = match (signing_key, signing_start) {
+        (Some(key), Some(marker)) => parse_signing_start(marker, key).unwrap_or_else(|| {
+            issues.push(AuditIssue {
+                sequence: 0,
+                kind: "invalid_signature".to_string(),
+                message: "The record of when signing began has been altered".to_string(),
+            });
+            1
+        }),
+        _ => 1,
+    };
+    let mut expected_sequence = 1;
+    let mut expected_prev_hash = GENESIS_HASH.to_string();
+
+    for entry in entries {
+        if entry.sequence != expected_sequence {
+            issues.push(AuditIssue {
+                sequence: entry.sequence,
+                kind: "missing_entries".to_string(),
+                message: format!(
+                    "Expected sequence {} but found {}; entries may have been deleted",
+                    expected_sequence, entry.sequence
+                ),
+            });
+        }
+
+        if entry.prev_hash != expected_prev_hash {
+            issues.push(AuditIssue {
+                sequence: entry.sequence,
+                kind: "broken_link".to_string(),
+                message: "Previous hash does not match the preceding entry".to_string(),
+            });
+        }
+
+        if compute_entry_hash(entry) != entry.entry_hash {
+            issues.push(AuditIssue {
+                sequence: entry.sequence,
+                kind: "altered_entry".to_string(),
+                message: "Entry contents do not match its hash".to_string(),
+            });
+        }
+
+        if let Some(key) = signing_key {
+            match &entry.signature {
+                Some(signature) if sign(&entry.entry_hash, key) != *signature => issues.push(AuditIssue {
+                    sequence: entry.sequence,
+                    kind: "invalid_signature".to_string(),
+                    message: "Signature does not match the entry hash".to_string(),
+                }),
+                None if entry.sequence >= signed_from => issues.push(AuditIssue {
+                    sequence: entry.sequence,
+                    kind: "unsigned_entry".to_string(),
+                    message: "Entry has no signature although signing was enabled".to_string(),
+                }),
+                _ => {}
+            }
+        }
+
+        expected_sequence = entry.sequence + 1;
+        expected_prev_hash = entry.entry_hash.clone();
+    }
+
+    AuditVerification {
+        valid: issues.is_empty(),
+        entries_checked: entries.len() as i64,
+        head_sequence: entries.last().map(|e| e.sequence).unwrap_or(0),
+        head_hash: entries.last().map(|e| e.entry_hash.clone()).unwrap_or_else(|| GENESIS_HASH.to_string()),
+        signed: signing_key.is_some(),
+        issues,
+    }
+}
+
+/// CSV for spreadsheets and disclosure reports. Chain fields are included so
+/// rows can still be checked against the live log.
+pub fn to_csv(entries: &[AuditLog]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "sequence", "created_at", "user_id", "action", "resource_type",
+        "resource_id", "details", "entry_hash", "signature",
+    ])?;
+
+    for entry in entries {
+        writer.write_record([
+            entry.sequence.to_string(),
+            entry.created_at.to_rfc3339(),
+            entry.user_id.to_string(),
+            entry.action.clone(),
+            entry.resource_type.clone(),
+            entry.resource_id.map(|id| id.to_string()).unwrap_or_default(),
+            entry.details.clone().unwrap_or_default(),
+            entry.entry_hash.clone(),
+            entry.signature.clone().unwrap_or_default(),
+        ])?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Hash over the whole export envelope, so the filter, author and time are
+/// covered along with the entries.
+fn export_content_hash(export: &AuditExport) -> Result<String> {
+    let canonical = serde_json::json!([
+        export.generated_at.to_rfc3339(),
+        export.generated_by.to_string(),
+        export.query,
+        export.entries,
+    ]);
+
+    Ok(hex::encode(Sha256::digest(serde_json::to_string(&canonical)?.as_bytes())))
+}
+
+/// A JSON export whose contents are hashed and signed, for accounting of
+/// disclosures requests that must be verifiable after they leave the app.
+pub fn signed_export(
+    entries: Vec<AuditLog>,
+    query: AuditLogQuery,
+    generated_by: Uuid,
+    signing_key: &[u8],
+) -> Result<AuditExport> {
+    let mut export = AuditExport {
+        generated_at: Utc::now(),
+        generated_by,
+        query,
+        entries,
+        content_hash: String::new(),
+        signature: String::new(),
+    };
+    export.content_hash = export_content_hash(&export)?;
+    export.signature = sign(&export.content_hash, signing_key);
+
+    Ok(export)
+}
+
+/// Checks an export against its hash and signature.
+pub fn verify_export(export: &AuditExport, signing_key: &[u8]) -> Result<bool> {
+    let content_hash = export_content_hash(export)?;
+    Ok(content_hash == export.content_hash && sign(&content_hash, signing_key) == export.signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(len: usize, key: Option<&[u8]>) -> Vec<AuditLog> {
+        let mut entries: Vec<AuditLog> = Vec::new();
+        for i in 0..len {
+            let mut log = AuditLog::new(Uuid::new_v4(), "claim_viewed", "claim", Some(Uuid::new_v4()), Some(format!("entry {}", i)));
+            let previous = entries.last().map(|e| (e.sequence, e.entry_hash.clone()));
+            seal(&mut log, previous, key);
+            entries.push(log);
+        }
+        entries
+    }
+
+    #[test]
+    fn test_intact_chain_verifies() {
+        let key = b"audit-key".to_vec();
+        let entries = chain(5, Some(&key));
+        let result = verify_chain(&entries, Some(&key), None);
+        assert!(result.valid);
+        assert_eq!(result.head_sequence, 5);
+    }
+
+    #[test]
+    fn test_detects_altered_entry() {
+        let mut entries = chain(4, None);
+        entries[2].details = Some("rewritten".to_string());
+        let result = verify_chain(&entries, None, None);
+        assert!(!result.valid);
+        assert!(result.issues.iter().any(|i| i.kind == "altered_entry" && i.sequence == 3));
+    }
+
+    #[test]
+    fn test_detects_deleted_entry() {
+        let mut entries = chain(4, None);
+        entries.remove(1);
+        let result = verify_chain(&entries, None, None);
+        assert!(result.issues.iter().any(|i| i.kind == "missing_entries"));
+        assert!(result.issues.iter().any(|i| i.kind == "broken_link"));
+    }
+
+    #[test]
+    fn test_csv_escapes_details() {
+        let mut entries = chain(1, None);
+        entries[0].details = Some("{\"a\": \"b, c\"}".to_string());
+        let csv = to_csv(&entries).unwrap();
+        assert!(csv.lines().next().unwrap().starts_with("sequence,created_at"));
+        assert!(csv.contains("\"{\"\"a\"\": \"\"b, c\"\"}\""));
+    }
+
+    #[test]
+    fn test_detects_forged_signature() {
+        let entries = chain(2, Some(b"real-key"));
+        let result = verify_chain(&entries, Some(b"other-key"), None);
+        assert!(result.issues.iter().any(|i| i.kind == "invalid_signature"));
+    }
+
+    #[test]
+    fn test_stripped_signature_is_reported() {
+        let key = b"audit-key".to_vec();
+        let mut entries = chain(1, None);
+        let previous = entries.last().map(|e| (e.sequence, e.entry_hash.clone()));
+        let mut signed = AuditLog::new(Uuid::new_v4(), "claim_viewed", "claim", None, None);
+        seal(&mut signed, previous, Some(&key));
+        entries.push(signed);
+        let marker = signing_start_marker(2, &key);
+        assert!(verify_chain(&entries, Some(&key), Some(&marker)).valid);
+
+        // Dropping the signature and re-hashing still leaves the entry unsigned.
+        entries[1].signature = None;
+        let result = verify_chain(&entries, Some(&key), Some(&marker));
+        assert!(result.issues.iter().any(|i| i.kind == "unsigned_entry" && i.sequence == 2));
+        assert!(!result.issues.iter().any(|i| i.sequence == 1));
+
+        // Moving the marker past the stripped entry breaks the marker instead.
+        let forged = format!("3:{}", marker.split_once(':').unwrap().1);
+        let result = verify_chain(&entries, Some(&key), Some(&forged));
+        assert!(result.issues.iter().any(|i| i.kind == "invalid_signature" && i.sequence == 0));
+        assert!(result.issues.iter().any(|i| i.kind == "unsigned_entry" && i.sequence == 1));
+    }
+
+    #[test]
+    fn test_export_signature_covers_query_and_author() {
+        let key = b"audit-key";
+        let query = AuditLogQuery { action: Some("claim_viewed".to_string()), ..Default::default() };
+        let export = signed_export(chain(2, Some(key)), query, Uuid::new_v4(), key).unwrap();
+        assert!(verify_export(&export, key).unwrap());
+
+        let mut widened = export.clone();
+        widened.query.action = None;
+        assert!(!verify_export(&widened, key).unwrap());
+
+        let mut reattributed = export.clone();
+        reattributed.generated_by = Uuid::new_v4();
+        assert!(!verify_export(&reattributed, key).unwrap());
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use uuid::Uuid;
+use crate::types::{AuthSession, User, UserRole};
+
+/// Sessions are dropped after this many minutes without a command.
+pub const IDLE_TIMEOUT_MINUTES: i64 = 15;
+/// Sessions are dropped this many hours after login regardless of activity.
+pub const ABSOLUTE_TIMEOUT_HOURS: i64 = 8;
+
+/// Consecutive failures after which an account is locked.
+pub const MAX_FAILED_ATTEMPTS: i64 = 5;
+/// How long an account stays locked unless an administrator unlocks it first.
+pub const LOCKOUT_MINUTES: i64 = 15;
+/// Failures from one workstation, across all usernames, that block further
+/// attempts from it for the rest of the window.
+pub const WORKSTATION_MAX_FAILURES: i64 = 20;
+pub const WORKSTATION_WINDOW_MINUTES: i64 = 15;
+const MAX_BACKOFF_SECONDS: i64 = 30;
+/// Time allowed between a correct password and the second factor.
+pub const MFA_CHALLENGE_MINUTES: i64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    UploadClaims,
+    ProcessClaims,
+    ViewClaims,
+    EditClaims,
+    ExportClaims,
+    ViewAnalytics,
+    ViewUsers,
+    ManageUsers,
+    ViewAuditLogs,
+    ViewSettings,
+    ManageSettings,
+    ManageOwnMfa,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Invalid session")]
+    InvalidSession,
+    #[error("Session expired")]
+    SessionExpired,
+    #[error("Permission denied: {0:?}")]
+    PermissionDenied(Permission),
+    #[error("Multi-factor enrollment required")]
+    MfaEnrollmentRequired,
+    #[error("Invalid or expired MFA challenge")]
+    InvalidChallenge,
+}
+
+/// The permission matrix. This is the only place role capabilities are
+/// decided; the frontend merely hides what the backend would refuse anyway.
+pub fn role_permissions(role: &UserRole) -> &'static [Permission] {
+    use Permission::*;
+
+    match role {
+        UserRole::BillingCoder => &[
+            UploadClaims,
+            ProcessClaims,
+            ViewClaims,
+            EditClaims,
+            ManageOwnMfa,
+        ],
+        UserRole::Auditor => &[
+            ViewClaims,
+            ExportClaims,
+            ViewAnalytics,
+            ViewAuditLogs,
+            ManageOwnMfa,
+        ],
+        UserRole::BillingManager => &[
+            UploadClaims,
+            ProcessClaims,
+            ViewClaims,
+            EditClaims,
+            ExportClaims,
+            ViewAnalytics,
+            ViewUsers,
+            ViewAuditLogs,
+            ViewSettings,
+            ManageSettings,
+            ManageOwnMfa,
+        ],
+        UserRole::LocalAdmin => &[
+            UploadClaims,
+            ProcessClaims,
+            ViewClaims,
+            EditClaims,
+            ExportClaims,
+            ViewAnalytics,
+            ViewUsers,
+            ManageUsers,
+            ViewAuditLogs,
+            ViewSettings,
+            ManageSettings,
+            ManageOwnMfa,
+        ],
+    }
+}
+
+pub fn has_permission(role: &UserRole, permission: Permission) -> bool {
+    role_permissions(role).contains(&permission)
+}
+
+/// Failed-login bookkeeping kept on the user row.
+#[derive(Debug, Clone)]
+pub struct LoginState {
+    pub failed_attempts: i64,
+    pub last_failed_login: Option<DateTime<Utc>>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl LoginState {
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.is_some_and(|until| now < until)
+    }
+
+    /// A lock whose time has run out; the counters should be cleared before
+    /// the attempt is evaluated so the user starts over.
+    pub fn lock_expired(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.is_some_and(|until| now >= until)
+    }
+
+    /// Earliest time the next attempt will be considered, doubling with each
+    /// consecutive failure.
+    pub fn next_attempt_at(&self) -> Option<DateTime<Utc>> {
+        self.last_failed_login
+            .map(|last| last + backoff_delay(self.failed_attempts))
+    }
+}
+
+pub fn backoff_delay(failed_attempts: i64) -> Duration {
+    if failed_attempts <= 0 {
+        return Duration::zero();
+    }
+    let seconds = 1i64 << (failed_attempts - 1).min(5);
+    Duration::seconds(seconds.min(MAX_BACKOFF_SECONDS))
+}
+
+/// Identifies this machine in the login attempt log.
+pub fn workstation_id() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub token: String,
+    pub user_id: Uuid,
+    pub role: UserRole,
+    pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    /// Set when the role requires MFA and the user has yet to enroll; such a
+    /// session can do nothing but enroll.
+    pub mfa_enrollment_pending: bool,
+}
+
+struct MfaChallenge {
+    user: User,
+    expires_at: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        let idle = self.last_activity + Duration::minutes(IDLE_TIMEOUT_MINUTES);
+        let absolute = self.created_at + Duration::hours(ABSOLUTE_TIMEOUT_HOURS);
+        idle.min(absolute)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at()
+    }
+}
+
+/// In-memory session registry. Sessions never touch disk, so restarting the
+/// application logs everyone out.
+pub struct SessionStore {
+    sessions: HashMap<String, Session>,
+    mfa_challenges: HashMap<String, MfaChallenge>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+            mfa_challenges: HashMap::new(),
+        }
+    }
+
+    pub fn create(&mut self, user: &User, now: DateTime<Utc>) -> AuthSession {
+        self.insert(user, now, false)
+    }
+
+    pub fn create_pending_enrollment(&mut self, user: &User, now: DateTime<Utc>) -> AuthSession {
+        self.insert(user, now, true)
+    }
+
+    fn insert(&mut self, user: &User, now: DateTime<Utc>, mfa_enrollment_pending: bool) -> AuthSession {
+        self.purge_expired(now);
+
+        let session = Session {
+            token: generate_token(),
+            user_id: user.id,
+            role: user.role.clone(),
+            created_at: now,
+            last_activity: now,
+            mfa_enrollment_pending,
+        };
+        let auth_session = AuthSession {
+            token: session.token.clone(),
+            user: user.clone(),
+            expires_at: session.expires_at(),
+            idle_timeout_minutes: IDLE_TIMEOUT_MINUTES,
+        };
+
+        self.sessions.insert(session.token.clone(), session);
+        auth_session
+    }
+
+    /// Looks up a session and refreshes its idle timer. Expired sessions are
+    /// removed on the way out so a stale token cannot be retried.
+    pub fn touch(&mut self, token: &str, now: DateTime<Utc>) -> Result<Session, AuthError> {
+        let session = self.sessions.get_mut(token).ok_or(AuthError::InvalidSession)?;
+
+        if session.is_expired(now) {
+            self.sessions.remove(token);
+            return Err(AuthError::SessionExpired);
+        }
+
+        session.last_activity = now;
+        Ok(session.clone())
+    }
+
+    pub fn authorize(
+        &mut self,
+        token: &str,
+        permission: Permission,
+        now: DateTime<Utc>,
+    ) -> Result<Session, AuthError> {
+        let session = self.touch(token, now)?;
+        if session.mfa_enrollment_pending && permission != Permission::ManageOwnMfa {
+            return Err(AuthError::MfaEnrollmentRequired);
+        }
+        if !has_permission(&session.role, permission) {
+            return Err(AuthError::PermissionDenied(permission));
+        }
+        Ok(session)
+    }
+
+    pub fn complete_mfa_enrollment(&mut self, token: &str) {
+        if let Some(session) = self.sessions.get_mut(token) {
+            session.mfa_enrollment_pending = false;
+        }
+    }
+
+    pub fn create_mfa_challenge(&mut self, user: &User, now: DateTime<Utc>) -> String {
+        self.purge_expired(now);
+
+        let token = generate_token();
+        self.mfa_challenges.insert(token.clone(), MfaChallenge {
+            user: user.clone(),
+            expires_at: now + Duration::minutes(MFA_CHALLENGE_MINUTES),
+        });
+        token
+    }
+
+    /// The user a pending challenge belongs to. The challenge stays open
+    /// until `complete_mfa_challenge` so a mistyped code can be retried.
+    pub fn mfa_challenge_user(&mut self, token: &str, now: DateTime<Utc>) -> Result<User, AuthError> {
+        let challenge = self.mfa_challenges.get(token).ok_or(AuthError::InvalidChallenge)?;
+        if now >= challenge.expires_at {
+            self.mfa_challenges.remove(token);
+            return Err(AuthError::InvalidChallenge);
+        }
+        Ok(challenge.user.clone())
+    }
+
+    pub fn complete_mfa_challenge(&mut self, token: &str) {
+        self.mfa_challenges.remove(token);
+    }
+
+    pub fn revoke(&mut self, token: &str) {
+        self.sessions.remove(token);
+    }
+
+    pub fn revoke_user(&mut self, user_id: &Uuid) {
+        self.sessions.retain(|_, session| session.user_id != *user_id);
+        self.mfa_challenges.retain(|_, challenge| challenge.user.id != *user_id);
+    }
+
+    fn purge_expired(&mut self, now: DateTime<Utc>) {
+        self.sessions.retain(|_, session| !session.is_expired(now));
+        self.mfa_challenges.retain(|_, challenge| now < challenge.expires_at);
+    }
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: UserRole) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: "tester".to_string(),
+            email: "tester@example.com".to_string(),
+            role,
+            created_at: Utc::now(),
+            last_login: None,
+            locked_until: None,
+            mfa_enabled: false,
+            is_active: true,
+        }
+    }
+
+    #[test]
+    fn test_permission_matrix() {
+        assert!(has_permission(&UserRole::LocalAdmin, Permission::ManageUsers));
+        assert!(!has_permission(&UserRole::BillingManager, Permission::ManageUsers));
+        assert!(!has_permission(&UserRole::BillingCoder, Permission::ViewUsers));
+        assert!(!has_permission(&UserRole::Auditor, Permission::EditClaims));
+        assert!(has_permission(&UserRole::Auditor, Permission::ViewAuditLogs));
+    }
+
+    #[test]
+    fn test_session_idle_and_absolute_expiry() {
+        let mut store = SessionStore::new();
+        let start = Utc::now();
+        let session = store.create(&user(UserRole::BillingCoder), start);
+
+        let mut now = start;
+        for _ in 0..40 {
+            now += Duration::minutes(IDLE_TIMEOUT_MINUTES - 1);
+            if now >= start + Duration::hours(ABSOLUTE_TIMEOUT_HOURS) {
+                break;
+            }
+            assert!(store.touch(&session.token, now).is_ok());
+        }
+        assert!(matches!(store.touch(&session.token, now), Err(AuthError::SessionExpired)));
+        assert!(matches!(store.touch(&session.token, now), Err(AuthError::InvalidSession)));
+
+        let session = store.create(&user(UserRole::BillingCoder), start);
+        let idle = start + Duration::minutes(IDLE_TIMEOUT_MINUTES);
+        assert!(matches!(store.touch(&session.token, idle), Err(AuthError::SessionExpired)));
+    }
+
+    #[test]
+    fn test_pending_enrollment_session_is_restricted() {
+        let mut store = SessionStore::new();
+        let now = Utc::now();
+        let session = store.create_pending_enrollment(&user(UserRole::LocalAdmin), now);
+
+        assert!(matches!(
+            store.authorize(&session.token, Permission::ViewClaims, now),
+            Err(AuthError::MfaEnrollmentRequired)
+        ));
+        assert!(store.authorize(&session.token, Permission::ManageOwnMfa, now).is_ok());
+
+        store.complete_mfa_enrollment(&session.token);
+        assert!(store.authorize(&session.token, Permission::ViewClaims, now).is_ok());
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff_delay(0), Duration::zero());
+        assert_eq!(backoff_delay(1), Duration::seconds(1));
+        assert_eq!(backoff_delay(3), Duration::seconds(4));
+        assert_eq!(backoff_delay(50), Duration::seconds(MAX_BACKOFF_SECONDS));
+    }
+
+    #[test]
+    fn test_authorize_rejects_missing_permission() {
+        let mut store = SessionStore::new();
+        let now = Utc::now();
+        let session = store.create(&user(UserRole::BillingCoder), now);
+
+        assert!(store.authorize(&session.token, Permission::EditClaims, now).is_ok());
+        assert!(matches!(
+            store.authorize(&session.token, Permission::ManageUsers, now),
+            Err(AuthError::PermissionDenied(Permission::ManageUsers))
+        ));
+    }
+}
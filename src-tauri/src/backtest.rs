@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::rules;
+use crate::types::{Claim, QueueType, Severity, ValidationResult};
+
+/// A finding as compared between configurations; ids and versions differ on
+/// every run, so rule, field and message identify it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule_id: String,
+    pub severity: Severity,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl From<&ValidationResult> for Finding {
+    fn from(result: &ValidationResult) -> Self {
+        Self {
+            rule_id: result.rule_id.clone(),
+            severity: result.severity,
+            field: result.field.clone(),
+            message: result.message.clone(),
+        }
+    }
+}
+
+/// How one claim's outcome differs under the candidate configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimImpact {
+    pub claim_id: Uuid,
+    pub filename: String,
+    pub current_queue: QueueType,
+    pub candidate_queue: QueueType,
+    pub appeared: Vec<Finding>,
+    pub disappeared: Vec<Finding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueMove {
+    pub from: QueueType,
+    pub to: QueueType,
+    pub claims: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleImpact {
+    pub rule_id: String,
+    pub appeared: usize,
+    pub disappeared: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub claims_evaluated: usize,
+    pub queue_moves: Vec<QueueMove>,
+    pub rule_impact: Vec<RuleImpact>,
+    /// Only claims whose findings or queue changed.
+    pub claims: Vec<ClaimImpact>,
+}
+
+/// Findings in `from` without a counterpart in `to`, counting repeats so a
+/// service-line rule firing on one more line shows up.
+fn unmatched(from: &[Finding], to: &[Finding]) -> Vec<Finding> {
+    let mut remaining: Vec<&Finding> = to.iter().collect();
+    from.iter()
+        .filter(|finding| match remaining.iter().position(|other| other == finding) {
+            Some(index) => {
+                remaining.swap_remove(index);
+                false
+            }
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Compares a claim's findings under the current and candidate
+/// configurations, or `None` when nothing changes.
+pub fn compare_claim(claim: &Claim, current: &[ValidationResult], candidate: &[ValidationResult]) -> Option<ClaimImpact> {
+    let current_findings: Vec<Finding> = current.iter().map(Finding::from).collect();
+    let candidate_findings: Vec<Finding> = candidate.iter().map(Finding::from).collect();
+
+    let impact = ClaimImpact {
+        claim_id: claim.id,
+        filename: claim.filename.clone(),
+        current_queue: rules::queue_for(current),
+        candidate_queue: rules::queue_for(candidate),
+        appeared: unmatched(&candidate_findings, &current_findings),
+        disappeared: unmatched(&current_findings, &candidate_findings),
+    };
+
+    let changed = impact.current_queue != impact.candidate_queue
+        || !impact.appeared.is_empty()
+        || !impact.disappeared.is_empty();
+    changed.then_some(impact)
+}
+
+pub fn summarize(from: NaiveDate, to: NaiveDate, claims_evaluated: usize, claims: Vec<ClaimImpact>) -> BacktestReport {
+    let mut queue_moves: Vec<QueueMove> = Vec::new();
+    let mut rule_impact: BTreeMap<String, RuleImpact> = BTreeMap::new();
+
+    for claim in &claims {
+        if claim.current_queue != claim.candidate_queue {
+            match queue_moves.iter_mut().find(|m| m.from == claim.current_queue && m.to == claim.candidate_queue) {
+                Some(queue_move) => queue_move.claims += 1,
+                None => queue_moves.push(QueueMove {
+                    from: claim.current_queue.clone(),
+                    to: claim.candidate_queue.clone(),
+                    claims: 1,
+                }),
+            }
+        }
+
+        let changes = claim.appeared.iter().map(|finding| (finding, true))
+            .chain(claim.disappeared.iter().map(|finding| (finding, false)));
+        for (finding, appeared) in changes {
+            let impact = rule_impact.entry(finding.rule_id.clone()).or_insert_with(|| RuleImpact {
+                rule_id: finding.rule_id.clone(),
+                appeared: 0,
+                disappeared: 0,
+            });
+            if appeared {
+                impact.appeared += 1;
+            } else {
+                impact.disappeared += 1;
+            }
+        }
+    }
+
+    BacktestReport {
+        from,
+        to,
+        claims_evaluated,
+        queue_moves,
+        rule_impact: rule_impact.into_values().collect(),
+        claims,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::rule_tests;
+    use crate::types::ExtractedData;
+
+    fn claim() -> Claim {
+        rule_tests::fixture_claim(&ExtractedData::default())
+    }
+
+    fn finding(rule_id: &str, severity: Severity, message: &str) -> ValidationResult {
+        ValidationResult::new(rule_id, rule_id, severity, message.to_string())
+    }
+
+    #[test]
+    fn test_compare_reports_new_and_resolved_findings() {
+        let claim = claim();
+        let current = vec![
+            finding("units_over_two", Severity::Info, "Line 1"),
+            finding("units_over_two", Severity::Info, "Line 1"),
+        ];
+        let candidate = vec![
+            finding("units_over_two", Severity::Info, "Line 1"),
+            finding("bcbs_prior_auth", Severity::Critical, "Prior authorization required"),
+        ];
+
+        let impact = compare_claim(&claim, &current, &candidate).unwrap();
+        assert_eq!(impact.current_queue, QueueType::ApprovedClaims);
+        assert_eq!(impact.candidate_queue, QueueType::CriticalErrors);
+        assert_eq!(impact.appeared.len(), 1);
+        assert_eq!(impact.disappeared.len(), 1);
+        assert!(compare_claim(&claim, &current, &current).is_none());
+
+        let report = summarize(Utc::now().date_naive(), Utc::now().date_naive(), 2, vec![impact]);
+        assert_eq!(report.queue_moves.len(), 1);
+        assert_eq!(report.queue_moves[0].claims, 1);
+        let rules: Vec<(&str, usize, usize)> = report.rule_impact.iter()
+            .map(|r| (r.rule_id.as_str(), r.appeared, r.disappeared))
+            .collect();
+        assert_eq!(rules, [("bcbs_prior_auth", 1, 0), ("units_over_two", 0, 1)]);
+    }
+}
@@ -1,19 +1,60 @@
 use tauri::State;
 use uuid::Uuid;
-use chrono::Utc;
-use std::collections::HashMap;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use crate::types::*;
+use crate::AppState;
+use crate::audit;
+use crate::backtest::{self, BacktestReport};
+use crate::coverage;
+use crate::auth::{self, Permission, Session, SessionStore};
 use crate::database::Database;
+use crate::declarative_rules::{self, CompiledRule, RuleConfigError};
+use crate::demographics;
 use crate::ocr::OcrProcessor;
 use crate::parser::ClaimParser;
-use crate::rules::RulesEngine;
+use crate::procedure_codes;
+use crate::reference_data::ReferenceData;
+use crate::rules::{self, RulesEngine};
 use crate::encryption::EncryptionService;
+use crate::fee_schedule;
+use crate::fixes;
+use crate::icd10;
+use crate::key_store;
+use crate::mfa;
+use crate::modifiers;
+use crate::mue;
+use crate::ncci;
+use crate::payers;
+use crate::rule_packs::{self, ResolvedPack};
+use crate::rule_tests;
+
+const MFA_POLICY_KEY: &str = "mfa_policy";
+const SETTINGS_KEY: &str = "settings";
+const AUDIT_PAGE_SIZE: i64 = 100;
+const MAX_AUDIT_PAGE_SIZE: i64 = 1000;
+
+/// Validates the caller's session and checks the role permission matrix.
+/// Every command except login goes through this before touching state.
+fn authorize(
+    state: &AppState,
+    session_token: &str,
+    permission: Permission,
+) -> Result<Session, String> {
+    state.sessions.lock().unwrap()
+        .authorize(session_token, permission, Utc::now())
+        .map_err(|e| e.to_string())
+}
 
 #[tauri::command]
 pub async fn upload_files(
+    session_token: String,
     file_paths: Vec<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<UploadProgress>, String> {
+    let session = authorize(&state, &session_token, Permission::UploadClaims)?;
+
     let mut results = Vec::new();
     
     for file_path in file_paths {
@@ -29,19 +70,7 @@ pub async fn upload_files(
             filename: filename.clone(),
             file_path: file_path.clone(),
             status: ClaimStatus::Uploaded,
-            extracted_data: ExtractedData {
-                payer: None,
-                patient_name: None,
-                patient_id: None,
-                cpt_codes: vec![],
-                modifiers: vec![],
-                charges: vec![],
-                dates: vec![],
-                provider_name: None,
-                provider_npi: None,
-                diagnosis_codes: vec![],
-                raw_text: String::new(),
-            },
+            extracted_data: ExtractedData::default(),
             validation_results: vec![],
             queue: QueueType::CriticalErrors, // Default, will be updated after processing
             assigned_to: None,
@@ -50,7 +79,8 @@ pub async fn upload_files(
             comments: vec![],
         };
 
-        if let Err(e) = state.db.lock().unwrap().create_claim(&claim).await {
+        let db = state.db.lock().unwrap();
+        if let Err(e) = db.create_claim(&claim).await {
             results.push(UploadProgress {
                 file_id,
                 filename,
@@ -59,12 +89,16 @@ pub async fn upload_files(
                 error: Some(e.to_string()),
             });
         } else {
+            // The claim is saved either way, so an audit failure is reported
+            // against this file instead of abandoning the rest of the batch.
+            let details = serde_json::json!({ "filename": filename });
+            let audited = log_audit(&db, &session, "claim_uploaded", "claim", Some(file_id), Some(details.to_string())).await;
             results.push(UploadProgress {
                 file_id,
                 filename,
                 status: "uploaded".to_string(),
                 progress: 100.0,
-                error: None,
+                error: audited.err().map(|e| format!("Uploaded, but the audit entry could not be written: {}", e)),
             });
         }
     }
@@ -74,9 +108,12 @@ pub async fn upload_files(
 
 #[tauri::command]
 pub async fn start_ocr(
+    session_token: String,
     claim_id: String,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
+    let session = authorize(&state, &session_token, Permission::ProcessClaims)?;
+
     let claim_id = Uuid::parse_str(&claim_id)
         .map_err(|e| e.to_string())?;
 
@@ -112,15 +149,19 @@ pub async fn start_ocr(
 
     db.update_claim(&updated_claim).await
         .map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "claim_ocr_processed", "claim", Some(claim_id), None).await?;
 
     Ok("OCR completed successfully".to_string())
 }
 
 #[tauri::command]
 pub async fn run_rules(
+    session_token: String,
     claim_id: String,
     state: State<'_, AppState>,
 ) -> Result<Vec<ValidationResult>, String> {
+    let session = authorize(&state, &session_token, Permission::ProcessClaims)?;
+
     let claim_id = Uuid::parse_str(&claim_id)
         .map_err(|e| e.to_string())?;
 
@@ -129,19 +170,9 @@ pub async fn run_rules(
         .map_err(|e| e.to_string())?
         .ok_or("Claim not found")?;
 
-    let rules_engine = RulesEngine::new();
-    let validation_results = rules_engine.validate_claim(&claim)
-        .await
-        .map_err(|e| e.to_string())?;
+    let (validation_results, pack) = evaluate_claim(&db, &claim).await?;
 
-    // Determine queue based on validation results
-    let queue = if validation_results.iter().any(|r| matches!(r.severity, Severity::Critical)) {
-        QueueType::CriticalErrors
-    } else if validation_results.iter().any(|r| matches!(r.severity, Severity::Warning)) {
-        QueueType::WarningsOnly
-    } else {
-        QueueType::ApprovedClaims
-    };
+    let queue = rules::queue_for(&validation_results);
 
     // Update claim with validation results
     let mut updated_claim = claim.clone();
@@ -151,15 +182,24 @@ pub async fn run_rules(
 
     db.update_claim(&updated_claim).await
         .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({
+        "queue": updated_claim.queue,
+        "findings": validation_results.len(),
+        "rule_pack": pack.as_ref().and_then(|pack| pack.chain.last()),
+    });
+    log_audit(&db, &session, "claim_rules_run", "claim", Some(claim_id), Some(details.to_string())).await?;
 
     Ok(validation_results)
 }
 
 #[tauri::command]
 pub async fn get_claims(
+    session_token: String,
     queue: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<Claim>, String> {
+    let session = authorize(&state, &session_token, Permission::ViewClaims)?;
+
     let db = state.db.lock().unwrap();
     let queue_type = if let Some(queue_str) = queue {
         serde_json::from_str(&queue_str).ok()
@@ -167,37 +207,55 @@ pub async fn get_claims(
         None
     };
 
-    db.get_claims(queue_type).await
-        .map_err(|e| e.to_string())
+    let claims = db.get_claims(queue_type).await
+        .map_err(|e| e.to_string())?;
+    log_claim_access(&db, &session, "claim_listed", &claims).await?;
+
+    Ok(claims)
 }
 
 #[tauri::command]
 pub async fn get_claim_by_id(
+    session_token: String,
     claim_id: String,
     state: State<'_, AppState>,
 ) -> Result<Option<Claim>, String> {
+    let session = authorize(&state, &session_token, Permission::ViewClaims)?;
+
     let claim_id = Uuid::parse_str(&claim_id)
         .map_err(|e| e.to_string())?;
 
     let db = state.db.lock().unwrap();
-    db.get_claim(&claim_id).await
-        .map_err(|e| e.to_string())
+    let claim = db.get_claim(&claim_id).await
+        .map_err(|e| e.to_string())?;
+    if let Some(claim) = &claim {
+        log_claim_access(&db, &session, "claim_viewed", std::slice::from_ref(claim)).await?;
+    }
+
+    Ok(claim)
 }
 
 #[tauri::command]
 pub async fn update_claim(
+    session_token: String,
     claim: Claim,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
+    let session = authorize(&state, &session_token, Permission::EditClaims)?;
+
     let mut db = state.db.lock().unwrap();
     db.update_claim(&claim).await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "claim_updated", "claim", Some(claim.id), None).await
 }
 
 #[tauri::command]
 pub async fn get_queues(
+    session_token: String,
     state: State<'_, AppState>,
 ) -> Result<HashMap<String, Vec<Claim>>, String> {
+    let session = authorize(&state, &session_token, Permission::ViewClaims)?;
+
     let db = state.db.lock().unwrap();
     
     let mut queues = HashMap::new();
@@ -214,13 +272,20 @@ pub async fn get_queues(
         .map_err(|e| e.to_string())?;
     queues.insert("approved".to_string(), approved);
 
+    for claims in queues.values() {
+        log_claim_access(&db, &session, "claim_listed", claims).await?;
+    }
+
     Ok(queues)
 }
 
 #[tauri::command]
 pub async fn get_analytics(
+    session_token: String,
     state: State<'_, AppState>,
 ) -> Result<Analytics, String> {
+    authorize(&state, &session_token, Permission::ViewAnalytics)?;
+
     let db = state.db.lock().unwrap();
     db.get_analytics().await
         .map_err(|e| e.to_string())
@@ -228,12 +293,26 @@ pub async fn get_analytics(
 
 #[tauri::command]
 pub async fn create_user(
+    session_token: Option<String>,
     username: String,
     email: String,
     password: String,
     role: String,
     state: State<'_, AppState>,
 ) -> Result<User, String> {
+    let db = state.db.lock().unwrap();
+
+    // The very first account is created before anyone can log in; after that
+    // only administrators may add users.
+    let user_count = db.count_users().await
+        .map_err(|e| e.to_string())?;
+    let creator = if user_count > 0 {
+        let session_token = session_token.ok_or("Session token required")?;
+        Some(authorize(&state, &session_token, Permission::ManageUsers)?)
+    } else {
+        None
+    };
+
     let user_id = Uuid::new_v4();
     let role_enum: UserRole = serde_json::from_str(&format!("\"{}\"", role))
         .map_err(|e| e.to_string())?;
@@ -245,15 +324,28 @@ pub async fn create_user(
         role: role_enum,
         created_at: Utc::now(),
         last_login: None,
+        locked_until: None,
+        mfa_enabled: false,
+        is_active: true,
     };
 
     // Hash password (in production, use proper password hashing)
     let password_hash = format!("hashed_{}", password);
 
-    let db = state.db.lock().unwrap();
     db.create_user(&user, &password_hash).await
         .map_err(|e| e.to_string())?;
 
+    let details = serde_json::json!({ "username": user.username, "role": user.role });
+    db.log_audit_event(&AuditLog::new(
+        creator.map_or(user.id, |session| session.user_id),
+        "user_created",
+        "user",
+        Some(user.id),
+        Some(details.to_string()),
+    ))
+    .await
+    .map_err(|e| e.to_string())?;
+
     Ok(user)
 }
 
@@ -262,88 +354,1791 @@ pub async fn authenticate_user(
     username: String,
     password: String,
     state: State<'_, AppState>,
-) -> Result<Option<User>, String> {
+) -> Result<Option<LoginResult>, String> {
+    let now = Utc::now();
+    let workstation = auth::workstation_id();
     let db = state.db.lock().unwrap();
+
+    let workstation_failures = db.count_workstation_failures(
+        &workstation,
+        now - Duration::minutes(auth::WORKSTATION_WINDOW_MINUTES),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    if workstation_failures >= auth::WORKSTATION_MAX_FAILURES {
+        log_login_event(&db, None, "login_blocked", &username, &workstation, Some("workstation limit reached")).await?;
+        return Err("Too many failed login attempts from this workstation. Try again later.".to_string());
+    }
+
     let result = db.get_user_by_username(&username).await
         .map_err(|e| e.to_string())?;
 
-    if let Some((user, password_hash)) = result {
-        // Simple password check (in production, use proper password verification)
-        if password_hash == format!("hashed_{}", password) {
-            Ok(Some(user))
-        } else {
-            Ok(None)
+    let (mut user, password_hash) = match result {
+        Some(found) => found,
+        None => {
+            db.record_login_attempt(&username, None, &workstation, false, now).await
+                .map_err(|e| e.to_string())?;
+            log_login_event(&db, None, "login_failure", &username, &workstation, Some("unknown username")).await?;
+            return Ok(None);
+        }
+    };
+
+    if !user.is_active {
+        db.record_login_attempt(&username, Some(&user.id), &workstation, false, now).await
+            .map_err(|e| e.to_string())?;
+        log_login_event(&db, Some(&user.id), "login_failure", &username, &workstation, Some("account deactivated")).await?;
+        return Ok(None);
+    }
+
+    check_login_allowed(&db, &user, &workstation, now).await?;
+
+    // Simple password check (in production, use proper password verification)
+    if password_hash != format!("hashed_{}", password) {
+        register_login_failure(&db, &user, &workstation, now, "incorrect password").await?;
+        return Ok(None);
+    }
+
+    let mfa_credentials = db.get_mfa_credentials(&user.id).await
+        .map_err(|e| e.to_string())?;
+    if mfa_credentials.enabled {
+        // Failure counters are left alone until the second factor passes, so
+        // re-entering the password does not buy more guesses at the code.
+        log_login_event(&db, Some(&user.id), "login_mfa_challenge", &username, &workstation, None).await?;
+        let challenge_token = state.sessions.lock().unwrap().create_mfa_challenge(&user, now);
+        return Ok(Some(LoginResult::MfaRequired { challenge_token }));
+    }
+
+    let policy = load_mfa_policy(&db).await?;
+    let enrollment_required = policy.required_roles.contains(&user.role);
+    let session = finish_login(&db, &state, &mut user, &workstation, now, enrollment_required).await?;
+
+    if enrollment_required {
+        Ok(Some(LoginResult::MfaEnrollmentRequired(session)))
+    } else {
+        Ok(Some(LoginResult::Authenticated(session)))
+    }
+}
+
+#[tauri::command]
+pub async fn verify_mfa(
+    challenge_token: String,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<AuthSession, String> {
+    let now = Utc::now();
+    let workstation = auth::workstation_id();
+    let db = state.db.lock().unwrap();
+
+    let mut user = state.sessions.lock().unwrap()
+        .mfa_challenge_user(&challenge_token, now)
+        .map_err(|e| e.to_string())?;
+
+    if let Err(e) = check_login_allowed(&db, &user, &workstation, now).await {
+        state.sessions.lock().unwrap().complete_mfa_challenge(&challenge_token);
+        return Err(e);
+    }
+
+    let credentials = db.get_mfa_credentials(&user.id).await
+        .map_err(|e| e.to_string())?;
+    let secret = credentials.secret.ok_or("MFA is not configured for this user")?;
+
+    let verified = match mfa::verify_totp(&secret, &code, now).map_err(|e| e.to_string())? {
+        Some(step) if credentials.last_used_step.is_none_or(|last| step > last) => {
+            db.set_mfa_last_step(&user.id, step).await
+                .map_err(|e| e.to_string())?;
+            true
+        }
+        // A code from an already used time step is a replay.
+        Some(_) => false,
+        None => {
+            let used = db.consume_recovery_code(&user.id, &mfa::hash_recovery_code(&code), now).await
+                .map_err(|e| e.to_string())?;
+            if used {
+                log_login_event(&db, Some(&user.id), "mfa_recovery_code_used", &user.username, &workstation, None).await?;
+            }
+            used
+        }
+    };
+
+    if !verified {
+        register_login_failure(&db, &user, &workstation, now, "invalid MFA code").await?;
+        return Err("Invalid verification code".to_string());
+    }
+
+    state.sessions.lock().unwrap().complete_mfa_challenge(&challenge_token);
+    finish_login(&db, &state, &mut user, &workstation, now, false).await
+}
+
+/// Refuses the attempt while the account is locked or inside its backoff
+/// window. An expired lock is cleared first so the user starts over.
+async fn check_login_allowed(
+    db: &Database,
+    user: &User,
+    workstation: &str,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let mut login_state = db.get_login_state(&user.id).await
+        .map_err(|e| e.to_string())?;
+    if login_state.lock_expired(now) {
+        db.reset_login_failures(&user.id).await
+            .map_err(|e| e.to_string())?;
+        login_state = db.get_login_state(&user.id).await
+            .map_err(|e| e.to_string())?;
+    }
+
+    if login_state.is_locked(now) {
+        db.record_login_attempt(&user.username, Some(&user.id), workstation, false, now).await
+            .map_err(|e| e.to_string())?;
+        log_login_event(db, Some(&user.id), "login_blocked", &user.username, workstation, Some("account locked")).await?;
+        return Err("Account is locked. Contact an administrator or try again later.".to_string());
+    }
+
+    if let Some(next_attempt_at) = login_state.next_attempt_at() {
+        if now < next_attempt_at {
+            log_login_event(db, Some(&user.id), "login_throttled", &user.username, workstation, None).await?;
+            return Err(format!(
+                "Too many failed attempts. Try again in {} seconds.",
+                (next_attempt_at - now).num_seconds().max(1)
+            ));
         }
+    }
+
+    Ok(())
+}
+
+async fn register_login_failure(
+    db: &Database,
+    user: &User,
+    workstation: &str,
+    now: DateTime<Utc>,
+    reason: &str,
+) -> Result<(), String> {
+    db.record_login_attempt(&user.username, Some(&user.id), workstation, false, now).await
+        .map_err(|e| e.to_string())?;
+    let failed_attempts = db.record_login_failure(&user.id, now).await
+        .map_err(|e| e.to_string())?;
+    log_login_event(db, Some(&user.id), "login_failure", &user.username, workstation, Some(reason)).await?;
+
+    if failed_attempts >= auth::MAX_FAILED_ATTEMPTS {
+        db.lock_user(&user.id, now + Duration::minutes(auth::LOCKOUT_MINUTES)).await
+            .map_err(|e| e.to_string())?;
+        log_login_event(db, Some(&user.id), "account_locked", &user.username, workstation, None).await?;
+    }
+
+    Ok(())
+}
+
+async fn finish_login(
+    db: &Database,
+    state: &AppState,
+    user: &mut User,
+    workstation: &str,
+    now: DateTime<Utc>,
+    mfa_enrollment_pending: bool,
+) -> Result<AuthSession, String> {
+    db.reset_login_failures(&user.id).await
+        .map_err(|e| e.to_string())?;
+    db.update_last_login(&user.id, now).await
+        .map_err(|e| e.to_string())?;
+    db.record_login_attempt(&user.username, Some(&user.id), workstation, true, now).await
+        .map_err(|e| e.to_string())?;
+    log_login_event(db, Some(&user.id), "login_success", &user.username, workstation, None).await?;
+    user.last_login = Some(now);
+
+    let mut sessions = state.sessions.lock().unwrap();
+    if mfa_enrollment_pending {
+        Ok(sessions.create_pending_enrollment(user, now))
     } else {
-        Ok(None)
+        Ok(sessions.create(user, now))
+    }
+}
+
+async fn load_mfa_policy(db: &Database) -> Result<MfaPolicy, String> {
+    let value = db.get_setting(MFA_POLICY_KEY).await
+        .map_err(|e| e.to_string())?;
+    match value {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(MfaPolicy::default()),
+    }
+}
+
+#[tauri::command]
+pub async fn begin_mfa_enrollment(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<MfaEnrollment, String> {
+    let session = authorize(&state, &session_token, Permission::ManageOwnMfa)?;
+
+    let db = state.db.lock().unwrap();
+    let user = db.get_user(&session.user_id).await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found")?;
+    if user.mfa_enabled {
+        return Err("MFA is already enabled. Disable it before enrolling a new device.".to_string());
+    }
+
+    let secret = mfa::generate_secret();
+    db.set_pending_mfa_secret(&user.id, &secret).await
+        .map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "mfa_enrollment_started", "user", Some(user.id), None).await?;
+
+    Ok(MfaEnrollment {
+        provisioning_uri: mfa::provisioning_uri(&secret, &user.username),
+        secret,
+    })
+}
+
+/// Activates MFA once the user proves their authenticator produces valid
+/// codes. The returned recovery codes are shown once and never stored in
+/// plain text.
+#[tauri::command]
+pub async fn confirm_mfa_enrollment(
+    session_token: String,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let session = authorize(&state, &session_token, Permission::ManageOwnMfa)?;
+
+    let db = state.db.lock().unwrap();
+    let credentials = db.get_mfa_credentials(&session.user_id).await
+        .map_err(|e| e.to_string())?;
+    if credentials.enabled {
+        return Err("MFA is already enabled".to_string());
+    }
+    let secret = credentials.secret.ok_or("Start MFA enrollment first")?;
+
+    let step = mfa::verify_totp(&secret, &code, Utc::now())
+        .map_err(|e| e.to_string())?
+        .ok_or("Invalid verification code")?;
+
+    let recovery_codes = mfa::generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes.iter().map(|c| mfa::hash_recovery_code(c)).collect();
+    db.enable_mfa(&session.user_id, step, &hashes).await
+        .map_err(|e| e.to_string())?;
+
+    state.sessions.lock().unwrap().complete_mfa_enrollment(&session_token);
+    log_audit(&db, &session, "mfa_enabled", "user", Some(session.user_id), None).await?;
+
+    Ok(recovery_codes)
+}
+
+#[tauri::command]
+pub async fn regenerate_recovery_codes(
+    session_token: String,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let session = authorize(&state, &session_token, Permission::ManageOwnMfa)?;
+
+    let db = state.db.lock().unwrap();
+    let credentials = db.get_mfa_credentials(&session.user_id).await
+        .map_err(|e| e.to_string())?;
+    let secret = match (credentials.enabled, credentials.secret) {
+        (true, Some(secret)) => secret,
+        _ => return Err("MFA is not enabled".to_string()),
+    };
+
+    let step = match mfa::verify_totp(&secret, &code, Utc::now()).map_err(|e| e.to_string())? {
+        Some(step) if credentials.last_used_step.is_none_or(|last| step > last) => step,
+        _ => return Err("Invalid verification code".to_string()),
+    };
+
+    let recovery_codes = mfa::generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes.iter().map(|c| mfa::hash_recovery_code(c)).collect();
+    db.enable_mfa(&session.user_id, step, &hashes).await
+        .map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "mfa_recovery_codes_regenerated", "user", Some(session.user_id), None).await?;
+
+    Ok(recovery_codes)
+}
+
+/// Turns MFA off for the caller, or, for administrators, resets another
+/// user's MFA after a lost device.
+#[tauri::command]
+pub async fn disable_mfa(
+    session_token: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let target_id = user_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let session = match target_id {
+        Some(_) => authorize(&state, &session_token, Permission::ManageUsers)?,
+        None => authorize(&state, &session_token, Permission::ManageOwnMfa)?,
+    };
+    let target_id = target_id.unwrap_or(session.user_id);
+
+    let db = state.db.lock().unwrap();
+    if target_id == session.user_id {
+        let policy = load_mfa_policy(&db).await?;
+        if policy.required_roles.contains(&session.role) {
+            return Err("MFA is required for your role and cannot be disabled".to_string());
+        }
+    }
+
+    let found = db.disable_mfa(&target_id).await
+        .map_err(|e| e.to_string())?;
+    if !found {
+        return Err("User not found".to_string());
     }
+    log_audit(&db, &session, "mfa_disabled", "user", Some(target_id), None).await
+}
+
+#[tauri::command]
+pub async fn get_mfa_policy(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<MfaPolicy, String> {
+    authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    let db = state.db.lock().unwrap();
+    load_mfa_policy(&db).await
+}
+
+#[tauri::command]
+pub async fn update_mfa_policy(
+    session_token: String,
+    policy: MfaPolicy,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let session = authorize(&state, &session_token, Permission::ManageUsers)?;
+
+    let db = state.db.lock().unwrap();
+    let value = serde_json::to_string(&policy)
+        .map_err(|e| e.to_string())?;
+    db.set_setting(MFA_POLICY_KEY, &value).await
+        .map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "mfa_policy_updated", "settings", None, Some(value)).await
+}
+
+async fn log_audit(
+    db: &Database,
+    session: &Session,
+    action: &str,
+    resource_type: &str,
+    resource_id: Option<Uuid>,
+    details: Option<String>,
+) -> Result<(), String> {
+    db.log_audit_event(&AuditLog::new(session.user_id, action, resource_type, resource_id, details))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Records one entry per claim returned, so every disclosure of a patient's
+/// claim can be accounted for individually.
+async fn log_claim_access(
+    db: &Database,
+    session: &Session,
+    action: &str,
+    claims: &[Claim],
+) -> Result<(), String> {
+    let logs: Vec<AuditLog> = claims
+        .iter()
+        .map(|claim| AuditLog::new(session.user_id, action, "claim", Some(claim.id), None))
+        .collect();
+
+    db.log_audit_events(&logs).await
+        .map_err(|e| e.to_string())
+}
+
+/// Login events are audited even when the username is unknown; those are
+/// recorded against the nil user id.
+async fn log_login_event(
+    db: &Database,
+    user_id: Option<&Uuid>,
+    action: &str,
+    username: &str,
+    workstation: &str,
+    reason: Option<&str>,
+) -> Result<(), String> {
+    let details = serde_json::json!({
+        "username": username,
+        "workstation": workstation,
+        "reason": reason,
+    });
+
+    db.log_audit_event(&AuditLog::new(
+        user_id.copied().unwrap_or_else(Uuid::nil),
+        action,
+        "user",
+        user_id.copied(),
+        Some(details.to_string()),
+    ))
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn logout(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.sessions.lock().unwrap().revoke(&session_token);
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn get_users(
+    session_token: String,
     state: State<'_, AppState>,
 ) -> Result<Vec<User>, String> {
+    authorize(&state, &session_token, Permission::ViewUsers)?;
+
     let db = state.db.lock().unwrap();
     db.get_users().await
         .map_err(|e| e.to_string())
 }
 
+/// Updates a user's email and role, and reactivates deactivated accounts.
+/// Deactivation goes through `delete_user` so open claims get reassigned.
 #[tauri::command]
 pub async fn update_user(
+    session_token: String,
     user: User,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    // TODO: Implement user update
-    Ok(())
+    let session = authorize(&state, &session_token, Permission::ManageUsers)?;
+
+    let db = state.db.lock().unwrap();
+    let existing = db.get_user(&user.id).await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found")?;
+
+    if !user.is_active && existing.is_active {
+        return Err("Use delete_user to deactivate an account".to_string());
+    }
+
+    let demoting_admin = existing.is_active
+        && existing.role == UserRole::LocalAdmin
+        && user.role != UserRole::LocalAdmin;
+    if demoting_admin && db.count_active_admins().await.map_err(|e| e.to_string())? <= 1 {
+        return Err("Cannot change the role of the last active LocalAdmin".to_string());
+    }
+
+    db.update_user(&user).await
+        .map_err(|e| e.to_string())?;
+    if user.is_active && !existing.is_active {
+        db.reactivate_user(&user.id).await
+            .map_err(|e| e.to_string())?;
+        log_audit(&db, &session, "user_reactivated", "user", Some(user.id), None).await?;
+    }
+
+    // A role change takes effect immediately rather than at the next login.
+    if user.role != existing.role {
+        state.sessions.lock().unwrap().revoke_user(&user.id);
+    }
+
+    let details = serde_json::json!({
+        "email": { "from": existing.email, "to": user.email },
+        "role": { "from": existing.role, "to": user.role },
+    });
+    log_audit(&db, &session, "user_updated", "user", Some(user.id), Some(details.to_string())).await
+}
+
+/// Deactivates rather than deletes, so audit history and claim assignments
+/// keep pointing at a real user. Open claims move to `reassign_to`, or are
+/// left unassigned when none is given, and the user's sessions end. Returns
+/// how many claims moved.
+async fn deactivate_account(
+    db: &Database,
+    sessions: &Mutex<SessionStore>,
+    actor: &Session,
+    user_id: &Uuid,
+    reassign_to: Option<&Uuid>,
+) -> Result<u64, String> {
+    if *user_id == actor.user_id {
+        return Err("You cannot deactivate your own account".to_string());
+    }
+
+    let user = db.get_user(user_id).await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found")?;
+    if !user.is_active {
+        return Err("User is already deactivated".to_string());
+    }
+
+    if user.role == UserRole::LocalAdmin && db.count_active_admins().await.map_err(|e| e.to_string())? <= 1 {
+        return Err("Cannot deactivate the last active LocalAdmin".to_string());
+    }
+
+    if let Some(assignee_id) = reassign_to {
+        let assignee = db.get_user(assignee_id).await
+            .map_err(|e| e.to_string())?
+            .ok_or("Reassignment target not found")?;
+        if !assignee.is_active || assignee.id == *user_id {
+            return Err("Claims can only be reassigned to another active user".to_string());
+        }
+    }
+
+    let reassigned = db.deactivate_user(user_id, reassign_to, Utc::now()).await
+        .map_err(|e| e.to_string())?;
+    sessions.lock().unwrap().revoke_user(user_id);
+    Ok(reassigned)
 }
 
 #[tauri::command]
 pub async fn delete_user(
+    session_token: String,
     user_id: String,
+    reassign_to: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    // TODO: Implement user deletion
-    Ok(())
+    let session = authorize(&state, &session_token, Permission::ManageUsers)?;
+
+    let user_id = Uuid::parse_str(&user_id)
+        .map_err(|e| e.to_string())?;
+    let reassign_to = reassign_to
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    let reassigned = deactivate_account(&db, &state.sessions, &session, &user_id, reassign_to.as_ref()).await?;
+
+    let details = serde_json::json!({
+        "reassigned_to": reassign_to,
+        "reassigned_claims": reassigned,
+    });
+    log_audit(&db, &session, "user_deactivated", "user", Some(user_id), Some(details.to_string())).await
 }
 
 #[tauri::command]
-pub async fn get_audit_logs(
+pub async fn unlock_user(
+    session_token: String,
+    user_id: String,
     state: State<'_, AppState>,
-) -> Result<Vec<AuditLog>, String> {
-    // TODO: Implement audit log retrieval
-    Ok(vec![])
+) -> Result<(), String> {
+    let session = authorize(&state, &session_token, Permission::ManageUsers)?;
+
+    let user_id = Uuid::parse_str(&user_id)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    let found = db.reset_login_failures(&user_id).await
+        .map_err(|e| e.to_string())?;
+    if !found {
+        return Err("User not found".to_string());
+    }
+
+    log_audit(&db, &session, "account_unlocked", "user", Some(user_id), None).await
 }
 
+/// Lifts a workstation block by clearing the machine's failed logins in the
+/// current window. Defaults to the workstation the administrator is on.
 #[tauri::command]
-pub async fn export_claims(
-    claim_ids: Vec<String>,
-    format: String,
+pub async fn unlock_workstation(
+    session_token: String,
+    workstation: Option<String>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    // TODO: Implement claim export
-    Ok("Export completed".to_string())
+) -> Result<u64, String> {
+    let session = authorize(&state, &session_token, Permission::ManageUsers)?;
+
+    let workstation = workstation.unwrap_or_else(auth::workstation_id);
+    let since = Utc::now() - Duration::minutes(auth::WORKSTATION_WINDOW_MINUTES);
+
+    let db = state.db.lock().unwrap();
+    let cleared = db.clear_workstation_failures(&workstation, since).await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({ "workstation": workstation, "cleared_failures": cleared });
+    log_audit(&db, &session, "workstation_unlocked", "workstation", None, Some(details.to_string())).await?;
+
+    Ok(cleared)
 }
 
 #[tauri::command]
-pub async fn get_settings(
+pub async fn get_audit_logs(
+    session_token: String,
+    query: Option<AuditLogQuery>,
     state: State<'_, AppState>,
-) -> Result<Settings, String> {
-    Ok(Settings {
-        hipaa_mode: true,
-        ocr_provider: "tesseract".to_string(),
-        cloud_ocr_enabled: false,
-        llm_provider: None,
-        encryption_key: None,
-        rules_config: serde_json::json!({}),
-    })
-}
+) -> Result<AuditLogPage, String> {
+    let session = authorize(&state, &session_token, Permission::ViewAuditLogs)?;
 
-#[tauri::command]
-pub async fn update_settings(
+    let query = query.unwrap_or_default();
+    let limit = query.limit.unwrap_or(AUDIT_PAGE_SIZE).clamp(1, MAX_AUDIT_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let db = state.db.lock().unwrap();
+    let (entries, total) = db.query_audit_logs(&query, Some((limit, offset))).await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::to_string(&query)
+        .map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "audit_log_queried", "audit_log", None, Some(details)).await?;
+
+    Ok(AuditLogPage {
+        entries,
+        total,
+        limit,
+        offset,
+    })
+}
+
+/// Writes every entry matching `query` to `path`, as CSV or as a signed
+/// JSON document. Paging fields in the query are ignored.
+#[tauri::command]
+pub async fn export_audit_logs(
+    session_token: String,
+    query: AuditLogQuery,
+    format: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<i64, String> {
+    let session = authorize(&state, &session_token, Permission::ViewAuditLogs)?;
+
+    let db = state.db.lock().unwrap();
+    let (entries, total) = db.query_audit_logs(&query, None).await
+        .map_err(|e| e.to_string())?;
+
+    let contents = match format.as_str() {
+        "csv" => audit::to_csv(&entries).map_err(|e| e.to_string())?,
+        "json" => {
+            let key = db.audit_signing_key()
+                .ok_or("Signed export requires an audit signing key to be configured")?;
+            let export = audit::signed_export(entries, query.clone(), session.user_id, key)
+                .map_err(|e| e.to_string())?;
+            serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?
+        }
+        other => return Err(format!("Unsupported export format: {}", other)),
+    };
+
+    tokio::fs::write(&path, contents).await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({
+        "format": format,
+        "path": path,
+        "entries": total,
+        "query": query,
+    });
+    log_audit(&db, &session, "audit_log_exported", "audit_log", None, Some(details.to_string())).await?;
+
+    Ok(total)
+}
+
+/// Checks a signed JSON export against the signing key, so an export handed
+/// back later can be shown to be unchanged.
+#[tauri::command]
+pub async fn verify_audit_export(
+    session_token: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let session = authorize(&state, &session_token, Permission::ViewAuditLogs)?;
+
+    let contents = tokio::fs::read_to_string(&path).await
+        .map_err(|e| e.to_string())?;
+    let export: AuditExport = serde_json::from_str(&contents)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    let key = db.audit_signing_key()
+        .ok_or("Verifying an export requires the audit signing key to be configured")?;
+    let valid = audit::verify_export(&export, key)
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({ "path": path, "valid": valid });
+    log_audit(&db, &session, "audit_export_verified", "audit_log", None, Some(details.to_string())).await?;
+
+    Ok(valid)
+}
+
+/// Re-walks the whole audit chain and reports any deleted, altered or
+/// re-signed entries.
+#[tauri::command]
+pub async fn verify_audit_log(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<AuditVerification, String> {
+    let session = authorize(&state, &session_token, Permission::ViewAuditLogs)?;
+
+    let db = state.db.lock().unwrap();
+    let verification = db.verify_audit_chain().await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({
+        "valid": verification.valid,
+        "head_sequence": verification.head_sequence,
+        "head_hash": verification.head_hash,
+    });
+    log_audit(&db, &session, "audit_log_verified", "audit_log", None, Some(details.to_string())).await?;
+
+    Ok(verification)
+}
+
+#[tauri::command]
+pub async fn export_claims(
+    session_token: String,
+    claim_ids: Vec<String>,
+    format: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    authorize(&state, &session_token, Permission::ExportClaims)?;
+
+    // TODO: Implement claim export
+    Ok("Export completed".to_string())
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<Settings, String> {
+    authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    let db = state.db.lock().unwrap();
+    let mut settings = load_settings(&db).await?;
+    settings.encryption_key = key_store::has_key().then(|| key_store::REDACTED.to_string());
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    session_token: String,
     settings: Settings,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    // TODO: Implement settings update
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let errors = declarative_rules::validate_rules_config(&settings.rules_config);
+    if !errors.is_empty() {
+        return Err(format!("Invalid rules configuration:\n{}", declarative_rules::format_errors(&errors)));
+    }
+
+    // The key goes to its own store; the redacted placeholder sent back from
+    // get_settings leaves it as it was.
+    let mut settings = settings;
+    let new_key = settings.encryption_key.take()
+        .filter(|key| key != key_store::REDACTED && !key.trim().is_empty());
+    if let Some(key) = &new_key {
+        key_store::save_key(key).map_err(|e| e.to_string())?;
+    }
+
+    let db = state.db.lock().unwrap();
+    let configuration = db.get_rule_configuration().await
+        .map_err(|e| e.to_string())?;
+    let packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    check_active_pack_tests(&db, &settings, &configuration, &packs, &active_pack_ids(&packs)).await?;
+
+    save_settings(&db, &settings).await?;
+    let details = serde_json::json!({ "encryption_key_changed": new_key.is_some() });
+    log_audit(&db, &session, "settings_updated", "settings", None, Some(details.to_string())).await
+}
+
+/// Checks a rules configuration without saving it, so the settings tab can
+/// mark each invalid rule before the user submits.
+#[tauri::command]
+pub async fn validate_rules_config(
+    session_token: String,
+    rules_config: serde_json::Value,
+    state: State<'_, AppState>,
+) -> Result<Vec<RuleConfigError>, String> {
+    authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    Ok(declarative_rules::validate_rules_config(&rules_config))
+}
+
+#[tauri::command]
+pub async fn get_rule_configuration(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<RuleConfiguration, String> {
+    authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    let db = state.db.lock().unwrap();
+    db.get_rule_configuration().await
+        .map_err(|e| e.to_string())
+}
+
+/// Replaces the per-rule overrides, saving them as a new version.
+#[tauri::command]
+pub async fn update_rule_configuration(
+    session_token: String,
+    overrides: Vec<RuleOverride>,
+    state: State<'_, AppState>,
+) -> Result<RuleConfiguration, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let db = state.db.lock().unwrap();
+    let settings = load_settings(&db).await?;
+    let custom_rules = compile_custom_rules(&settings.rules_config)?;
+    rules::validate_overrides(&overrides, &custom_rules)
+        .map_err(|e| e.to_string())?;
+    let candidate = RuleConfiguration {
+        overrides: overrides.clone(),
+        ..db.get_rule_configuration().await.map_err(|e| e.to_string())?
+    };
+    let packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    check_active_pack_tests(&db, &settings, &candidate, &packs, &active_pack_ids(&packs)).await?;
+
+    let configuration = db.save_rule_configuration(&overrides, &session.user_id).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({
+        "version": configuration.version,
+        "overrides": configuration.overrides,
+    });
+    log_audit(&db, &session, "rule_configuration_updated", "settings", None, Some(details.to_string())).await?;
+
+    Ok(configuration)
+}
+
+/// Imports a CMS ICD-10-CM order or codes file, replacing any codes
+/// previously imported for that fiscal year.
+#[tauri::command]
+pub async fn import_icd10_codes(
+    session_token: String,
+    file_path: String,
+    fiscal_year: i32,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let codes = icd10::parse_release_file(&contents, fiscal_year)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.replace_icd10_codes(fiscal_year, &codes).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "fiscal_year": fiscal_year, "codes": codes.len() });
+    log_audit(&db, &session, "icd10_codes_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(codes.len())
+}
+
+/// Imports a CPT or HCPCS Level II code list (CSV), adding to or updating
+/// the codes already imported.
+#[tauri::command]
+pub async fn import_procedure_codes(
+    session_token: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let codes = procedure_codes::parse_code_file(&contents)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.upsert_procedure_codes(&codes).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "codes": codes.len() });
+    log_audit(&db, &session, "procedure_codes_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(codes.len())
+}
+
+/// Imports a CMS NCCI PTP edit file for the practitioner or hospital table.
+#[tauri::command]
+pub async fn import_ncci_edits(
+    session_token: String,
+    file_path: String,
+    setting: NcciSetting,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let edits = ncci::parse_ptp_file(&contents, setting)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.upsert_ncci_edits(&edits).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "setting": setting, "edits": edits.len() });
+    log_audit(&db, &session, "ncci_edits_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(edits.len())
+}
+
+/// Imports a CMS MUE table, replacing the values for that setting.
+#[tauri::command]
+pub async fn import_mue_limits(
+    session_token: String,
+    file_path: String,
+    setting: NcciSetting,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let limits = mue::parse_mue_file(&contents, setting)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.replace_mue_limits(setting, &limits).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "setting": setting, "limits": limits.len() });
+    log_audit(&db, &session, "mue_limits_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(limits.len())
+}
+
+/// Imports a Medicare physician fee schedule for a locality, or the
+/// chargemaster when `source` is `chargemaster`, replacing the previous one.
+#[tauri::command]
+pub async fn import_fee_schedule(
+    session_token: String,
+    file_path: String,
+    source: FeeSource,
+    locality: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let locality = match (source, locality.as_deref().map(str::trim)) {
+        (FeeSource::Chargemaster, _) => String::new(),
+        (FeeSource::Medicare, Some(locality)) if !locality.is_empty() => locality.to_string(),
+        (FeeSource::Medicare, _) => return Err("A locality is required for the Medicare fee schedule".to_string()),
+    };
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let entries = fee_schedule::parse_fee_file(&contents, source, &locality)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.replace_fee_schedule(source, &locality, &entries).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "source": source, "locality": locality, "entries": entries.len() });
+    log_audit(&db, &session, "fee_schedule_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(entries.len())
+}
+
+/// Imports payer-specific or newly published modifiers (CSV of code and
+/// description), adding to the standard set.
+#[tauri::command]
+pub async fn import_modifiers(
+    session_token: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let modifiers = modifiers::parse_modifier_file(&contents)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.upsert_modifiers(&modifiers).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "modifiers": modifiers.len() });
+    log_audit(&db, &session, "modifiers_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(modifiers.len())
+}
+
+/// Imports LCD/NCD coverage ranges (see `coverage::parse_policy_file`),
+/// replacing earlier versions of the policies in the file.
+#[tauri::command]
+pub async fn import_coverage_policies(
+    session_token: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let rules = coverage::parse_policy_file(&contents)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.replace_coverage_policies(&rules).await
+        .map_err(|e| e.to_string())?;
+    let mut policies: Vec<&str> = rules.iter().map(|rule| rule.policy_id.as_str()).collect();
+    policies.sort();
+    policies.dedup();
+    let details = serde_json::json!({ "policies": policies, "ranges": rules.len() });
+    log_audit(&db, &session, "coverage_policies_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(rules.len())
+}
+
+/// Imports age and sex restrictions for diagnosis and procedure codes (see
+/// `demographics::parse_attribute_file`).
+#[tauri::command]
+pub async fn import_code_attributes(
+    session_token: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let attributes = demographics::parse_attribute_file(&contents)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    db.upsert_code_attributes(&attributes).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "code_attributes": attributes.len() });
+    log_audit(&db, &session, "code_attributes_imported", "reference_data", None, Some(details.to_string())).await?;
+
+    Ok(attributes.len())
+}
+
+#[tauri::command]
+pub async fn get_payers(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<Payer>, String> {
+    authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    let db = state.db.lock().unwrap();
+    db.get_payers().await
+        .map_err(|e| e.to_string())
+}
+
+/// Adds a payer to the registry or updates an existing one.
+#[tauri::command]
+pub async fn save_payer(
+    session_token: String,
+    payer: Payer,
+    state: State<'_, AppState>,
+) -> Result<Payer, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+    payers::validate(&payer)
+        .map_err(|e| e.to_string())?;
+
+    let payer = Payer {
+        aliases: payer.aliases.iter().map(|alias| alias.trim().to_lowercase()).filter(|alias| !alias.is_empty()).collect(),
+        ..payer
+    };
+
+    let db = state.db.lock().unwrap();
+    db.save_payer(&payer).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::to_string(&payer).map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "payer_saved", "payer", None, Some(details)).await?;
+
+    Ok(payer)
+}
+
+#[tauri::command]
+pub async fn delete_payer(
+    session_token: String,
+    payer_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let db = state.db.lock().unwrap();
+    let deleted = db.delete_payer(&payer_id).await
+        .map_err(|e| e.to_string())?;
+    if !deleted {
+        return Err("Payer not found".to_string());
+    }
+    let details = serde_json::json!({ "payer_id": payer_id });
+    log_audit(&db, &session, "payer_deleted", "payer", None, Some(details.to_string())).await?;
+
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_rule_packs(
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<RulePack>, String> {
+    authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    let db = state.db.lock().unwrap();
+    db.get_rule_packs().await
+        .map_err(|e| e.to_string())
+}
+
+/// Adds or updates a rule pack after checking it compiles together with
+/// the workspace rules and the packs it inherits from.
+#[tauri::command]
+pub async fn save_rule_pack(
+    session_token: String,
+    pack: RulePack,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let db = state.db.lock().unwrap();
+    let settings = load_settings(&db).await?;
+    let mut packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    rule_packs::validate(&pack, &packs, &settings.rules_config)
+        .map_err(|e| e.to_string())?;
+    packs.retain(|other| other.id != pack.id);
+    packs.push(pack.clone());
+    let configuration = db.get_rule_configuration().await
+        .map_err(|e| e.to_string())?;
+    check_active_pack_tests(&db, &settings, &configuration, &packs, std::slice::from_ref(&pack.id)).await?;
+
+    db.save_rule_packs(std::slice::from_ref(&pack)).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({
+        "pack_id": pack.id,
+        "payer_id": pack.payer_id,
+        "plan": pack.plan,
+        "extends": pack.extends,
+        "rules": pack.rules.len(),
+        "overrides": pack.overrides.len(),
+        "active": pack.active,
+    });
+    log_audit(&db, &session, "rule_pack_saved", "rule_pack", None, Some(details.to_string())).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_rule_pack(
+    session_token: String,
+    pack_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let db = state.db.lock().unwrap();
+    let packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    if let Some(child) = packs.iter().find(|pack| pack.extends.as_deref() == Some(pack_id.as_str())) {
+        return Err(format!("Rule pack {} inherits from this pack", child.id));
+    }
+    let deleted = db.delete_rule_pack(&pack_id).await
+        .map_err(|e| e.to_string())?;
+    if !deleted {
+        return Err("Rule pack not found".to_string());
+    }
+    let details = serde_json::json!({ "pack_id": pack_id });
+    log_audit(&db, &session, "rule_pack_deleted", "rule_pack", None, Some(details.to_string())).await?;
+
+    Ok(())
+}
+
+/// Writes the packs, along with the packs they inherit from, to a file
+/// that `import_rule_packs` can load in another workspace.
+#[tauri::command]
+pub async fn export_rule_packs(
+    session_token: String,
+    pack_ids: Vec<String>,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    let db = state.db.lock().unwrap();
+    let packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    let file = rule_packs::export(&packs, &pack_ids)
+        .map_err(|e| e.to_string())?;
+    let contents = serde_json::to_string_pretty(&file)
+        .map_err(|e| e.to_string())?;
+    std::fs::write(&file_path, contents)
+        .map_err(|e| e.to_string())?;
+
+    let exported: Vec<&str> = file.packs.iter().map(|pack| pack.id.as_str()).collect();
+    let details = serde_json::json!({ "packs": exported });
+    log_audit(&db, &session, "rule_packs_exported", "rule_pack", None, Some(details.to_string())).await?;
+
+    Ok(file.packs.len())
+}
+
+/// Loads packs exported from another workspace, replacing packs with the
+/// same id. Nothing is saved unless every pack in the file is valid here.
+#[tauri::command]
+pub async fn import_rule_packs(
+    session_token: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+
+    let contents = std::fs::read_to_string(&file_path)
+        .map_err(|e| e.to_string())?;
+    let imported = rule_packs::parse_file(&contents)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    let settings = load_settings(&db).await?;
+    let mut packs: Vec<RulePack> = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|pack| !imported.iter().any(|new| new.id == pack.id))
+        .collect();
+    packs.extend(imported.iter().cloned());
+    for pack in &imported {
+        rule_packs::validate(pack, &packs, &settings.rules_config)
+            .map_err(|e| e.to_string())?;
+    }
+    let ids: Vec<String> = imported.iter().map(|pack| pack.id.clone()).collect();
+    let configuration = db.get_rule_configuration().await
+        .map_err(|e| e.to_string())?;
+    check_active_pack_tests(&db, &settings, &configuration, &packs, &ids).await?;
+
+    db.save_rule_packs(&imported).await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "packs": ids });
+    log_audit(&db, &session, "rule_packs_imported", "rule_pack", None, Some(details.to_string())).await?;
+
+    Ok(imported.len())
+}
+
+/// Runs the tests attached to custom rules: the workspace rules, or the
+/// rules a pack resolves to when `pack_id` is given.
+#[tauri::command]
+pub async fn run_rule_tests(
+    session_token: String,
+    pack_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RuleTestResult>, String> {
+    authorize(&state, &session_token, Permission::ViewSettings)?;
+
+    let db = state.db.lock().unwrap();
+    let settings = load_settings(&db).await?;
+    let configuration = db.get_rule_configuration().await
+        .map_err(|e| e.to_string())?;
+    let packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    execute_rule_tests(&db, &settings, &configuration, &packs, pack_id.as_deref()).await
+}
+
+/// Each fixture is checked the way `run_rules` checks a stored claim, with
+/// the same overrides and code sets. Stored claims and today's date are
+/// left out, so results only change when the rules or code sets do.
+async fn execute_rule_tests(
+    db: &Database,
+    settings: &Settings,
+    configuration: &RuleConfiguration,
+    packs: &[RulePack],
+    pack_id: Option<&str>,
+) -> Result<Vec<RuleTestResult>, String> {
+    let mut configuration = configuration.clone();
+    let pack = pack_id
+        .map(|id| rule_packs::resolve(packs, id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let custom_rules = apply_rule_pack(&settings.rules_config, &mut configuration, pack.as_ref())?;
+    let cases = rule_tests::cases(&custom_rules);
+
+    let mut rules_engine = RulesEngine::new()
+        .with_custom_rules(custom_rules)
+        .with_configuration(configuration);
+    let shared = db.load_shared_reference_data().await
+        .map_err(|e| e.to_string())?;
+    let mut results = Vec::new();
+    for (rule_id, test) in cases {
+        let claim = rule_tests::fixture_claim(&test.claim);
+        let reference = db.load_claim_reference_data(&claim, settings, &shared).await
+            .map_err(|e| e.to_string())?;
+        rules_engine = rules_engine.with_reference_data(reference);
+        if let Some(as_of) = rule_tests::as_of(&test) {
+            rules_engine = rules_engine.with_as_of(as_of);
+        }
+        let findings = rules_engine.validate_claim(&claim).await
+            .map_err(|e| e.to_string())?;
+        results.push(rule_tests::compare(&rule_id, &test, &findings));
+    }
+
+    Ok(results)
+}
+
+/// Refuses a change to `changed` packs that would leave an active pack, or
+/// one inheriting from them, with failing rule tests. Settings and
+/// overrides are the ones the change would save.
+async fn check_active_pack_tests(
+    db: &Database,
+    settings: &Settings,
+    configuration: &RuleConfiguration,
+    packs: &[RulePack],
+    changed: &[String],
+) -> Result<(), String> {
+    let mut checked = HashSet::new();
+    for id in changed {
+        for pack in rule_packs::affected_active_packs(packs, id) {
+            if !checked.insert(pack.id.clone()) {
+                continue;
+            }
+            let results = execute_rule_tests(db, settings, configuration, packs, Some(&pack.id)).await?;
+            if let Some(failures) = rule_tests::describe_failures(&results) {
+                return Err(format!("Rule pack {} can't be active while its rule tests fail:\n{}", pack.id, failures));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Workspace rules and overrides reach every pack, so changing them
+/// re-checks all the active ones.
+fn active_pack_ids(packs: &[RulePack]) -> Vec<String> {
+    packs.iter()
+        .filter(|pack| pack.active)
+        .map(|pack| pack.id.clone())
+        .collect()
+}
+
+/// Re-runs the rules on claims received between `from` and `to` under both
+/// the current configuration and a candidate one, and reports what would
+/// change. Nothing is saved to the claims.
+#[tauri::command]
+pub async fn backtest_rules(
+    session_token: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    rules_config: serde_json::Value,
+    overrides: Vec<RuleOverride>,
+    state: State<'_, AppState>,
+) -> Result<BacktestReport, String> {
+    let session = authorize(&state, &session_token, Permission::ManageSettings)?;
+    if from > to {
+        return Err("The start date must not be after the end date".to_string());
+    }
+
+    let candidate_rules = compile_custom_rules(&rules_config)?;
+    rules::validate_overrides(&overrides, &candidate_rules)
+        .map_err(|e| e.to_string())?;
+    let candidate_configuration = RuleConfiguration { overrides, ..Default::default() };
+
+    let db = state.db.lock().unwrap();
+    let settings = load_settings(&db).await?;
+    let current_configuration = db.get_rule_configuration().await
+        .map_err(|e| e.to_string())?;
+    let packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    let start = from.and_time(chrono::NaiveTime::MIN).and_utc();
+    let end = (to + Duration::days(1)).and_time(chrono::NaiveTime::MIN).and_utc();
+    let claims = db.get_claims_created_between(start, end).await
+        .map_err(|e| e.to_string())?;
+
+    let shared = db.load_shared_reference_data().await
+        .map_err(|e| e.to_string())?;
+
+    let mut impacts = Vec::new();
+    for claim in &claims {
+        let mut reference = db.load_claim_reference_data(claim, &settings, &shared).await
+            .map_err(|e| e.to_string())?;
+        reference.related_claims = db.get_same_day_claims_for_patient(claim).await
+            .map_err(|e| e.to_string())?;
+        let pack = select_rule_pack(&packs, &reference, claim)?;
+
+        let mut results = Vec::new();
+        for (rules_config, configuration) in [
+            (&settings.rules_config, &current_configuration),
+            (&rules_config, &candidate_configuration),
+        ] {
+            let mut configuration = configuration.clone();
+            let custom_rules = apply_rule_pack(rules_config, &mut configuration, pack.as_ref())?;
+            let rules_engine = RulesEngine::new()
+                .with_custom_rules(custom_rules)
+                .with_configuration(configuration)
+                .with_reference_data(reference.clone())
+                .with_as_of(claim.created_at.date_naive());
+            results.push(rules_engine.validate_claim(claim).await
+                .map_err(|e| e.to_string())?);
+        }
+        impacts.extend(backtest::compare_claim(claim, &results[0], &results[1]));
+    }
+    let report = backtest::summarize(from, to, claims.len(), impacts);
+
+    log_claim_access(&db, &session, "claim_backtested", &claims).await?;
+    let details = serde_json::json!({
+        "from": from,
+        "to": to,
+        "claims_evaluated": report.claims_evaluated,
+        "claims_changed": report.claims.len(),
+    });
+    log_audit(&db, &session, "rules_backtested", "settings", None, Some(details.to_string())).await?;
+
+    Ok(report)
+}
+
+/// Runs every rule on the claim under the saved configuration and the
+/// payer's rule pack, if it has one. Nothing is saved.
+async fn evaluate_claim(db: &Database, claim: &Claim) -> Result<(Vec<ValidationResult>, Option<ResolvedPack>), String> {
+    let settings = load_settings(db).await?;
+    let mut configuration = db.get_rule_configuration().await
+        .map_err(|e| e.to_string())?;
+    let reference = db.load_reference_data(claim, &settings).await
+        .map_err(|e| e.to_string())?;
+
+    // The payer's rule pack layers its rules and overrides over the workspace's.
+    let packs = db.get_rule_packs().await
+        .map_err(|e| e.to_string())?;
+    let pack = select_rule_pack(&packs, &reference, claim)?;
+    let custom_rules = apply_rule_pack(&settings.rules_config, &mut configuration, pack.as_ref())?;
+
+    let rules_engine = RulesEngine::new()
+        .with_custom_rules(custom_rules)
+        .with_configuration(configuration)
+        .with_reference_data(reference);
+    let validation_results = rules_engine.validate_claim(claim)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok((validation_results, pack))
+}
+
+/// Applies the fix attached to one of the claim's findings, re-runs the
+/// rules and records the change in the claim's history.
+async fn apply_finding_fix(db: &Database, session: &Session, claim: &mut Claim, finding_id: &Uuid) -> Result<(), String> {
+    let finding = claim.validation_results.iter()
+        .find(|result| result.id == *finding_id)
+        .ok_or("Finding not found")?;
+    let patch = finding.fix.clone().ok_or("This finding has no automatic fix")?;
+    let rule_id = finding.rule_id.clone();
+
+    let previous_data = claim.extracted_data.clone();
+    fixes::apply(&mut claim.extracted_data, &patch)
+        .map_err(|e| e.to_string())?;
+    let (validation_results, _) = evaluate_claim(db, claim).await?;
+    claim.queue = rules::queue_for(&validation_results);
+    claim.validation_results = validation_results;
+    claim.updated_at = Utc::now();
+
+    let entry = ClaimHistoryEntry {
+        id: Uuid::new_v4(),
+        claim_id: claim.id,
+        user_id: session.user_id,
+        rule_id: Some(rule_id.clone()),
+        description: fixes::describe(&patch),
+        patch,
+        previous_data,
+        created_at: Utc::now(),
+    };
+    let details = serde_json::json!({
+        "rule_id": rule_id,
+        "patch": entry.patch,
+        "queue": claim.queue,
+    });
+    let log = AuditLog::new(session.user_id, "claim_fix_applied", "claim", Some(claim.id), Some(details.to_string()));
+    db.save_claim_fix(claim, &entry, &log).await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn apply_fix(
+    session_token: String,
+    claim_id: String,
+    finding_id: String,
+    state: State<'_, AppState>,
+) -> Result<Claim, String> {
+    let session = authorize(&state, &session_token, Permission::EditClaims)?;
+
+    let claim_id = Uuid::parse_str(&claim_id)
+        .map_err(|e| e.to_string())?;
+    let finding_id = Uuid::parse_str(&finding_id)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    let mut claim = db.get_claim(&claim_id).await
+        .map_err(|e| e.to_string())?
+        .ok_or("Claim not found")?;
+    apply_finding_fix(&db, &session, &mut claim, &finding_id).await?;
+
+    Ok(claim)
+}
+
+/// Applies the fixes for one rule to every claim in a queue. A claim's
+/// rules are re-run after each fix, so later fixes see the corrected data.
+/// Each fix is saved on its own: when one fails the claim is reported and
+/// keeps the fixes applied before it.
+#[tauri::command]
+pub async fn apply_fixes_to_queue(
+    session_token: String,
+    queue: QueueType,
+    rule_id: String,
+    state: State<'_, AppState>,
+) -> Result<BatchFixSummary, String> {
+    let session = authorize(&state, &session_token, Permission::EditClaims)?;
+
+    let db = state.db.lock().unwrap();
+    let claims = db.get_claims(Some(queue)).await
+        .map_err(|e| e.to_string())?;
+
+    let mut summary = BatchFixSummary::default();
+    let fixable = |claim: &Claim| -> Vec<Uuid> {
+        claim.validation_results.iter()
+            .filter(|result| result.rule_id == rule_id && result.fix.is_some())
+            .map(|result| result.id)
+            .collect()
+    };
+    for mut claim in claims {
+        // Bounded by the original count in case a fix keeps re-firing.
+        let mut applied = 0;
+        for _ in 0..fixable(&claim).len() {
+            let Some(finding_id) = fixable(&claim).first().copied() else {
+                break;
+            };
+            if let Err(error) = apply_finding_fix(&db, &session, &mut claim, &finding_id).await {
+                summary.failures.push(BatchFixFailure { claim_id: claim.id, error });
+                break;
+            }
+            applied += 1;
+        }
+        if applied > 0 {
+            summary.claims_fixed += 1;
+            summary.fixes_applied += applied;
+        }
+    }
+
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn get_claim_history(
+    session_token: String,
+    claim_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ClaimHistoryEntry>, String> {
+    let session = authorize(&state, &session_token, Permission::ViewClaims)?;
+
+    let claim_id = Uuid::parse_str(&claim_id)
+        .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().unwrap();
+    let history = db.get_claim_history(&claim_id).await
+        .map_err(|e| e.to_string())?;
+    log_audit(&db, &session, "claim_history_viewed", "claim", Some(claim_id), None).await?;
+
+    Ok(history)
+}
+
+fn select_rule_pack(packs: &[RulePack], reference: &ReferenceData, claim: &Claim) -> Result<Option<ResolvedPack>, String> {
+    rule_packs::select(packs, reference.payer.as_ref(), claim.extracted_data.plan.as_deref())
+        .map(|pack| rule_packs::resolve(packs, &pack.id))
+        .transpose()
+        .map_err(|e| e.to_string())
+}
+
+/// Compiles the workspace rules with the pack's rules layered on top and
+/// merges the pack's overrides into `configuration`.
+fn apply_rule_pack(
+    rules_config: &serde_json::Value,
+    configuration: &mut RuleConfiguration,
+    pack: Option<&ResolvedPack>,
+) -> Result<Vec<CompiledRule>, String> {
+    match pack {
+        Some(pack) => {
+            configuration.overrides = rule_packs::merge_overrides(&configuration.overrides, &pack.overrides);
+            compile_custom_rules(&rule_packs::rules_config(rules_config, pack))
+        }
+        None => compile_custom_rules(rules_config),
+    }
+}
+
+fn compile_custom_rules(rules_config: &serde_json::Value) -> Result<Vec<CompiledRule>, String> {
+    declarative_rules::compile_rules_config(rules_config)
+        .map_err(|errors| format!("Invalid rules configuration:\n{}", declarative_rules::format_errors(&errors)))
+}
+
+/// The stored settings. An encryption key saved in them by an older version
+/// is moved to the key store the first time they are read.
+async fn load_settings(db: &Database) -> Result<Settings, String> {
+    let value = db.get_setting(SETTINGS_KEY).await
+        .map_err(|e| e.to_string())?;
+    let mut settings: Settings = match value {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string())?,
+        None => return Ok(Settings::default()),
+    };
+
+    if let Some(key) = settings.encryption_key.take() {
+        if !key_store::has_key() {
+            key_store::save_key(&key).map_err(|e| e.to_string())?;
+        }
+        save_settings(db, &settings).await?;
+    }
+    Ok(settings)
+}
+
+async fn save_settings(db: &Database, settings: &Settings) -> Result<(), String> {
+    let value = serde_json::to_string(settings)
+        .map_err(|e| e.to_string())?;
+    db.set_setting(SETTINGS_KEY, &value).await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn add_user(db: &Database, username: &str, role: UserRole) -> User {
+        let user = User {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            role,
+            created_at: Utc::now(),
+            last_login: None,
+            locked_until: None,
+            mfa_enabled: false,
+            is_active: true,
+        };
+        db.create_user(&user, "hashed_secret").await.unwrap();
+        user
+    }
+
+    async fn add_claim(db: &Database, assigned_to: &User, status: ClaimStatus) -> Claim {
+        let mut claim = rule_tests::fixture_claim(&ExtractedData::default());
+        claim.assigned_to = Some(assigned_to.id);
+        claim.status = status;
+        db.create_claim(&claim).await.unwrap();
+        claim
+    }
+
+    fn admin_session(sessions: &Mutex<SessionStore>, admin: &User) -> Session {
+        let token = sessions.lock().unwrap().create(admin, Utc::now()).token;
+        sessions.lock().unwrap().authorize(&token, Permission::ManageUsers, Utc::now()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failures_back_off_then_lock_until_expiry() {
+        let db = Database::in_memory().await.unwrap();
+        let user = add_user(&db, "coder", UserRole::BillingCoder).await;
+        let start = Utc::now();
+
+        register_login_failure(&db, &user, "desk-1", start, "incorrect password").await.unwrap();
+        let throttled = check_login_allowed(&db, &user, "desk-1", start).await.unwrap_err();
+        assert!(throttled.contains("Try again in 1 seconds"), "{}", throttled);
+
+        let mut now = start + auth::backoff_delay(1);
+        for attempt in 1..auth::MAX_FAILED_ATTEMPTS {
+            check_login_allowed(&db, &user, "desk-1", now).await.unwrap();
+            register_login_failure(&db, &user, "desk-1", now, "incorrect password").await.unwrap();
+            now += auth::backoff_delay(attempt + 1);
+        }
+
+        let locked = check_login_allowed(&db, &user, "desk-1", now).await.unwrap_err();
+        assert!(locked.contains("locked"), "{}", locked);
+
+        // Once the lock runs out the counters start over.
+        now += Duration::minutes(auth::LOCKOUT_MINUTES);
+        check_login_allowed(&db, &user, "desk-1", now).await.unwrap();
+        let login_state = db.get_login_state(&user.id).await.unwrap();
+        assert_eq!(login_state.failed_attempts, 0);
+        assert!(login_state.locked_until.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_last_local_admin_cannot_be_deactivated() {
+        let db = Database::in_memory().await.unwrap();
+        let sessions = Mutex::new(SessionStore::new());
+        let admin = add_user(&db, "admin", UserRole::LocalAdmin).await;
+        let second = add_user(&db, "second", UserRole::LocalAdmin).await;
+        let actor = admin_session(&sessions, &admin);
+
+        let own = deactivate_account(&db, &sessions, &actor, &admin.id, None).await.unwrap_err();
+        assert!(own.contains("your own account"), "{}", own);
+
+        deactivate_account(&db, &sessions, &actor, &second.id, None).await.unwrap();
+        let second_actor = Session { user_id: second.id, ..actor.clone() };
+        let last = deactivate_account(&db, &sessions, &second_actor, &admin.id, None).await.unwrap_err();
+        assert!(last.contains("last active LocalAdmin"), "{}", last);
+        assert!(db.get_user(&admin.id).await.unwrap().unwrap().is_active);
+    }
+
+    #[tokio::test]
+    async fn test_deactivation_moves_open_claims_and_ends_sessions() {
+        let db = Database::in_memory().await.unwrap();
+        let sessions = Mutex::new(SessionStore::new());
+        let admin = add_user(&db, "admin", UserRole::LocalAdmin).await;
+        let coder = add_user(&db, "coder", UserRole::BillingCoder).await;
+        let colleague = add_user(&db, "colleague", UserRole::BillingCoder).await;
+        let actor = admin_session(&sessions, &admin);
+
+        let open = add_claim(&db, &coder, ClaimStatus::UnderReview).await;
+        let paid = add_claim(&db, &coder, ClaimStatus::Paid).await;
+        let coder_token = sessions.lock().unwrap().create(&coder, Utc::now()).token;
+
+        // A bad reassignment target is refused before anything changes.
+        let unknown = Uuid::new_v4();
+        assert!(deactivate_account(&db, &sessions, &actor, &coder.id, Some(&unknown)).await.is_err());
+        assert!(db.get_user(&coder.id).await.unwrap().unwrap().is_active);
+        assert!(sessions.lock().unwrap().authorize(&coder_token, Permission::ViewClaims, Utc::now()).is_ok());
+
+        let moved = deactivate_account(&db, &sessions, &actor, &coder.id, Some(&colleague.id)).await.unwrap();
+        assert_eq!(moved, 1);
+        assert!(!db.get_user(&coder.id).await.unwrap().unwrap().is_active);
+        assert_eq!(db.get_claim(&open.id).await.unwrap().unwrap().assigned_to, Some(colleague.id));
+        assert_eq!(db.get_claim(&paid.id).await.unwrap().unwrap().assigned_to, Some(coder.id));
+        assert!(matches!(
+            sessions.lock().unwrap().authorize(&coder_token, Permission::ViewClaims, Utc::now()),
+            Err(auth::AuthError::InvalidSession)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_overrides_that_break_active_pack_tests_are_refused() {
+        let db = Database::in_memory().await.unwrap();
+        let settings = load_settings(&db).await.unwrap();
+        let fixture = ExtractedData { payer: Some("Aetna".to_string()), ..Default::default() };
+        let pack = RulePack {
+            id: "aetna".to_string(),
+            name: "Aetna".to_string(),
+            payer_id: Some("aetna".to_string()),
+            plan: None,
+            extends: None,
+            rules: vec![serde_json::json!({
+                "id": "prior_auth",
+                "name": "Prior authorization",
+                "severity": "warning",
+                "condition": { "field": "payer", "op": "exists" },
+                "message": "Check prior authorization",
+                "tests": [{ "name": "fires for Aetna", "claim": fixture, "expect": ["prior_auth"] }],
+            })],
+            overrides: vec![],
+            updated_at: None,
+            active: true,
+        };
+        db.save_rule_packs(std::slice::from_ref(&pack)).await.unwrap();
+        let packs = db.get_rule_packs().await.unwrap();
+        let configuration = db.get_rule_configuration().await.unwrap();
+        check_active_pack_tests(&db, &settings, &configuration, &packs, &active_pack_ids(&packs)).await.unwrap();
+
+        let disabled = RuleConfiguration {
+            overrides: vec![RuleOverride {
+                rule_id: "prior_auth".to_string(),
+                enabled: false,
+                severity: None,
+                min_confidence: None,
+            }],
+            ..configuration
+        };
+        let refused = check_active_pack_tests(&db, &settings, &disabled, &packs, &active_pack_ids(&packs)).await.unwrap_err();
+        assert!(refused.contains("Rule pack aetna"), "{}", refused);
+    }
+
+    #[tokio::test]
+    async fn test_rule_tests_ignore_stored_claims() {
+        let db = Database::in_memory().await.unwrap();
+        let date = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 1, 10, 0, 0, 0).unwrap();
+        let fixture = ExtractedData {
+            payer: Some("Aetna".to_string()),
+            patient_name: Some("Jane Doe".to_string()),
+            cpt_codes: vec!["99213".to_string()],
+            dates: vec![ExtractedDate::new(DateRole::ServiceFrom, date)],
+            ..Default::default()
+        };
+        db.create_claim(&rule_tests::fixture_claim(&fixture)).await.unwrap();
+        let settings = Settings {
+            rules_config: serde_json::json!({ "rules": [{
+                "id": "prior_auth",
+                "name": "Prior authorization",
+                "severity": "warning",
+                "condition": { "field": "payer", "op": "exists" },
+                "message": "Check prior authorization",
+                "tests": [{ "name": "stored twin", "claim": fixture, "expect": ["prior_auth", "duplicate_claim"] }],
+            }]}),
+            ..Settings::default()
+        };
+        let configuration = db.get_rule_configuration().await.unwrap();
+
+        let results = execute_rule_tests(&db, &settings, &configuration, &[], None).await.unwrap();
+        assert_eq!(results[0].missing, ["duplicate_claim"]);
+    }
+}
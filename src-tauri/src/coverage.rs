@@ -0,0 +1,129 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use crate::icd10;
+use crate::procedure_codes;
+use crate::types::CoverageRule;
+
+/// Parses a coverage policy export, one covered ICD-10 range per row:
+/// `policy_id,title,policy_type,jurisdiction,procedure_code,icd10_start,icd10_end,effective_date,end_date`.
+/// A blank jurisdiction marks a national policy; a blank `icd10_end` covers
+/// just the start code and its children.
+pub fn parse_policy_file(contents: &str) -> Result<Vec<CoverageRule>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.to_ascii_lowercase()).collect();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let required = |name: &str| column(name).ok_or_else(|| anyhow!("Missing column {}", name));
+
+    let policy_id_column = required("policy_id")?;
+    let procedure_column = required("procedure_code")?;
+    let start_column = required("icd10_start")?;
+    let title_column = column("title");
+    let type_column = column("policy_type");
+    let jurisdiction_column = column("jurisdiction");
+    let end_column = column("icd10_end");
+    let effective_column = column("effective_date");
+    let end_date_column = column("end_date");
+
+    let mut rules = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = index + 2;
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or_default();
+
+        let procedure_code = field(Some(procedure_column)).to_ascii_uppercase();
+        if !procedure_codes::is_valid_format(&procedure_code) {
+            bail!("Line {}: invalid procedure code {}", line, procedure_code);
+        }
+        let icd10_start = icd10::normalize(field(Some(start_column)));
+        if icd10_start.len() < 3 {
+            bail!("Line {}: invalid ICD-10 code {}", line, icd10_start);
+        }
+        let icd10_end = match field(end_column) {
+            "" => icd10_start.clone(),
+            value => icd10::normalize(value),
+        };
+
+        rules.push(CoverageRule {
+            policy_id: field(Some(policy_id_column)).to_string(),
+            title: field(title_column).to_string(),
+            policy_type: match field(type_column) {
+                "" => "LCD".to_string(),
+                value => value.to_ascii_uppercase(),
+            },
+            jurisdiction: Some(field(jurisdiction_column).to_string()).filter(|j| !j.is_empty()),
+            procedure_code,
+            icd10_start,
+            icd10_end,
+            effective_date: parse_date(field(effective_column), line)?,
+            end_date: parse_date(field(end_date_column), line)?,
+        });
+    }
+
+    Ok(rules)
+}
+
+fn parse_date(value: &str, line: usize) -> Result<Option<NaiveDate>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    ["%Y-%m-%d", "%m/%d/%Y", "%Y%m%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .map(Some)
+        .ok_or_else(|| anyhow!("Line {}: invalid date {}", line, value))
+}
+
+/// Ranges are inclusive and cover children: `M17.0`–`M17.9` includes `M17.11`.
+pub fn covers(rule: &CoverageRule, diagnosis_code: &str) -> bool {
+    let code = icd10::normalize(diagnosis_code);
+    let prefix = &code[..code.len().min(rule.icd10_end.len())];
+    code.as_str() >= rule.icd10_start.as_str() && prefix <= rule.icd10_end.as_str()
+}
+
+pub fn is_in_effect(rule: &CoverageRule, date_of_service: Option<NaiveDate>) -> bool {
+    let Some(date) = date_of_service else {
+        return rule.end_date.is_none();
+    };
+    rule.effective_date.is_none_or(|start| start <= date) && rule.end_date.is_none_or(|end| date <= end)
+}
+
+pub fn describe_range(rule: &CoverageRule) -> String {
+    if rule.icd10_start == rule.icd10_end {
+        icd10::display(&rule.icd10_start)
+    } else {
+        format!("{}-{}", icd10::display(&rule.icd10_start), icd10::display(&rule.icd10_end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_policy_rows() {
+        let file = "policy_id,title,policy_type,jurisdiction,procedure_code,icd10_start,icd10_end,effective_date,end_date\n\
+                    L33252,Knee Injections,LCD,J15,20610,M17.0,M17.9,2019-10-01,\n\
+                    NCD190.14,Electrocardiogram,NCD,,93000,I48.0,,,\n";
+        let rules = parse_policy_file(file).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].icd10_end, "M179");
+        assert_eq!(rules[1].jurisdiction, None);
+        assert_eq!(rules[1].icd10_end, "I480");
+    }
+
+    #[test]
+    fn test_ranges_include_child_codes() {
+        let rule = &parse_policy_file(
+            "policy_id,procedure_code,icd10_start,icd10_end\nL1,20610,M17.0,M17.9\n"
+        ).unwrap()[0];
+
+        assert!(covers(rule, "M17.11"));
+        assert!(covers(rule, "M17.0"));
+        assert!(!covers(rule, "M16.9"));
+        assert!(!covers(rule, "M18.0"));
+    }
+}
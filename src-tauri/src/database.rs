@@ -1,11 +1,21 @@
-use sqlx::{SqlitePool, Row};
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool, Transaction};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use crate::types::*;
+use crate::audit;
+use crate::auth::LoginState;
+use crate::demographics;
+use crate::duplicates;
+use crate::icd10;
+use crate::mfa::MfaCredentials;
+use crate::modifiers;
+use crate::payers;
+use crate::reference_data::{ReferenceData, SharedReferenceData};
 use anyhow::Result;
 
 pub struct Database {
     pool: SqlitePool,
+    audit_signing_key: Option<Vec<u8>>,
 }
 
 impl Database {
@@ -13,7 +23,30 @@ impl Database {
         let database_url = "sqlite:claimsense.db";
         let pool = SqlitePool::connect(database_url).await?;
         
-        let db = Database { pool };
+        let db = Database {
+            pool,
+            audit_signing_key: audit::signing_key_from_env(),
+        };
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    /// A private in-memory database for tests. One connection is kept open
+    /// for the life of the pool, as each connection would get its own
+    /// empty database.
+    #[cfg(test)]
+    pub async fn in_memory() -> Result<Self> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await?;
+
+        let db = Database {
+            pool,
+            audit_signing_key: None,
+        };
         db.migrate().await?;
         Ok(db)
     }
@@ -82,6 +115,272 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS login_attempts (
+                id TEXT PRIMARY KEY,
+                username TEXT NOT NULL,
+                user_id TEXT,
+                workstation TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.add_column_if_missing("users", "failed_login_attempts", "INTEGER NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("users", "last_failed_login", "TEXT").await?;
+        self.add_column_if_missing("users", "locked_until", "TEXT").await?;
+        self.add_column_if_missing("users", "mfa_secret", "TEXT").await?;
+        self.add_column_if_missing("users", "mfa_enabled", "INTEGER NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("users", "mfa_last_step", "INTEGER").await?;
+        self.add_column_if_missing("users", "is_active", "INTEGER NOT NULL DEFAULT 1").await?;
+        self.add_column_if_missing("users", "deactivated_at", "TEXT").await?;
+
+        self.add_column_if_missing("audit_logs", "sequence", "INTEGER").await?;
+        self.add_column_if_missing("audit_logs", "prev_hash", "TEXT").await?;
+        self.add_column_if_missing("audit_logs", "entry_hash", "TEXT").await?;
+        self.add_column_if_missing("audit_logs", "signature", "TEXT").await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_audit_logs_sequence ON audit_logs(sequence)")
+            .execute(&self.pool)
+            .await?;
+        self.backfill_audit_signing_start().await?;
+        self.chain_unsealed_audit_logs().await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                code_hash TEXT NOT NULL,
+                used_at TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rule_configurations (
+                version INTEGER PRIMARY KEY AUTOINCREMENT,
+                overrides TEXT NOT NULL,
+                created_by TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS icd10_codes (
+                code TEXT NOT NULL,
+                fiscal_year INTEGER NOT NULL,
+                description TEXT NOT NULL,
+                billable INTEGER NOT NULL,
+                PRIMARY KEY (code, fiscal_year)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS procedure_codes (
+                code TEXT NOT NULL,
+                effective_date TEXT NOT NULL DEFAULT '',
+                code_type TEXT NOT NULL,
+                description TEXT NOT NULL,
+                termination_date TEXT,
+                PRIMARY KEY (code, effective_date)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ncci_ptp_edits (
+                setting TEXT NOT NULL,
+                column1 TEXT NOT NULL,
+                column2 TEXT NOT NULL,
+                effective_date TEXT NOT NULL,
+                deletion_date TEXT,
+                modifier_indicator INTEGER NOT NULL,
+                rationale TEXT NOT NULL,
+                PRIMARY KEY (setting, column1, column2, effective_date)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mue_limits (
+                setting TEXT NOT NULL,
+                code TEXT NOT NULL,
+                mue_value INTEGER NOT NULL,
+                adjudication_indicator INTEGER NOT NULL,
+                rationale TEXT NOT NULL,
+                PRIMARY KEY (setting, code)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS modifiers (
+                code TEXT PRIMARY KEY,
+                description TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS coverage_policies (
+                policy_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                policy_type TEXT NOT NULL,
+                jurisdiction TEXT,
+                procedure_code TEXT NOT NULL,
+                icd10_start TEXT NOT NULL,
+                icd10_end TEXT NOT NULL,
+                effective_date TEXT,
+                end_date TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_coverage_policies_code ON coverage_policies (procedure_code)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS code_attributes (
+                code_system TEXT NOT NULL,
+                code TEXT NOT NULL,
+                min_age INTEGER,
+                max_age INTEGER,
+                sex TEXT,
+                description TEXT NOT NULL,
+                PRIMARY KEY (code_system, code)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS payers (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                aliases TEXT NOT NULL,
+                filing_limit_days INTEGER,
+                secondary_filing_limit_days INTEGER
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        for payer in payers::default_payers() {
+            self.insert_payer(&payer, "INSERT OR IGNORE").await?;
+        }
+        self.add_default_payer_aliases().await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS fee_schedule (
+                source TEXT NOT NULL,
+                locality TEXT NOT NULL,
+                code TEXT NOT NULL,
+                modifier TEXT NOT NULL,
+                amount REAL NOT NULL,
+                PRIMARY KEY (source, locality, code, modifier)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS claim_history (
+                id TEXT PRIMARY KEY,
+                claim_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                rule_id TEXT,
+                patch TEXT NOT NULL,
+                description TEXT NOT NULL,
+                previous_data TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_claim_history_claim ON claim_history (claim_id, created_at)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rule_packs (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                payer_id TEXT,
+                plan TEXT,
+                extends TEXT,
+                rules TEXT NOT NULL,
+                overrides TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                active INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        for (code, description) in modifiers::STANDARD_MODIFIERS {
+            sqlx::query("INSERT OR IGNORE INTO modifiers (code, description) VALUES (?, ?)")
+                .bind(code)
+                .bind(description)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// `CREATE TABLE IF NOT EXISTS` leaves older databases untouched, so
+    /// columns added after a table was first shipped go through here.
+    async fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&self.pool)
+            .await?;
+
+        let exists = columns.iter()
+            .any(|row| row.try_get::<String, _>("name").map(|name| name == column).unwrap_or(false));
+
+        if !exists {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&self.pool)
+                .await?;
+        }
+
         Ok(())
     }
 
@@ -142,7 +441,67 @@ impl Database {
         Ok(claims)
     }
 
+    /// Claims received in `[from, to)`, oldest first.
+    pub async fn get_claims_created_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Claim>> {
+        let rows = sqlx::query("SELECT * FROM claims WHERE created_at >= ? AND created_at < ? ORDER BY created_at")
+            .bind(from.to_rfc3339())
+            .bind(to.to_rfc3339())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(|row| self.row_to_claim(row)).collect()
+    }
+
+    /// Other claims for the same patient with a service date on this claim's
+    /// date of service. The date is matched in SQL; the patient is compared
+    /// afterwards with `duplicates::same_patient`, whose name normalization
+    /// SQLite can't express. Older claims store dates as bare timestamps
+    /// rather than objects, which `json_extract` would reject.
+    pub async fn get_same_day_claims_for_patient(&self, claim: &Claim) -> Result<Vec<Claim>> {
+        let data = &claim.extracted_data;
+        let Some(date_of_service) = data.date_of_service() else {
+            return Ok(Vec::new());
+        };
+        if data.patient_id.is_none() && data.patient_name.is_none() {
+            return Ok(Vec::new());
+        }
+        let day = date_of_service.format("%Y-%m-%d").to_string();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM claims
+            WHERE id != ?
+              AND (EXISTS (SELECT 1 FROM json_each(extracted_data, '$.dates')
+                           WHERE substr(CASE WHEN type = 'object' THEN json_extract(value, '$.date') ELSE value END, 1, 10) = ?)
+                   OR EXISTS (SELECT 1 FROM json_each(extracted_data, '$.service_lines')
+                              WHERE substr(json_extract(value, '$.date_of_service'), 1, 10) = ?))
+            ORDER BY created_at
+            "#,
+        )
+        .bind(claim.id.to_string())
+        .bind(&day)
+        .bind(&day)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut claims = Vec::new();
+        for row in rows {
+            let other = self.row_to_claim(row)?;
+            if duplicates::same_patient(data, &other.extracted_data) {
+                claims.push(other);
+            }
+        }
+        Ok(claims)
+    }
+
     pub async fn update_claim(&self, claim: &Claim) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::write_claim(&mut tx, claim).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn write_claim(tx: &mut Transaction<'_, Sqlite>, claim: &Claim) -> Result<()> {
         sqlx::query(
             r#"
             UPDATE claims SET
@@ -161,12 +520,66 @@ impl Database {
         .bind(claim.updated_at.to_rfc3339())
         .bind(serde_json::to_string(&claim.comments)?)
         .bind(claim.id.to_string())
-        .execute(&self.pool)
+        .execute(&mut **tx)
         .await?;
 
         Ok(())
     }
 
+    /// Saves a fixed claim together with its history entry and audit
+    /// record, so none of them is written without the others.
+    pub async fn save_claim_fix(&self, claim: &Claim, entry: &ClaimHistoryEntry, log: &AuditLog) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::write_claim(&mut tx, claim).await?;
+        Self::insert_claim_history(&mut tx, entry).await?;
+        self.append_audit_logs(&mut tx, std::slice::from_ref(log)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_claim_history(tx: &mut Transaction<'_, Sqlite>, entry: &ClaimHistoryEntry) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO claim_history (id, claim_id, user_id, rule_id, patch, description, previous_data, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(entry.id.to_string())
+        .bind(entry.claim_id.to_string())
+        .bind(entry.user_id.to_string())
+        .bind(&entry.rule_id)
+        .bind(serde_json::to_string(&entry.patch)?)
+        .bind(&entry.description)
+        .bind(serde_json::to_string(&entry.previous_data)?)
+        .bind(entry.created_at.to_rfc3339())
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Newest change first.
+    pub async fn get_claim_history(&self, claim_id: &Uuid) -> Result<Vec<ClaimHistoryEntry>> {
+        let rows = sqlx::query("SELECT * FROM claim_history WHERE claim_id = ? ORDER BY created_at DESC")
+            .bind(claim_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ClaimHistoryEntry {
+                    id: Uuid::parse_str(&row.try_get::<String, _>("id")?)?,
+                    claim_id: Uuid::parse_str(&row.try_get::<String, _>("claim_id")?)?,
+                    user_id: Uuid::parse_str(&row.try_get::<String, _>("user_id")?)?,
+                    rule_id: row.try_get("rule_id")?,
+                    patch: serde_json::from_str(&row.try_get::<String, _>("patch")?)?,
+                    description: row.try_get("description")?,
+                    previous_data: serde_json::from_str(&row.try_get::<String, _>("previous_data")?)?,
+                    created_at: parse_timestamp(&row.try_get::<String, _>("created_at")?)?,
+                })
+            })
+            .collect()
+    }
+
     pub async fn create_user(&self, user: &User, password_hash: &str) -> Result<()> {
         sqlx::query(
             r#"
@@ -193,16 +606,25 @@ impl Database {
             .await?;
 
         if let Some(row) = row {
-            let user = self.row_to_user(row)?;
             let password_hash = row.try_get::<String, _>("password_hash")?;
+            let user = self.row_to_user(row)?;
             Ok(Some((user, password_hash)))
         } else {
             Ok(None)
         }
     }
 
+    pub async fn get_user(&self, id: &Uuid) -> Result<Option<User>> {
+        let row = sqlx::query("SELECT * FROM users WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| self.row_to_user(row)).transpose()
+    }
+
     pub async fn get_users(&self) -> Result<Vec<User>> {
-        let rows = sqlx::query("SELECT id, username, email, role, created_at, last_login FROM users")
+        let rows = sqlx::query("SELECT id, username, email, role, created_at, last_login, locked_until, mfa_enabled, is_active FROM users")
             .fetch_all(&self.pool)
             .await?;
 
@@ -214,54 +636,1133 @@ impl Database {
         Ok(users)
     }
 
-    pub async fn log_audit_event(&self, log: &AuditLog) -> Result<()> {
-        sqlx::query(
-            r#"
-            INSERT INTO audit_logs (id, user_id, action, resource_type, resource_id, details, created_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            "#,
+    pub async fn update_user(&self, user: &User) -> Result<()> {
+        sqlx::query("UPDATE users SET email = ?, role = ? WHERE id = ?")
+            .bind(&user.email)
+            .bind(serde_json::to_string(&user.role)?)
+            .bind(user.id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn count_active_admins(&self) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM users WHERE role = ? AND is_active = 1"
         )
-        .bind(log.id.to_string())
-        .bind(log.user_id.to_string())
-        .bind(&log.action)
-        .bind(&log.resource_type)
-        .bind(log.resource_id.map(|id| id.to_string()))
-        .bind(log.details.as_deref())
-        .bind(log.created_at.to_rfc3339())
-        .execute(&self.pool)
+        .bind(serde_json::to_string(&UserRole::LocalAdmin)?)
+        .fetch_one(&self.pool)
         .await?;
 
+        Ok(count)
+    }
+
+    /// Deactivates the user and moves their open claims to `reassign_to` (or
+    /// unassigns them) in one transaction. Returns how many claims moved.
+    pub async fn deactivate_user(&self, user_id: &Uuid, reassign_to: Option<&Uuid>, at: DateTime<Utc>) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE users SET is_active = 0, deactivated_at = ? WHERE id = ?")
+            .bind(at.to_rfc3339())
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        let rows = sqlx::query("SELECT id, status FROM claims WHERE assigned_to = ?")
+            .bind(user_id.to_string())
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let mut reassigned = 0;
+        for row in rows {
+            let status: ClaimStatus = serde_json::from_str(&row.try_get::<String, _>("status")?)?;
+            if !status.is_open() {
+                continue;
+            }
+
+            sqlx::query("UPDATE claims SET assigned_to = ?, updated_at = ? WHERE id = ?")
+                .bind(reassign_to.map(|id| id.to_string()))
+                .bind(at.to_rfc3339())
+                .bind(row.try_get::<String, _>("id")?)
+                .execute(&mut *tx)
+                .await?;
+            reassigned += 1;
+        }
+
+        tx.commit().await?;
+        Ok(reassigned)
+    }
+
+    pub async fn reactivate_user(&self, user_id: &Uuid) -> Result<()> {
+        sqlx::query("UPDATE users SET is_active = 1, deactivated_at = NULL WHERE id = ?")
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
 
-    pub async fn get_analytics(&self) -> Result<Analytics> {
-        let total_claims = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM claims")
+    pub async fn count_users(&self) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users")
             .fetch_one(&self.pool)
             .await?;
 
-        let critical_errors = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM claims WHERE queue = ?"
-        )
-        .bind(serde_json::to_string(&QueueType::CriticalErrors)?)
-        .fetch_one(&self.pool)
-        .await?;
+        Ok(count)
+    }
 
-        let warnings = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM claims WHERE queue = ?"
+    pub async fn update_last_login(&self, user_id: &Uuid, last_login: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE users SET last_login = ? WHERE id = ?")
+            .bind(last_login.to_rfc3339())
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_login_state(&self, user_id: &Uuid) -> Result<LoginState> {
+        let row = sqlx::query(
+            "SELECT failed_login_attempts, last_failed_login, locked_until FROM users WHERE id = ?"
         )
-        .bind(serde_json::to_string(&QueueType::WarningsOnly)?)
+        .bind(user_id.to_string())
         .fetch_one(&self.pool)
         .await?;
 
-        let approved_claims = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM claims WHERE queue = ?"
+        Ok(LoginState {
+            failed_attempts: row.try_get("failed_login_attempts")?,
+            last_failed_login: parse_optional_timestamp(row.try_get("last_failed_login")?)?,
+            locked_until: parse_optional_timestamp(row.try_get("locked_until")?)?,
+        })
+    }
+
+    /// Increments the user's consecutive failure count and returns the new value.
+    pub async fn record_login_failure(&self, user_id: &Uuid, at: DateTime<Utc>) -> Result<i64> {
+        let failed_attempts = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE users SET failed_login_attempts = failed_login_attempts + 1, last_failed_login = ?
+            WHERE id = ?
+            RETURNING failed_login_attempts
+            "#,
         )
-        .bind(serde_json::to_string(&QueueType::ApprovedClaims)?)
+        .bind(at.to_rfc3339())
+        .bind(user_id.to_string())
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(Analytics {
-            total_claims,
+        Ok(failed_attempts)
+    }
+
+    pub async fn lock_user(&self, user_id: &Uuid, until: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE users SET locked_until = ? WHERE id = ?")
+            .bind(until.to_rfc3339())
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Clears failure counters and any lock, after a good login or an admin unlock.
+    pub async fn reset_login_failures(&self, user_id: &Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users SET failed_login_attempts = 0, last_failed_login = NULL, locked_until = NULL
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn record_login_attempt(
+        &self,
+        username: &str,
+        user_id: Option<&Uuid>,
+        workstation: &str,
+        success: bool,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO login_attempts (id, username, user_id, workstation, success, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(username)
+        .bind(user_id.map(|id| id.to_string()))
+        .bind(workstation)
+        .bind(success)
+        .bind(at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn count_workstation_failures(&self, workstation: &str, since: DateTime<Utc>) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM login_attempts WHERE workstation = ? AND success = 0 AND created_at >= ?"
+        )
+        .bind(workstation)
+        .bind(since.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Drops the failures that count toward a workstation block, so an
+    /// administrator can release a shared machine. Returns how many were
+    /// cleared.
+    pub async fn clear_workstation_failures(&self, workstation: &str, since: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM login_attempts WHERE workstation = ? AND success = 0 AND created_at >= ?"
+        )
+        .bind(workstation)
+        .bind(since.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn get_mfa_credentials(&self, user_id: &Uuid) -> Result<MfaCredentials> {
+        let row = sqlx::query("SELECT mfa_secret, mfa_enabled, mfa_last_step FROM users WHERE id = ?")
+            .bind(user_id.to_string())
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(MfaCredentials {
+            secret: row.try_get("mfa_secret")?,
+            enabled: row.try_get("mfa_enabled")?,
+            last_used_step: row.try_get("mfa_last_step")?,
+        })
+    }
+
+    /// Stores a new, not yet confirmed secret. MFA stays disabled until the
+    /// user proves the authenticator works.
+    pub async fn set_pending_mfa_secret(&self, user_id: &Uuid, secret: &str) -> Result<()> {
+        sqlx::query("UPDATE users SET mfa_secret = ?, mfa_enabled = 0, mfa_last_step = NULL WHERE id = ?")
+            .bind(secret)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn enable_mfa(&self, user_id: &Uuid, last_used_step: i64, recovery_code_hashes: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE users SET mfa_enabled = 1, mfa_last_step = ? WHERE id = ?")
+            .bind(last_used_step)
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = ?")
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        for code_hash in recovery_code_hashes {
+            sqlx::query("INSERT INTO mfa_recovery_codes (id, user_id, code_hash) VALUES (?, ?, ?)")
+                .bind(Uuid::new_v4().to_string())
+                .bind(user_id.to_string())
+                .bind(code_hash)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn disable_mfa(&self, user_id: &Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE users SET mfa_secret = NULL, mfa_enabled = 0, mfa_last_step = NULL WHERE id = ?"
+        )
+        .bind(user_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = ?")
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_mfa_last_step(&self, user_id: &Uuid, step: i64) -> Result<()> {
+        sqlx::query("UPDATE users SET mfa_last_step = ? WHERE id = ?")
+            .bind(step)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks an unused recovery code as spent. Returns false if the code is
+    /// unknown or was already used.
+    pub async fn consume_recovery_code(&self, user_id: &Uuid, code_hash: &str, at: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE mfa_recovery_codes SET used_at = ? WHERE user_id = ? AND code_hash = ? AND used_at IS NULL"
+        )
+        .bind(at.to_rfc3339())
+        .bind(user_id.to_string())
+        .bind(code_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let value = sqlx::query_scalar::<_, String>("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(value)
+    }
+
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value"
+        )
+        .bind(key)
+        .bind(value)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The newest rule configuration, or the empty version 0 if none was saved.
+    pub async fn get_rule_configuration(&self) -> Result<RuleConfiguration> {
+        let row = sqlx::query(
+            "SELECT version, overrides, created_by, created_at FROM rule_configurations ORDER BY version DESC LIMIT 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(RuleConfiguration {
+                version: row.try_get("version")?,
+                overrides: serde_json::from_str(&row.try_get::<String, _>("overrides")?)?,
+                created_by: Some(Uuid::parse_str(&row.try_get::<String, _>("created_by")?)?),
+                created_at: Some(parse_timestamp(&row.try_get::<String, _>("created_at")?)?),
+            }),
+            None => Ok(RuleConfiguration::default()),
+        }
+    }
+
+    /// Saves the overrides as a new configuration version.
+    pub async fn save_rule_configuration(&self, overrides: &[RuleOverride], created_by: &Uuid) -> Result<RuleConfiguration> {
+        let created_at = Utc::now();
+        let version = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO rule_configurations (overrides, created_by, created_at) VALUES (?, ?, ?) RETURNING version"
+        )
+        .bind(serde_json::to_string(overrides)?)
+        .bind(created_by.to_string())
+        .bind(created_at.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(RuleConfiguration {
+            version,
+            overrides: overrides.to_vec(),
+            created_by: Some(*created_by),
+            created_at: Some(created_at),
+        })
+    }
+
+    /// Replaces the stored code set for one fiscal year.
+    pub async fn replace_icd10_codes(&self, fiscal_year: i32, codes: &[Icd10Code]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM icd10_codes WHERE fiscal_year = ?")
+            .bind(fiscal_year)
+            .execute(&mut *tx)
+            .await?;
+
+        for code in codes {
+            sqlx::query(
+                "INSERT OR REPLACE INTO icd10_codes (code, fiscal_year, description, billable) VALUES (?, ?, ?, ?)"
+            )
+            .bind(&code.code)
+            .bind(fiscal_year)
+            .bind(&code.description)
+            .bind(code.billable)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Adds or updates code periods; codes not in the file are kept, so
+    /// CPT and HCPCS files and quarterly updates can be loaded separately.
+    pub async fn upsert_procedure_codes(&self, codes: &[ProcedureCode]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for code in codes {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO procedure_codes (code, effective_date, code_type, description, termination_date)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&code.code)
+            .bind(code.effective_date.map(|d| d.to_string()).unwrap_or_default())
+            .bind(&code.code_type)
+            .bind(&code.description)
+            .bind(code.termination_date)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Adds or updates PTP edits. CMS splits each quarter's table across
+    /// several files, so imports never remove edits from earlier files.
+    pub async fn upsert_ncci_edits(&self, edits: &[NcciEdit]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for edit in edits {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO ncci_ptp_edits
+                    (setting, column1, column2, effective_date, deletion_date, modifier_indicator, rationale)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(edit.setting.as_str())
+            .bind(&edit.column1)
+            .bind(&edit.column2)
+            .bind(edit.effective_date)
+            .bind(edit.deletion_date)
+            .bind(edit.modifier_indicator as i64)
+            .bind(&edit.rationale)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replaces the MUE table for one setting; CMS publishes each quarter's
+    /// values as a complete file.
+    pub async fn replace_mue_limits(&self, setting: NcciSetting, limits: &[MueLimit]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM mue_limits WHERE setting = ?")
+            .bind(setting.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+        for limit in limits {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO mue_limits (setting, code, mue_value, adjudication_indicator, rationale)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(setting.as_str())
+            .bind(&limit.code)
+            .bind(limit.mue_value as i64)
+            .bind(limit.adjudication_indicator as i64)
+            .bind(&limit.rationale)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn upsert_modifiers(&self, modifiers: &[Modifier]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for modifier in modifiers {
+            sqlx::query("INSERT OR REPLACE INTO modifiers (code, description) VALUES (?, ?)")
+                .bind(&modifier.code)
+                .bind(&modifier.description)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replaces every policy present in the import; policies not in the file
+    /// are left alone, since LCDs are usually downloaded one at a time.
+    pub async fn replace_coverage_policies(&self, rules: &[CoverageRule]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut policy_ids: Vec<&str> = rules.iter().map(|rule| rule.policy_id.as_str()).collect();
+        policy_ids.sort();
+        policy_ids.dedup();
+        for policy_id in policy_ids {
+            sqlx::query("DELETE FROM coverage_policies WHERE policy_id = ?")
+                .bind(policy_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for rule in rules {
+            sqlx::query(
+                r#"
+                INSERT INTO coverage_policies
+                    (policy_id, title, policy_type, jurisdiction, procedure_code, icd10_start, icd10_end, effective_date, end_date)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&rule.policy_id)
+            .bind(&rule.title)
+            .bind(&rule.policy_type)
+            .bind(&rule.jurisdiction)
+            .bind(&rule.procedure_code)
+            .bind(&rule.icd10_start)
+            .bind(&rule.icd10_end)
+            .bind(rule.effective_date)
+            .bind(rule.end_date)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn upsert_code_attributes(&self, attributes: &[CodeAttribute]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for attribute in attributes {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO code_attributes (code_system, code, min_age, max_age, sex, description)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(attribute.code_system.as_str())
+            .bind(&attribute.code)
+            .bind(attribute.min_age.map(i64::from))
+            .bind(attribute.max_age.map(i64::from))
+            .bind(attribute.sex.map(|sex| sex.as_str()))
+            .bind(&attribute.description)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_payers(&self) -> Result<Vec<Payer>> {
+        let rows = sqlx::query("SELECT * FROM payers ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Payer {
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                    aliases: serde_json::from_str(&row.try_get::<String, _>("aliases")?)?,
+                    filing_limit_days: row.try_get::<Option<i64>, _>("filing_limit_days")?.map(|days| days as u32),
+                    secondary_filing_limit_days: row.try_get::<Option<i64>, _>("secondary_filing_limit_days")?
+                        .map(|days| days as u32),
+                })
+            })
+            .collect()
+    }
+
+    pub async fn save_payer(&self, payer: &Payer) -> Result<()> {
+        self.insert_payer(payer, "INSERT OR REPLACE").await
+    }
+
+    async fn insert_payer(&self, payer: &Payer, verb: &str) -> Result<()> {
+        sqlx::query(&format!(
+            "{} INTO payers (id, name, aliases, filing_limit_days, secondary_filing_limit_days) VALUES (?, ?, ?, ?, ?)",
+            verb,
+        ))
+        .bind(&payer.id)
+        .bind(&payer.name)
+        .bind(serde_json::to_string(&payer.aliases)?)
+        .bind(payer.filing_limit_days.map(i64::from))
+        .bind(payer.secondary_filing_limit_days.map(i64::from))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Payers seeded before the parser keyword became their alias still
+    /// have only their lowercased name (so "unitedhealthcare" never matched
+    /// the parser's "unitedhealth"). Untouched seeded rows get the keyword.
+    async fn add_default_payer_aliases(&self) -> Result<()> {
+        let stored = self.get_payers().await?;
+        for default in payers::default_payers() {
+            let Some(payer) = stored.iter().find(|payer| payer.id == default.id) else {
+                continue;
+            };
+            if payer.aliases != [default.name.to_lowercase()] || payer.aliases == default.aliases {
+                continue;
+            }
+
+            let mut payer = payer.clone();
+            payer.aliases.extend(default.aliases);
+            self.insert_payer(&payer, "INSERT OR REPLACE").await?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the payer existed.
+    pub async fn delete_payer(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM payers WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_rule_packs(&self) -> Result<Vec<RulePack>> {
+        let rows = sqlx::query("SELECT * FROM rule_packs ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(RulePack {
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                    payer_id: row.try_get("payer_id")?,
+                    plan: row.try_get("plan")?,
+                    extends: row.try_get("extends")?,
+                    rules: serde_json::from_str(&row.try_get::<String, _>("rules")?)?,
+                    overrides: serde_json::from_str(&row.try_get::<String, _>("overrides")?)?,
+                    updated_at: Some(parse_timestamp(&row.try_get::<String, _>("updated_at")?)?),
+                    active: row.try_get("active")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Saves the packs in one transaction, stamping them with the save time.
+    pub async fn save_rule_packs(&self, packs: &[RulePack]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let updated_at = Utc::now().to_rfc3339();
+
+        for pack in packs {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO rule_packs (id, name, payer_id, plan, extends, rules, overrides, updated_at, active)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&pack.id)
+            .bind(&pack.name)
+            .bind(&pack.payer_id)
+            .bind(&pack.plan)
+            .bind(&pack.extends)
+            .bind(serde_json::to_string(&pack.rules)?)
+            .bind(serde_json::to_string(&pack.overrides)?)
+            .bind(&updated_at)
+            .bind(pack.active)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Returns whether the pack existed.
+    pub async fn delete_rule_pack(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM rule_packs WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces the fee schedule for one source and locality.
+    pub async fn replace_fee_schedule(&self, source: FeeSource, locality: &str, entries: &[FeeScheduleEntry]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM fee_schedule WHERE source = ? AND locality = ?")
+            .bind(source.as_str())
+            .bind(locality)
+            .execute(&mut *tx)
+            .await?;
+
+        for entry in entries {
+            sqlx::query(
+                "INSERT OR REPLACE INTO fee_schedule (source, locality, code, modifier, amount) VALUES (?, ?, ?, ?, ?)"
+            )
+            .bind(source.as_str())
+            .bind(locality)
+            .bind(&entry.code)
+            .bind(&entry.modifier)
+            .bind(entry.amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Looks up the imported code set rows and the patient's other claims
+    /// needed to run rules on a claim.
+    pub async fn load_reference_data(&self, claim: &Claim, settings: &Settings) -> Result<ReferenceData> {
+        let shared = self.load_shared_reference_data().await?;
+        let mut reference = self.load_claim_reference_data(claim, settings, &shared).await?;
+        reference.related_claims = self.get_same_day_claims_for_patient(claim).await?;
+        Ok(reference)
+    }
+
+    pub async fn load_shared_reference_data(&self) -> Result<SharedReferenceData> {
+        Ok(SharedReferenceData {
+            icd10_fiscal_years: sqlx::query_scalar("SELECT DISTINCT fiscal_year FROM icd10_codes ORDER BY fiscal_year")
+                .fetch_all(&self.pool)
+                .await?,
+            procedure_codes_loaded: sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM procedure_codes")
+                .fetch_one(&self.pool)
+                .await? > 0,
+            modifier_codes: sqlx::query_scalar::<_, String>("SELECT code FROM modifiers")
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect(),
+            payers: self.get_payers().await?,
+        })
+    }
+
+    /// The code set rows for a claim, with the claim-independent ones
+    /// already loaded by `load_shared_reference_data`. Unlike
+    /// `load_reference_data` this leaves `related_claims` empty, so the
+    /// results don't depend on the claims stored.
+    pub async fn load_claim_reference_data(
+        &self,
+        claim: &Claim,
+        settings: &Settings,
+        shared: &SharedReferenceData,
+    ) -> Result<ReferenceData> {
+        let data = &claim.extracted_data;
+        let diagnosis_codes: Vec<String> = data.diagnosis_codes.iter().map(|c| icd10::normalize(c)).collect();
+        let mut procedure_codes: Vec<String> = data.cpt_codes.clone();
+        procedure_codes.extend(data.service_lines.iter().map(|line| line.procedure_code.clone()));
+        procedure_codes.sort();
+        procedure_codes.dedup();
+
+        let mut reference = ReferenceData {
+            icd10_fiscal_years: shared.icd10_fiscal_years.clone(),
+            procedure_codes_loaded: shared.procedure_codes_loaded,
+            modifier_codes: shared.modifier_codes.clone(),
+            payer: data.payer.as_deref().and_then(|name| payers::find(&shared.payers, name)).cloned(),
+            ..Default::default()
+        };
+
+        if !diagnosis_codes.is_empty() && !reference.icd10_fiscal_years.is_empty() {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "SELECT code, fiscal_year, description, billable FROM icd10_codes WHERE code IN "
+            );
+            push_in_list(&mut query, &diagnosis_codes);
+
+            for row in query.build().fetch_all(&self.pool).await? {
+                let code = Icd10Code {
+                    code: row.try_get("code")?,
+                    description: row.try_get("description")?,
+                    billable: row.try_get("billable")?,
+                    fiscal_year: row.try_get("fiscal_year")?,
+                };
+                reference.icd10_codes.entry(code.code.clone()).or_default().push(code);
+            }
+        }
+
+        let mut diagnosis_prefixes: Vec<String> = diagnosis_codes.iter().flat_map(|c| demographics::icd10_prefixes(c)).collect();
+        diagnosis_prefixes.sort();
+        diagnosis_prefixes.dedup();
+        if !diagnosis_prefixes.is_empty() || !procedure_codes.is_empty() {
+            let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM code_attributes WHERE (code_system = 'icd10' AND code IN ");
+            push_in_list(&mut query, &diagnosis_prefixes);
+            query.push(") OR (code_system = 'procedure' AND code IN ");
+            push_in_list(&mut query, &procedure_codes);
+            query.push(")");
+
+            for row in query.build().fetch_all(&self.pool).await? {
+                reference.code_attributes.push(CodeAttribute {
+                    code_system: match row.try_get::<String, _>("code_system")?.as_str() {
+                        "icd10" => CodeSystem::Icd10,
+                        _ => CodeSystem::Procedure,
+                    },
+                    code: row.try_get("code")?,
+                    min_age: row.try_get::<Option<i64>, _>("min_age")?.map(|age| age as u32),
+                    max_age: row.try_get::<Option<i64>, _>("max_age")?.map(|age| age as u32),
+                    sex: row.try_get::<Option<String>, _>("sex")?.as_deref().and_then(Sex::parse),
+                    description: row.try_get("description")?,
+                });
+            }
+        }
+
+        if procedure_codes.is_empty() {
+            return Ok(reference);
+        }
+
+        if reference.procedure_codes_loaded {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "SELECT code, effective_date, code_type, description, termination_date FROM procedure_codes WHERE code IN "
+            );
+            push_in_list(&mut query, &procedure_codes);
+
+            for row in query.build().fetch_all(&self.pool).await? {
+                let code = ProcedureCode {
+                    code: row.try_get("code")?,
+                    code_type: row.try_get("code_type")?,
+                    description: row.try_get("description")?,
+                    effective_date: row.try_get::<String, _>("effective_date")?.parse().ok(),
+                    termination_date: row.try_get("termination_date")?,
+                };
+                reference.procedure_codes.entry(code.code.clone()).or_default().push(code);
+            }
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM ncci_ptp_edits WHERE setting = ");
+        query.push_bind(settings.ncci_setting.as_str());
+        query.push(" AND column1 IN ");
+        push_in_list(&mut query, &procedure_codes);
+        query.push(" AND column2 IN ");
+        push_in_list(&mut query, &procedure_codes);
+
+        for row in query.build().fetch_all(&self.pool).await? {
+            reference.ncci_edits.push(NcciEdit {
+                setting: settings.ncci_setting,
+                column1: row.try_get("column1")?,
+                column2: row.try_get("column2")?,
+                effective_date: row.try_get("effective_date")?,
+                deletion_date: row.try_get("deletion_date")?,
+                modifier_indicator: row.try_get::<i64, _>("modifier_indicator")? as u8,
+                rationale: row.try_get("rationale")?,
+            });
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM mue_limits WHERE setting = ");
+        query.push_bind(settings.ncci_setting.as_str());
+        query.push(" AND code IN ");
+        push_in_list(&mut query, &procedure_codes);
+
+        for row in query.build().fetch_all(&self.pool).await? {
+            let limit = MueLimit {
+                setting: settings.ncci_setting,
+                code: row.try_get("code")?,
+                mue_value: row.try_get::<i64, _>("mue_value")? as u32,
+                adjudication_indicator: row.try_get::<i64, _>("adjudication_indicator")? as u8,
+                rationale: row.try_get("rationale")?,
+            };
+            reference.mue_limits.insert(limit.code.clone(), limit);
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM fee_schedule WHERE code IN ");
+        push_in_list(&mut query, &procedure_codes);
+        query.push(" AND (source = 'chargemaster' OR (source = 'medicare' AND locality = ");
+        query.push_bind(settings.fee_schedule_locality.clone().unwrap_or_default());
+        query.push("))");
+
+        for row in query.build().fetch_all(&self.pool).await? {
+            reference.fee_schedule.push(FeeScheduleEntry {
+                source: match row.try_get::<String, _>("source")?.as_str() {
+                    "medicare" => FeeSource::Medicare,
+                    _ => FeeSource::Chargemaster,
+                },
+                locality: row.try_get("locality")?,
+                code: row.try_get("code")?,
+                modifier: row.try_get("modifier")?,
+                amount: row.try_get("amount")?,
+            });
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM coverage_policies WHERE procedure_code IN ");
+        push_in_list(&mut query, &procedure_codes);
+        query.push(" AND (jurisdiction IS NULL OR jurisdiction = ");
+        query.push_bind(settings.coverage_jurisdiction.clone().unwrap_or_default());
+        query.push(")");
+
+        for row in query.build().fetch_all(&self.pool).await? {
+            reference.coverage_rules.push(CoverageRule {
+                policy_id: row.try_get("policy_id")?,
+                title: row.try_get("title")?,
+                policy_type: row.try_get("policy_type")?,
+                jurisdiction: row.try_get("jurisdiction")?,
+                procedure_code: row.try_get("procedure_code")?,
+                icd10_start: row.try_get("icd10_start")?,
+                icd10_end: row.try_get("icd10_end")?,
+                effective_date: row.try_get("effective_date")?,
+                end_date: row.try_get("end_date")?,
+            });
+        }
+
+        Ok(reference)
+    }
+
+    /// Appends an entry to the audit chain.
+    pub async fn log_audit_event(&self, log: &AuditLog) -> Result<()> {
+        self.log_audit_events(std::slice::from_ref(log)).await
+    }
+
+    /// Appends several entries in one transaction so they are sequenced
+    /// back to back.
+    pub async fn log_audit_events(&self, logs: &[AuditLog]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        self.append_audit_logs(&mut tx, logs).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn append_audit_logs(&self, tx: &mut Transaction<'_, Sqlite>, logs: &[AuditLog]) -> Result<()> {
+        let mut previous = self.audit_chain_head(tx).await?;
+        if !logs.is_empty() {
+            let next_sequence = previous.as_ref().map_or(1, |(sequence, _)| sequence + 1);
+            self.record_audit_signing_start(tx, next_sequence).await?;
+        }
+        for log in logs {
+            let mut log = log.clone();
+            audit::seal(&mut log, previous, self.audit_signing_key.as_deref());
+            Self::insert_audit_log(tx, &log).await?;
+            previous = Some((log.sequence, log.entry_hash));
+        }
+        Ok(())
+    }
+
+    /// Notes the first sequence sealed with the signing key, once. Later
+    /// entries without a signature then fail verification.
+    async fn record_audit_signing_start(&self, tx: &mut Transaction<'_, Sqlite>, sequence: i64) -> Result<()> {
+        let Some(key) = self.audit_signing_key.as_deref() else {
+            return Ok(());
+        };
+
+        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO NOTHING")
+            .bind(audit::SIGNING_START_KEY)
+            .bind(audit::signing_start_marker(sequence, key))
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Databases signed before the start was recorded get it from their
+    /// earliest signed entry.
+    async fn backfill_audit_signing_start(&self) -> Result<()> {
+        if self.audit_signing_key.is_none() || self.get_setting(audit::SIGNING_START_KEY).await?.is_some() {
+            return Ok(());
+        }
+
+        let first_signed = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MIN(sequence) FROM audit_logs WHERE signature IS NOT NULL"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        if let Some(sequence) = first_signed {
+            let mut tx = self.pool.begin().await?;
+            self.record_audit_signing_start(&mut tx, sequence).await?;
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+
+    async fn audit_chain_head(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<Option<(i64, String)>> {
+        let row = sqlx::query(
+            "SELECT sequence, entry_hash FROM audit_logs WHERE sequence IS NOT NULL ORDER BY sequence DESC LIMIT 1"
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        row.map(|row| Ok((row.try_get("sequence")?, row.try_get("entry_hash")?)))
+            .transpose()
+    }
+
+    async fn insert_audit_log(tx: &mut Transaction<'_, Sqlite>, log: &AuditLog) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_logs (
+                id, user_id, action, resource_type, resource_id, details, created_at,
+                sequence, prev_hash, entry_hash, signature
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(log.id.to_string())
+        .bind(log.user_id.to_string())
+        .bind(&log.action)
+        .bind(&log.resource_type)
+        .bind(log.resource_id.map(|id| id.to_string()))
+        .bind(log.details.as_deref())
+        .bind(log.created_at.to_rfc3339())
+        .bind(log.sequence)
+        .bind(&log.prev_hash)
+        .bind(&log.entry_hash)
+        .bind(log.signature.as_deref())
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Entries written before the chain existed are sealed onto it once, in
+    /// the order they were created.
+    async fn chain_unsealed_audit_logs(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query("SELECT * FROM audit_logs WHERE entry_hash IS NULL ORDER BY created_at")
+            .fetch_all(&mut *tx)
+            .await?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut previous = self.audit_chain_head(&mut tx).await?;
+        let next_sequence = previous.as_ref().map_or(1, |(sequence, _)| sequence + 1);
+        self.record_audit_signing_start(&mut tx, next_sequence).await?;
+        for row in rows {
+            let mut log = self.row_to_audit_log(row)?;
+            audit::seal(&mut log, previous, self.audit_signing_key.as_deref());
+
+            sqlx::query(
+                "UPDATE audit_logs SET sequence = ?, prev_hash = ?, entry_hash = ?, signature = ? WHERE id = ?"
+            )
+            .bind(log.sequence)
+            .bind(&log.prev_hash)
+            .bind(&log.entry_hash)
+            .bind(log.signature.as_deref())
+            .bind(log.id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+            previous = Some((log.sequence, log.entry_hash));
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub fn audit_signing_key(&self) -> Option<&[u8]> {
+        self.audit_signing_key.as_deref()
+    }
+
+    /// Filtered audit entries in chain order, with the total match count for
+    /// paging. Passing `None` for `page` returns every match.
+    pub async fn query_audit_logs(&self, query: &AuditLogQuery, page: Option<(i64, i64)>) -> Result<(Vec<AuditLog>, i64)> {
+        let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM audit_logs");
+        Self::push_audit_filters(&mut count, query);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM audit_logs");
+        Self::push_audit_filters(&mut select, query);
+        select.push(" ORDER BY sequence");
+        if let Some((limit, offset)) = page {
+            select.push(" LIMIT ").push_bind(limit);
+            select.push(" OFFSET ").push_bind(offset);
+        }
+
+        let rows = select.build().fetch_all(&self.pool).await?;
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            entries.push(self.row_to_audit_log(row)?);
+        }
+
+        Ok((entries, total))
+    }
+
+    fn push_audit_filters(builder: &mut QueryBuilder<'_, Sqlite>, query: &AuditLogQuery) {
+        builder.push(" WHERE 1 = 1");
+
+        if let Some(user_id) = &query.user_id {
+            builder.push(" AND user_id = ").push_bind(user_id.to_string());
+        }
+        if let Some(action) = &query.action {
+            builder.push(" AND action = ").push_bind(action.clone());
+        }
+        if let Some(resource_type) = &query.resource_type {
+            builder.push(" AND resource_type = ").push_bind(resource_type.clone());
+        }
+        if let Some(resource_id) = &query.resource_id {
+            builder.push(" AND resource_id = ").push_bind(resource_id.to_string());
+        }
+        if let Some(patient) = &query.patient {
+            builder.push(
+                " AND resource_type = 'claim' AND resource_id IN (SELECT id FROM claims \
+                 WHERE json_extract(extracted_data, '$.patient_id') = "
+            );
+            builder.push_bind(patient.clone());
+            builder.push(" OR lower(json_extract(extracted_data, '$.patient_name')) = lower(");
+            builder.push_bind(patient.clone());
+            builder.push("))");
+        }
+        if let Some(from) = &query.from {
+            builder.push(" AND created_at >= ").push_bind(from.to_rfc3339());
+        }
+        if let Some(to) = &query.to {
+            builder.push(" AND created_at <= ").push_bind(to.to_rfc3339());
+        }
+    }
+
+    pub async fn verify_audit_chain(&self) -> Result<AuditVerification> {
+        let rows = sqlx::query("SELECT * FROM audit_logs ORDER BY sequence")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            entries.push(self.row_to_audit_log(row)?);
+        }
+
+        let signing_start = self.get_setting(audit::SIGNING_START_KEY).await?;
+        Ok(audit::verify_chain(&entries, self.audit_signing_key.as_deref(), signing_start.as_deref()))
+    }
+
+    pub async fn get_analytics(&self) -> Result<Analytics> {
+        let total_claims = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM claims")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let critical_errors = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM claims WHERE queue = ?"
+        )
+        .bind(serde_json::to_string(&QueueType::CriticalErrors)?)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let warnings = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM claims WHERE queue = ?"
+        )
+        .bind(serde_json::to_string(&QueueType::WarningsOnly)?)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let approved_claims = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM claims WHERE queue = ?"
+        )
+        .bind(serde_json::to_string(&QueueType::ApprovedClaims)?)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Analytics {
+            total_claims,
             critical_errors,
             warnings,
             approved_claims,
@@ -304,7 +1805,212 @@ impl Database {
                 .map(|s| DateTime::parse_from_rfc3339(&s))
                 .transpose()?
                 .map(|dt| dt.with_timezone(&Utc)),
+            locked_until: parse_optional_timestamp(row.try_get("locked_until")?)?,
+            mfa_enabled: row.try_get("mfa_enabled")?,
+            is_active: row.try_get("is_active")?,
         })
     }
+
+    fn row_to_audit_log(&self, row: sqlx::sqlite::SqliteRow) -> Result<AuditLog> {
+        Ok(AuditLog {
+            id: Uuid::parse_str(&row.try_get::<String, _>("id")?)?,
+            user_id: Uuid::parse_str(&row.try_get::<String, _>("user_id")?)?,
+            action: row.try_get("action")?,
+            resource_type: row.try_get("resource_type")?,
+            resource_id: row.try_get::<Option<String>, _>("resource_id")?
+                .map(|s| Uuid::parse_str(&s))
+                .transpose()?,
+            details: row.try_get("details")?,
+            created_at: parse_timestamp(&row.try_get::<String, _>("created_at")?)?,
+            sequence: row.try_get::<Option<i64>, _>("sequence")?.unwrap_or(0),
+            prev_hash: row.try_get::<Option<String>, _>("prev_hash")?.unwrap_or_default(),
+            entry_hash: row.try_get::<Option<String>, _>("entry_hash")?.unwrap_or_default(),
+            signature: row.try_get("signature")?,
+        })
+    }
+}
+
+/// Appends `(?, ?, ...)` binding each value.
+fn push_in_list(builder: &mut QueryBuilder<'_, Sqlite>, values: &[String]) {
+    builder.push("(");
+    let mut separated = builder.separated(", ");
+    for value in values {
+        separated.push_bind(value.clone());
+    }
+    builder.push(")");
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
 }
 
+fn parse_optional_timestamp(value: Option<String>) -> Result<Option<DateTime<Utc>>> {
+    Ok(value
+        .map(|s| DateTime::parse_from_rfc3339(&s))
+        .transpose()?
+        .map(|dt| dt.with_timezone(&Utc)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_clearing_workstation_keeps_other_machines_and_successes() {
+        let db = Database::in_memory().await.unwrap();
+        let now = Utc::now();
+        for _ in 0..crate::auth::WORKSTATION_MAX_FAILURES {
+            db.record_login_attempt("someone", None, "shared-desk", false, now).await.unwrap();
+        }
+        db.record_login_attempt("someone", None, "shared-desk", true, now).await.unwrap();
+        db.record_login_attempt("someone", None, "other-desk", false, now).await.unwrap();
+
+        let since = now - Duration::minutes(crate::auth::WORKSTATION_WINDOW_MINUTES);
+        assert_eq!(db.count_workstation_failures("shared-desk", since).await.unwrap(), crate::auth::WORKSTATION_MAX_FAILURES);
+
+        let cleared = db.clear_workstation_failures("shared-desk", since).await.unwrap();
+        assert_eq!(cleared, crate::auth::WORKSTATION_MAX_FAILURES as u64);
+        assert_eq!(db.count_workstation_failures("shared-desk", since).await.unwrap(), 0);
+        assert_eq!(db.count_workstation_failures("other-desk", since).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_filters_and_paging() {
+        let db = Database::in_memory().await.unwrap();
+        let patient = ExtractedData { patient_name: Some("Jane Doe".to_string()), ..Default::default() };
+        let claim = crate::rule_tests::fixture_claim(&patient);
+        let other = crate::rule_tests::fixture_claim(&ExtractedData::default());
+        db.create_claim(&claim).await.unwrap();
+        db.create_claim(&other).await.unwrap();
+
+        let coder = Uuid::new_v4();
+        let auditor = Uuid::new_v4();
+        let start = Utc::now();
+        let mut logs = vec![
+            AuditLog::new(coder, "claim_viewed", "claim", Some(claim.id), None),
+            AuditLog::new(coder, "claim_updated", "claim", Some(claim.id), None),
+            AuditLog::new(coder, "claim_viewed", "claim", Some(other.id), None),
+            AuditLog::new(auditor, "audit_log_exported", "audit_log", None, None),
+        ];
+        for (minutes, log) in logs.iter_mut().enumerate() {
+            log.created_at = start + Duration::minutes(minutes as i64);
+        }
+        db.log_audit_events(&logs).await.unwrap();
+
+        let sequences = |entries: Vec<AuditLog>| entries.iter().map(|e| e.sequence).collect::<Vec<_>>();
+
+        let by_user_and_action = AuditLogQuery {
+            user_id: Some(coder),
+            action: Some("claim_viewed".to_string()),
+            ..Default::default()
+        };
+        let (entries, total) = db.query_audit_logs(&by_user_and_action, None).await.unwrap();
+        assert_eq!((sequences(entries), total), (vec![1, 3], 2));
+
+        let by_patient = AuditLogQuery { patient: Some("jane doe".to_string()), ..Default::default() };
+        let (entries, _) = db.query_audit_logs(&by_patient, None).await.unwrap();
+        assert_eq!(sequences(entries), [1, 2]);
+
+        let by_time = AuditLogQuery {
+            from: Some(start + Duration::minutes(1)),
+            to: Some(start + Duration::minutes(2)),
+            ..Default::default()
+        };
+        let (entries, _) = db.query_audit_logs(&by_time, None).await.unwrap();
+        assert_eq!(sequences(entries), [2, 3]);
+
+        let (entries, total) = db.query_audit_logs(&AuditLogQuery::default(), Some((2, 1))).await.unwrap();
+        assert_eq!((sequences(entries), total), (vec![2, 3], 4));
+    }
+
+    #[tokio::test]
+    async fn test_same_day_claims_match_normalized_patient_names() {
+        let db = Database::in_memory().await.unwrap();
+        let on = |name: &str, day: u32| {
+            let date = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, day, 0, 0, 0).unwrap();
+            crate::rule_tests::fixture_claim(&ExtractedData {
+                patient_name: Some(name.to_string()),
+                dates: vec![ExtractedDate::new(DateRole::ServiceFrom, date)],
+                ..Default::default()
+            })
+        };
+
+        let claim = on("Jane Doe", 15);
+        let spaced = on("JANE  DOE.", 15);
+        let other_day = on("Jane Doe", 16);
+        let other_patient = on("John Doe", 15);
+        for stored in [&claim, &spaced, &other_day, &other_patient] {
+            db.create_claim(stored).await.unwrap();
+        }
+
+        let found = db.get_same_day_claims_for_patient(&claim).await.unwrap();
+        let ids: Vec<Uuid> = found.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [spaced.id]);
+    }
+
+    #[tokio::test]
+    async fn test_rules_run_alongside_claims_with_bare_stored_dates() {
+        let db = Database::in_memory().await.unwrap();
+        let date = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 15, 0, 0, 0).unwrap();
+        let claim = crate::rule_tests::fixture_claim(&ExtractedData {
+            patient_name: Some("Jane Doe".to_string()),
+            cpt_codes: vec!["99213".to_string()],
+            dates: vec![ExtractedDate::new(DateRole::ServiceFrom, date)],
+            ..Default::default()
+        });
+        db.create_claim(&claim).await.unwrap();
+
+        // Claims saved before dates had roles store them as bare timestamps.
+        let legacy = crate::rule_tests::fixture_claim(&ExtractedData::default());
+        db.create_claim(&legacy).await.unwrap();
+        let mut stored = serde_json::to_value(&claim.extracted_data).unwrap();
+        stored["dates"] = serde_json::json!([date.to_rfc3339()]);
+        sqlx::query("UPDATE claims SET extracted_data = ? WHERE id = ?")
+            .bind(stored.to_string())
+            .bind(legacy.id.to_string())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let reference = db.load_reference_data(&claim, &Settings::default()).await.unwrap();
+        let ids: Vec<Uuid> = reference.related_claims.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [legacy.id]);
+        let results = crate::rules::RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        assert!(results.iter().any(|r| r.rule_id == "duplicate_claim"));
+    }
+
+    #[tokio::test]
+    async fn test_claim_fix_is_saved_with_history_and_audit_or_not_at_all() {
+        let db = Database::in_memory().await.unwrap();
+        let mut claim = crate::rule_tests::fixture_claim(&ExtractedData::default());
+        db.create_claim(&claim).await.unwrap();
+        let user_id = Uuid::new_v4();
+
+        let previous_data = claim.extracted_data.clone();
+        claim.extracted_data.payer = Some("Aetna".to_string());
+        let entry = ClaimHistoryEntry {
+            id: Uuid::new_v4(),
+            claim_id: claim.id,
+            user_id,
+            rule_id: Some("missing_payer".to_string()),
+            patch: FixPatch::SetField { field: "payer".to_string(), value: serde_json::json!("Aetna") },
+            description: "Set payer to \"Aetna\"".to_string(),
+            previous_data,
+            created_at: Utc::now(),
+        };
+        let log = || AuditLog::new(user_id, "claim_fix_applied", "claim", Some(claim.id), None);
+        db.save_claim_fix(&claim, &entry, &log()).await.unwrap();
+
+        // Reusing the history id makes the second write fail part way.
+        let mut again = claim.clone();
+        again.extracted_data.payer = Some("Cigna".to_string());
+        assert!(db.save_claim_fix(&again, &entry, &log()).await.is_err());
+
+        let stored = db.get_claim(&claim.id).await.unwrap().unwrap();
+        assert_eq!(stored.extracted_data.payer.as_deref(), Some("Aetna"));
+        assert_eq!(db.get_claim_history(&claim.id).await.unwrap().len(), 1);
+        let (_, total) = db.query_audit_logs(&AuditLogQuery::default(), None).await.unwrap();
+        assert_eq!(total, 1);
+    }
+}
@@ -0,0 +1,1023 @@
+use std::collections::HashMap;
+use std::fmt;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::demographics;
+use crate::evidence;
+use crate::fixes;
+use crate::rules::BUILTIN_RULE_IDS;
+use crate::types::{ExtractedData, RuleTest, ServiceLine, Severity, ValidationResult};
+
+/// A problem in a rule definition, located by a JSON path such as
+/// `rules[2].condition.all[0].op`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleConfigError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for RuleConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+pub fn format_errors(errors: &[RuleConfigError]) -> String {
+    errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleScope {
+    Claim,
+    ServiceLine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Text,
+    Number,
+    List,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Payer,
+    PatientName,
+    PatientId,
+    PatientAge,
+    PatientSex,
+    ProviderName,
+    ProviderNpi,
+    BillingNpi,
+    RenderingNpi,
+    CptCodes,
+    Modifiers,
+    DiagnosisCodes,
+    TotalCharge,
+    ServiceLineCount,
+    DateOfService,
+    LineNumber,
+    LineProcedureCode,
+    LineModifiers,
+    LineUnits,
+    LineCharge,
+    LineDateOfService,
+}
+
+/// Every field a rule may reference, with its kind and whether it only makes
+/// sense for `service_line` rules.
+const FIELDS: &[(&str, Field, FieldKind, bool)] = &[
+    ("payer", Field::Payer, FieldKind::Text, false),
+    ("patient_name", Field::PatientName, FieldKind::Text, false),
+    ("patient_id", Field::PatientId, FieldKind::Text, false),
+    ("patient_age", Field::PatientAge, FieldKind::Number, false),
+    ("patient_sex", Field::PatientSex, FieldKind::Text, false),
+    ("provider_name", Field::ProviderName, FieldKind::Text, false),
+    ("provider_npi", Field::ProviderNpi, FieldKind::Text, false),
+    ("billing_npi", Field::BillingNpi, FieldKind::Text, false),
+    ("rendering_npi", Field::RenderingNpi, FieldKind::Text, false),
+    ("cpt_codes", Field::CptCodes, FieldKind::List, false),
+    ("modifiers", Field::Modifiers, FieldKind::List, false),
+    ("diagnosis_codes", Field::DiagnosisCodes, FieldKind::List, false),
+    ("total_charge", Field::TotalCharge, FieldKind::Number, false),
+    ("service_line_count", Field::ServiceLineCount, FieldKind::Number, false),
+    ("date_of_service", Field::DateOfService, FieldKind::Text, false),
+    ("line.number", Field::LineNumber, FieldKind::Number, true),
+    ("line.procedure_code", Field::LineProcedureCode, FieldKind::Text, true),
+    ("line.modifiers", Field::LineModifiers, FieldKind::List, true),
+    ("line.units", Field::LineUnits, FieldKind::Number, true),
+    ("line.charge", Field::LineCharge, FieldKind::Number, true),
+    ("line.date_of_service", Field::LineDateOfService, FieldKind::Text, true),
+];
+
+const RULE_KEYS: &[&str] = &[
+    "id", "name", "description", "severity", "scope", "condition",
+    "message", "field", "suggested_fix", "fix", "confidence", "tests",
+];
+
+#[derive(Debug)]
+enum FieldValue {
+    Missing,
+    Text(String),
+    Number(f64),
+    List(Vec<String>),
+}
+
+impl FieldValue {
+    fn display(&self) -> String {
+        match self {
+            FieldValue::Missing => String::new(),
+            FieldValue::Text(text) => text.clone(),
+            FieldValue::Number(n) if n.fract() == 0.0 => format!("{}", *n as i64),
+            FieldValue::Number(n) => format!("{:.2}", n),
+            FieldValue::List(items) => items.join(", "),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            FieldValue::Missing => Value::Null,
+            FieldValue::Text(text) => Value::from(text.as_str()),
+            FieldValue::Number(n) => Value::from(*n),
+            FieldValue::List(items) => Value::from(items.clone()),
+        }
+    }
+
+    fn texts(&self) -> Vec<&str> {
+        match self {
+            FieldValue::Text(text) => vec![text.as_str()],
+            FieldValue::List(items) => items.iter().map(|s| s.as_str()).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Operator {
+    Exists,
+    Missing,
+    Equals(Literal),
+    NotEquals(Literal),
+    In(Vec<Literal>),
+    NotIn(Vec<Literal>),
+    Contains(String),
+    NotContains(String),
+    Matches(Regex),
+    StartsWith(String),
+    GreaterThan(f64),
+    GreaterOrEqual(f64),
+    LessThan(f64),
+    LessOrEqual(f64),
+}
+
+#[derive(Debug)]
+enum Literal {
+    Text(String),
+    Number(f64),
+}
+
+impl Literal {
+    fn matches(&self, value: &FieldValue) -> bool {
+        match (self, value) {
+            (Literal::Number(expected), FieldValue::Number(actual)) => (expected - actual).abs() < 1e-9,
+            (Literal::Text(expected), value) => value.texts().iter().any(|t| t.eq_ignore_ascii_case(expected)),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Condition {
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+    Compare { field: Field, op: Operator },
+}
+
+#[derive(Debug)]
+enum TemplatePart {
+    Literal(String),
+    Field(Field),
+}
+
+#[derive(Debug)]
+struct Template(Vec<TemplatePart>);
+
+struct Context<'a> {
+    data: &'a ExtractedData,
+    line: Option<(usize, &'a ServiceLine)>,
+}
+
+/// A validated rule, ready to evaluate.
+#[derive(Debug)]
+pub struct CompiledRule {
+    pub id: String,
+    pub name: String,
+    pub severity: Severity,
+    pub scope: RuleScope,
+    /// Digest of the rule's definition, recorded on its findings.
+    pub version: String,
+    condition: Condition,
+    message: Template,
+    field: Option<String>,
+    suggested_fix: Option<Template>,
+    fix: Option<Value>,
+    confidence: f64,
+    pub tests: Vec<RuleTest>,
+}
+
+impl CompiledRule {
+    pub fn evaluate(&self, data: &ExtractedData) -> Vec<ValidationResult> {
+        match self.scope {
+            RuleScope::Claim => {
+                let ctx = Context { data, line: None };
+                if self.condition.eval(&ctx) {
+                    vec![self.result(&ctx)]
+                } else {
+                    vec![]
+                }
+            }
+            RuleScope::ServiceLine => data
+                .service_lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| Context { data, line: Some((index, line)) })
+                .filter(|ctx| self.condition.eval(ctx))
+                .map(|ctx| self.result(&ctx))
+                .collect(),
+        }
+    }
+
+    fn result(&self, ctx: &Context) -> ValidationResult {
+        let mut result = ValidationResult::new(&self.id, &self.name, self.severity, self.message.render(ctx))
+            .with_confidence(self.confidence);
+        if let Some(field) = &self.field {
+            result = result.with_field(field);
+        }
+        if let Some(fix) = &self.suggested_fix {
+            result = result.with_suggested_fix(&fix.render(ctx));
+        }
+        if let Some(patch) = self.fix.as_ref().and_then(|fix| fixes::parse_rule_fix(fix, ctx.line.map(|(index, _)| index + 1)).ok()) {
+            result = result.with_fix(patch);
+        }
+
+        let mut fields = Vec::new();
+        self.condition.fields(&mut fields);
+        for field in fields {
+            result = result.with_value(field_name(field), field_value(field, ctx).to_json());
+        }
+        result.evidence.rule_version = Some(self.version.clone());
+        result
+    }
+}
+
+impl Condition {
+    /// The fields the condition reads, each once.
+    fn fields(&self, found: &mut Vec<Field>) {
+        match self {
+            Condition::All(conditions) | Condition::Any(conditions) => conditions.iter().for_each(|c| c.fields(found)),
+            Condition::Not(condition) => condition.fields(found),
+            Condition::Compare { field, .. } => {
+                if !found.contains(field) {
+                    found.push(*field);
+                }
+            }
+        }
+    }
+
+    fn eval(&self, ctx: &Context) -> bool {
+        match self {
+            Condition::All(conditions) => conditions.iter().all(|c| c.eval(ctx)),
+            Condition::Any(conditions) => conditions.iter().any(|c| c.eval(ctx)),
+            Condition::Not(condition) => !condition.eval(ctx),
+            Condition::Compare { field, op } => op.eval(&field_value(*field, ctx)),
+        }
+    }
+}
+
+impl Operator {
+    fn eval(&self, value: &FieldValue) -> bool {
+        let present = match value {
+            FieldValue::Missing => false,
+            FieldValue::Text(text) => !text.is_empty(),
+            FieldValue::List(items) => !items.is_empty(),
+            FieldValue::Number(_) => true,
+        };
+
+        match self {
+            Operator::Exists => present,
+            Operator::Missing => !present,
+            Operator::Equals(literal) => literal.matches(value),
+            Operator::NotEquals(literal) => !literal.matches(value),
+            Operator::In(literals) => literals.iter().any(|l| l.matches(value)),
+            Operator::NotIn(literals) => !literals.iter().any(|l| l.matches(value)),
+            Operator::Contains(needle) => contains(value, needle),
+            Operator::NotContains(needle) => !contains(value, needle),
+            Operator::Matches(regex) => value.texts().iter().any(|t| regex.is_match(t)),
+            Operator::StartsWith(prefix) => {
+                let prefix = prefix.to_ascii_lowercase();
+                value.texts().iter().any(|t| t.to_ascii_lowercase().starts_with(&prefix))
+            }
+            Operator::GreaterThan(n) => matches!(value, FieldValue::Number(v) if v > n),
+            Operator::GreaterOrEqual(n) => matches!(value, FieldValue::Number(v) if v >= n),
+            Operator::LessThan(n) => matches!(value, FieldValue::Number(v) if v < n),
+            Operator::LessOrEqual(n) => matches!(value, FieldValue::Number(v) if v <= n),
+        }
+    }
+}
+
+/// Substring match for text fields, element match for list fields.
+fn contains(value: &FieldValue, needle: &str) -> bool {
+    match value {
+        FieldValue::Text(text) => text.to_ascii_lowercase().contains(&needle.to_ascii_lowercase()),
+        FieldValue::List(items) => items.iter().any(|item| item.eq_ignore_ascii_case(needle)),
+        _ => false,
+    }
+}
+
+impl Template {
+    fn render(&self, ctx: &Context) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(text) => text.clone(),
+                TemplatePart::Field(field) => field_value(*field, ctx).display(),
+            })
+            .collect()
+    }
+}
+
+fn field_name(field: Field) -> &'static str {
+    FIELDS.iter()
+        .find(|(_, candidate, ..)| *candidate == field)
+        .map(|(name, ..)| *name)
+        .unwrap_or_default()
+}
+
+fn text(value: &Option<String>) -> FieldValue {
+    value.as_ref().map_or(FieldValue::Missing, |v| FieldValue::Text(v.clone()))
+}
+
+fn field_value(field: Field, ctx: &Context) -> FieldValue {
+    let data = ctx.data;
+    let line = ctx.line.map(|(_, line)| line);
+
+    match field {
+        Field::Payer => text(&data.payer),
+        Field::PatientName => text(&data.patient_name),
+        Field::PatientId => text(&data.patient_id),
+        Field::PatientAge => data.patient_dob
+            .zip(data.date_of_service())
+            .and_then(|(dob, date)| demographics::age_on(dob, date.date_naive()))
+            .map_or(FieldValue::Missing, |age| FieldValue::Number(age as f64)),
+        Field::PatientSex => data.patient_sex.map_or(FieldValue::Missing, |sex| FieldValue::Text(sex.as_str().to_string())),
+        Field::ProviderName => text(&data.provider_name),
+        Field::ProviderNpi => text(&data.provider_npi),
+        Field::BillingNpi => text(&data.billing_npi),
+        Field::RenderingNpi => text(&data.rendering_npi),
+        Field::CptCodes => FieldValue::List(data.cpt_codes.clone()),
+        Field::Modifiers => FieldValue::List(data.modifiers.clone()),
+        Field::DiagnosisCodes => FieldValue::List(data.diagnosis_codes.clone()),
+        // The parsed box 28 total, else the line charges; `charges` holds
+        // every dollar amount in the text, the total included.
+        Field::TotalCharge => data.total_charge
+            .or_else(|| {
+                let charges: Vec<f64> = data.service_lines.iter().filter_map(|l| l.charge).collect();
+                (!charges.is_empty()).then(|| charges.iter().sum())
+            })
+            .map_or(FieldValue::Missing, FieldValue::Number),
+        Field::ServiceLineCount => FieldValue::Number(data.service_lines.len() as f64),
+        Field::DateOfService => data.date_of_service()
+            .map_or(FieldValue::Missing, |d| FieldValue::Text(d.format("%Y-%m-%d").to_string())),
+        Field::LineNumber => ctx.line.map_or(FieldValue::Missing, |(i, _)| FieldValue::Number((i + 1) as f64)),
+        Field::LineProcedureCode => line.map_or(FieldValue::Missing, |l| FieldValue::Text(l.procedure_code.clone())),
+        Field::LineModifiers => line.map_or(FieldValue::Missing, |l| FieldValue::List(l.modifiers.clone())),
+        Field::LineUnits => line.map_or(FieldValue::Missing, |l| FieldValue::Number(l.units as f64)),
+        Field::LineCharge => line.and_then(|l| l.charge).map_or(FieldValue::Missing, FieldValue::Number),
+        Field::LineDateOfService => line.and_then(|l| l.date_of_service)
+            .map_or(FieldValue::Missing, |d| FieldValue::Text(d.format("%Y-%m-%d").to_string())),
+    }
+}
+
+/// Compiles the `rules` array of `Settings.rules_config`. Every problem is
+/// reported, not just the first, so the settings tab can mark them all.
+pub fn compile_rules_config(config: &Value) -> Result<Vec<CompiledRule>, Vec<RuleConfigError>> {
+    let mut compiler = Compiler::default();
+
+    let rules = match config {
+        Value::Null => return Ok(vec![]),
+        Value::Object(map) => match map.get("rules") {
+            None => return Ok(vec![]),
+            Some(Value::Array(rules)) => rules,
+            Some(_) => return Err(vec![error("rules", "expected an array of rule definitions")]),
+        },
+        _ => return Err(vec![error("", "expected an object with a \"rules\" array")]),
+    };
+
+    let mut compiled = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        if let Some(rule) = compiler.rule(&format!("rules[{}]", index), rule) {
+            compiled.push(rule);
+        }
+    }
+
+    // Tests may expect any rule in the config, so ids are checked once all
+    // rules are known.
+    for (index, rule) in compiled.iter().enumerate() {
+        for (test_index, test) in rule.tests.iter().enumerate() {
+            for expected in &test.expect {
+                if !BUILTIN_RULE_IDS.contains(&expected.as_str()) && !compiler.seen_ids.contains_key(expected) {
+                    compiler.fail(
+                        &format!("rules[{}].tests[{}].expect", index, test_index),
+                        format!("unknown rule id \"{}\"", expected),
+                    );
+                }
+            }
+        }
+    }
+
+    if compiler.errors.is_empty() {
+        Ok(compiled)
+    } else {
+        Err(compiler.errors)
+    }
+}
+
+pub fn validate_rules_config(config: &Value) -> Vec<RuleConfigError> {
+    compile_rules_config(config).err().unwrap_or_default()
+}
+
+fn error(path: &str, message: &str) -> RuleConfigError {
+    RuleConfigError {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[derive(Default)]
+struct Compiler {
+    errors: Vec<RuleConfigError>,
+    seen_ids: HashMap<String, String>,
+}
+
+impl Compiler {
+    fn fail(&mut self, path: &str, message: impl Into<String>) {
+        self.errors.push(RuleConfigError {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn rule(&mut self, path: &str, value: &Value) -> Option<CompiledRule> {
+        let Some(map) = value.as_object() else {
+            self.fail(path, "expected a rule object");
+            return None;
+        };
+
+        for key in map.keys() {
+            if !RULE_KEYS.contains(&key.as_str()) {
+                self.fail(&format!("{}.{}", path, key), format!("unknown property \"{}\"", key));
+            }
+        }
+
+        let id = self.rule_id(path, map);
+        let name = self.required_string(path, map, "name");
+        let severity = self.severity(path, map);
+        let scope = self.scope(path, map);
+        let condition = match map.get("condition") {
+            Some(condition) => self.condition(&format!("{}.condition", path), condition, scope.unwrap_or(RuleScope::Claim)),
+            None => {
+                self.fail(&format!("{}.condition", path), "required");
+                None
+            }
+        };
+        let message = self.required_string(path, map, "message")
+            .and_then(|m| self.template(&format!("{}.message", path), &m, scope.unwrap_or(RuleScope::Claim)));
+        let field = self.optional_string(path, map, "field");
+        let suggested_fix = self.optional_string(path, map, "suggested_fix")
+            .and_then(|f| self.template(&format!("{}.suggested_fix", path), &f, scope.unwrap_or(RuleScope::Claim)));
+        let fix = self.fix(path, map, scope.unwrap_or(RuleScope::Claim));
+        let confidence = self.confidence(path, map);
+        let tests = self.tests(path, map);
+        self.optional_string(path, map, "description");
+
+        Some(CompiledRule {
+            id: id?,
+            name: name?,
+            severity: severity?,
+            scope: scope?,
+            version: evidence::rule_version(value),
+            condition: condition?,
+            message: message?,
+            field,
+            suggested_fix,
+            fix: fix?,
+            confidence: confidence?,
+            tests: tests?,
+        })
+    }
+
+    fn rule_id(&mut self, path: &str, map: &Map<String, Value>) -> Option<String> {
+        let id = self.required_string(path, map, "id")?;
+        let id_path = format!("{}.id", path);
+
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            self.fail(&id_path, "must be non-empty and use only lowercase letters, digits and underscores");
+            return None;
+        }
+        if BUILTIN_RULE_IDS.contains(&id.as_str()) {
+            self.fail(&id_path, format!("\"{}\" is a built-in rule id", id));
+            return None;
+        }
+        if let Some(first) = self.seen_ids.get(&id) {
+            let message = format!("duplicate rule id \"{}\" (first defined at {})", id, first);
+            self.fail(&id_path, message);
+            return None;
+        }
+
+        self.seen_ids.insert(id.clone(), path.to_string());
+        Some(id)
+    }
+
+    fn required_string(&mut self, path: &str, map: &Map<String, Value>, key: &str) -> Option<String> {
+        let key_path = format!("{}.{}", path, key);
+        match map.get(key) {
+            Some(Value::String(s)) if !s.trim().is_empty() => Some(s.clone()),
+            Some(Value::String(_)) => {
+                self.fail(&key_path, "must not be empty");
+                None
+            }
+            Some(_) => {
+                self.fail(&key_path, "expected a string");
+                None
+            }
+            None => {
+                self.fail(&key_path, "required");
+                None
+            }
+        }
+    }
+
+    fn optional_string(&mut self, path: &str, map: &Map<String, Value>, key: &str) -> Option<String> {
+        match map.get(key) {
+            None | Some(Value::Null) => None,
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(_) => {
+                self.fail(&format!("{}.{}", path, key), "expected a string");
+                None
+            }
+        }
+    }
+
+    fn severity(&mut self, path: &str, map: &Map<String, Value>) -> Option<Severity> {
+        let severity = self.required_string(path, map, "severity")?;
+        let parsed = parse_severity(&severity);
+        if parsed.is_none() {
+            self.fail(
+                &format!("{}.severity", path),
+                format!("unknown severity \"{}\"; expected Critical, Warning or Info", severity),
+            );
+        }
+        parsed
+    }
+
+    fn scope(&mut self, path: &str, map: &Map<String, Value>) -> Option<RuleScope> {
+        match map.get("scope") {
+            None | Some(Value::Null) => Some(RuleScope::Claim),
+            Some(Value::String(s)) if s == "claim" => Some(RuleScope::Claim),
+            Some(Value::String(s)) if s == "service_line" => Some(RuleScope::ServiceLine),
+            Some(_) => {
+                self.fail(&format!("{}.scope", path), "expected \"claim\" or \"service_line\"");
+                None
+            }
+        }
+    }
+
+    fn confidence(&mut self, path: &str, map: &Map<String, Value>) -> Option<f64> {
+        match map.get("confidence") {
+            None | Some(Value::Null) => Some(1.0),
+            Some(value) => match value.as_f64() {
+                Some(c) if (0.0..=1.0).contains(&c) => Some(c),
+                _ => {
+                    self.fail(&format!("{}.confidence", path), "expected a number between 0 and 1");
+                    None
+                }
+            },
+        }
+    }
+
+    /// Service-line rules may leave out the fix's `line`; it defaults to the
+    /// line the rule fired on.
+    fn fix(&mut self, path: &str, map: &Map<String, Value>, scope: RuleScope) -> Option<Option<Value>> {
+        let fix = match map.get("fix") {
+            None | Some(Value::Null) => return Some(None),
+            Some(fix) => fix,
+        };
+        let default_line = (scope == RuleScope::ServiceLine).then_some(1);
+        match fixes::parse_rule_fix(fix, default_line) {
+            Ok(_) => Some(Some(fix.clone())),
+            Err(e) => {
+                self.fail(&format!("{}.fix", path), e.to_string());
+                None
+            }
+        }
+    }
+
+    fn tests(&mut self, path: &str, map: &Map<String, Value>) -> Option<Vec<RuleTest>> {
+        let tests = match map.get("tests") {
+            None | Some(Value::Null) => return Some(vec![]),
+            Some(Value::Array(tests)) => tests,
+            Some(_) => {
+                self.fail(&format!("{}.tests", path), "expected an array of test cases");
+                return None;
+            }
+        };
+
+        let mut parsed = Vec::new();
+        for (index, test) in tests.iter().enumerate() {
+            let test_path = format!("{}.tests[{}]", path, index);
+            match serde_json::from_value::<RuleTest>(test.clone()) {
+                Ok(test) if test.name.trim().is_empty() => self.fail(&format!("{}.name", test_path), "must not be empty"),
+                Ok(test) => parsed.push(test),
+                Err(e) => self.fail(&test_path, e.to_string()),
+            }
+        }
+        (parsed.len() == tests.len()).then_some(parsed)
+    }
+
+    fn field(&mut self, path: &str, name: &str, scope: RuleScope) -> Option<(Field, FieldKind)> {
+        match FIELDS.iter().find(|(field_name, ..)| *field_name == name) {
+            Some((_, _, _, true)) if scope == RuleScope::Claim => {
+                self.fail(path, format!("\"{}\" is only available in service_line rules", name));
+                None
+            }
+            Some((_, field, kind, _)) => Some((*field, *kind)),
+            None => {
+                self.fail(path, format!("unknown field \"{}\"", name));
+                None
+            }
+        }
+    }
+
+    fn condition(&mut self, path: &str, value: &Value, scope: RuleScope) -> Option<Condition> {
+        let Some(map) = value.as_object() else {
+            self.fail(path, "expected a condition object");
+            return None;
+        };
+
+        let forms: Vec<&str> = ["all", "any", "not", "field"]
+            .into_iter()
+            .filter(|key| map.contains_key(*key))
+            .collect();
+        if forms.len() != 1 {
+            self.fail(path, "expected exactly one of \"all\", \"any\", \"not\" or \"field\"");
+            return None;
+        }
+
+        match forms[0] {
+            "all" | "any" => {
+                let key = forms[0];
+                let list_path = format!("{}.{}", path, key);
+                let Some(items) = map[key].as_array().filter(|items| !items.is_empty()) else {
+                    self.fail(&list_path, "expected a non-empty array of conditions");
+                    return None;
+                };
+
+                let conditions: Vec<Option<Condition>> = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.condition(&format!("{}[{}]", list_path, i), item, scope))
+                    .collect();
+                let conditions: Option<Vec<Condition>> = conditions.into_iter().collect();
+
+                if key == "all" {
+                    conditions.map(Condition::All)
+                } else {
+                    conditions.map(Condition::Any)
+                }
+            }
+            "not" => self
+                .condition(&format!("{}.not", path), &map["not"], scope)
+                .map(|c| Condition::Not(Box::new(c))),
+            _ => self.comparison(path, map, scope),
+        }
+    }
+
+    fn comparison(&mut self, path: &str, map: &Map<String, Value>, scope: RuleScope) -> Option<Condition> {
+        for key in map.keys() {
+            if !["field", "op", "value"].contains(&key.as_str()) {
+                self.fail(&format!("{}.{}", path, key), format!("unknown property \"{}\"", key));
+            }
+        }
+
+        let field_path = format!("{}.field", path);
+        let field = match &map["field"] {
+            Value::String(name) => self.field(&field_path, name, scope),
+            _ => {
+                self.fail(&field_path, "expected a field name");
+                None
+            }
+        };
+
+        let op_path = format!("{}.op", path);
+        let op_name = match map.get("op") {
+            Some(Value::String(op)) => op.clone(),
+            Some(_) => {
+                self.fail(&op_path, "expected an operator name");
+                return None;
+            }
+            None => {
+                self.fail(&op_path, "required");
+                return None;
+            }
+        };
+
+        let (field, kind) = field?;
+        let op = self.operator(path, &op_name, kind, map.get("value"))?;
+        Some(Condition::Compare { field, op })
+    }
+
+    fn operator(&mut self, path: &str, op: &str, kind: FieldKind, value: Option<&Value>) -> Option<Operator> {
+        let op_path = format!("{}.op", path);
+        let value_path = format!("{}.value", path);
+
+        let needs_value = !matches!(op, "exists" | "missing");
+        if needs_value && value.is_none() {
+            self.fail(&value_path, format!("required for operator \"{}\"", op));
+            return None;
+        }
+        if !needs_value && value.is_some() {
+            self.fail(&value_path, format!("operator \"{}\" takes no value", op));
+            return None;
+        }
+
+        let numeric = matches!(op, "greater_than" | "greater_or_equal" | "less_than" | "less_or_equal");
+        let textual = matches!(op, "contains" | "not_contains" | "matches" | "starts_with");
+        if numeric && kind != FieldKind::Number {
+            self.fail(&op_path, format!("operator \"{}\" requires a numeric field", op));
+            return None;
+        }
+        if textual && kind == FieldKind::Number {
+            self.fail(&op_path, format!("operator \"{}\" requires a text or list field", op));
+            return None;
+        }
+
+        let operator = match op {
+            "exists" => Operator::Exists,
+            "missing" => Operator::Missing,
+            "equals" => Operator::Equals(self.literal(&value_path, value?, kind)?),
+            "not_equals" => Operator::NotEquals(self.literal(&value_path, value?, kind)?),
+            "in" | "not_in" => {
+                let Some(items) = value?.as_array().filter(|items| !items.is_empty()) else {
+                    self.fail(&value_path, "expected a non-empty array");
+                    return None;
+                };
+                let literals: Option<Vec<Literal>> = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.literal(&format!("{}[{}]", value_path, i), item, kind))
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .collect();
+                if op == "in" {
+                    Operator::In(literals?)
+                } else {
+                    Operator::NotIn(literals?)
+                }
+            }
+            "contains" => Operator::Contains(self.string_value(&value_path, value?)?),
+            "not_contains" => Operator::NotContains(self.string_value(&value_path, value?)?),
+            "starts_with" => Operator::StartsWith(self.string_value(&value_path, value?)?),
+            "matches" => {
+                let pattern = self.string_value(&value_path, value?)?;
+                match Regex::new(&pattern) {
+                    Ok(regex) => Operator::Matches(regex),
+                    Err(e) => {
+                        self.fail(&value_path, format!("invalid regular expression: {}", e));
+                        return None;
+                    }
+                }
+            }
+            "greater_than" => Operator::GreaterThan(self.number_value(&value_path, value?)?),
+            "greater_or_equal" => Operator::GreaterOrEqual(self.number_value(&value_path, value?)?),
+            "less_than" => Operator::LessThan(self.number_value(&value_path, value?)?),
+            "less_or_equal" => Operator::LessOrEqual(self.number_value(&value_path, value?)?),
+            other => {
+                self.fail(&op_path, format!("unknown operator \"{}\"", other));
+                return None;
+            }
+        };
+
+        Some(operator)
+    }
+
+    fn literal(&mut self, path: &str, value: &Value, kind: FieldKind) -> Option<Literal> {
+        match kind {
+            FieldKind::Number => self.number_value(path, value).map(Literal::Number),
+            FieldKind::Text | FieldKind::List => self.string_value(path, value).map(Literal::Text),
+        }
+    }
+
+    fn string_value(&mut self, path: &str, value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            _ => {
+                self.fail(path, "expected a string");
+                None
+            }
+        }
+    }
+
+    fn number_value(&mut self, path: &str, value: &Value) -> Option<f64> {
+        match value.as_f64() {
+            Some(n) => Some(n),
+            None => {
+                self.fail(path, "expected a number");
+                None
+            }
+        }
+    }
+
+    /// Splits `{{field}}` placeholders out of a message, checking each one
+    /// names a field available in the rule's scope.
+    fn template(&mut self, path: &str, source: &str, scope: RuleScope) -> Option<Template> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        let mut valid = true;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                self.fail(path, "unclosed \"{{\" placeholder");
+                return None;
+            };
+
+            let name = after[..end].trim();
+            match self.field(path, name, scope) {
+                Some((field, _)) => parts.push(TemplatePart::Field(field)),
+                None => valid = false,
+            }
+            rest = &after[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+
+        valid.then_some(Template(parts))
+    }
+}
+
+pub fn parse_severity(value: &str) -> Option<Severity> {
+    match value.to_ascii_lowercase().as_str() {
+        "critical" => Some(Severity::Critical),
+        "warning" => Some(Severity::Warning),
+        "info" => Some(Severity::Info),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::types::FixPatch;
+
+    fn claim_data() -> ExtractedData {
+        ExtractedData {
+            payer: Some("blue cross".to_string()),
+            cpt_codes: vec!["99213".to_string(), "20610".to_string()],
+            charges: vec![125.0, 310.5, 435.5],
+            total_charge: Some(435.5),
+            service_lines: vec![
+                ServiceLine {
+                    procedure_code: "99213".to_string(),
+                    modifiers: vec![],
+                    units: 1,
+                    charge: Some(125.0),
+                    date_of_service: None,
+                    diagnosis_pointers: vec![],
+                },
+                ServiceLine {
+                    procedure_code: "20610".to_string(),
+                    modifiers: vec!["RT".to_string()],
+                    units: 3,
+                    charge: Some(310.5),
+                    date_of_service: None,
+                    diagnosis_pointers: vec![],
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_claim_rule_fires_with_rendered_message() {
+        let config = json!({ "rules": [{
+            "id": "bcbs_em_with_procedure",
+            "name": "BCBS E/M with procedure",
+            "severity": "Warning",
+            "condition": { "all": [
+                { "field": "payer", "op": "equals", "value": "Blue Cross" },
+                { "field": "cpt_codes", "op": "contains", "value": "99213" },
+                { "field": "total_charge", "op": "greater_than", "value": 400 }
+            ]},
+            "message": "{{payer}} claim bills {{cpt_codes}}",
+            "suggested_fix": "Append modifier 25"
+        }]});
+
+        let rules = compile_rules_config(&config).unwrap();
+        let results = rules[0].evaluate(&claim_data());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message, "blue cross claim bills 99213, 20610");
+        assert_eq!(results[0].severity, Severity::Warning);
+        let evidence = &results[0].evidence;
+        assert_eq!(evidence.values["payer"], "blue cross");
+        assert_eq!(evidence.values["total_charge"], 435.5);
+        assert_eq!(evidence.rule_version, Some(rules[0].version.clone()));
+
+        // Without a parsed total the line charges are added up.
+        let unlabelled_total = ExtractedData { total_charge: None, ..claim_data() };
+        assert_eq!(rules[0].evaluate(&unlabelled_total)[0].evidence.values["total_charge"], 435.5);
+    }
+
+    #[test]
+    fn test_service_line_rule_evaluates_each_line() {
+        let config = json!({ "rules": [{
+            "id": "units_over_two",
+            "name": "Units over two",
+            "severity": "Info",
+            "scope": "service_line",
+            "condition": { "field": "line.units", "op": "greater_than", "value": 2 },
+            "message": "Line {{line.number}} bills {{line.units}} units of {{line.procedure_code}}",
+            "fix": { "op": "remove_modifier", "modifier": "RT" }
+        }]});
+
+        let rules = compile_rules_config(&config).unwrap();
+        let results = rules[0].evaluate(&claim_data());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message, "Line 2 bills 3 units of 20610");
+        assert_eq!(results[0].fix, Some(FixPatch::RemoveModifier { line: 2, modifier: "RT".to_string() }));
+    }
+
+    #[test]
+    fn test_errors_report_precise_paths() {
+        let config = json!({ "rules": [
+            {
+                "id": "ok_rule",
+                "name": "Fine",
+                "severity": "Warning",
+                "condition": { "field": "payer", "op": "exists" },
+                "message": "fine"
+            },
+            {
+                "id": "ok_rule",
+                "name": "Broken",
+                "severity": "High",
+                "condition": { "all": [
+                    { "field": "payer", "op": "exists" },
+                    { "field": "line.units", "op": "greater_than", "value": "two" }
+                ]},
+                "message": "{{unknown_field}}",
+                "severty": "Info"
+            }
+        ]});
+
+        let errors = compile_rules_config(&config).unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert!(paths.contains(&"rules[1].severty"));
+        assert!(paths.contains(&"rules[1].id"));
+        assert!(paths.contains(&"rules[1].severity"));
+        assert!(paths.contains(&"rules[1].condition.all[1].field"));
+        assert!(paths.contains(&"rules[1].message"));
+        assert!(!paths.iter().any(|p| p.starts_with("rules[0]")));
+    }
+
+    #[test]
+    fn test_rejects_operator_field_mismatch_and_bad_regex() {
+        let config = json!({ "rules": [{
+            "id": "bad_ops",
+            "name": "Bad",
+            "severity": "Info",
+            "condition": { "any": [
+                { "field": "payer", "op": "greater_than", "value": 1 },
+                { "field": "payer", "op": "matches", "value": "(" },
+                { "field": "payer", "op": "sounds_like", "value": "x" }
+            ]},
+            "message": "bad"
+        }]});
+
+        let errors = compile_rules_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].path, "rules[0].condition.any[0].op");
+        assert_eq!(errors[1].path, "rules[0].condition.any[1].value");
+        assert_eq!(errors[2].path, "rules[0].condition.any[2].op");
+    }
+
+    #[test]
+    fn test_parses_rule_tests_and_checks_expected_ids() {
+        let rule = |expect: Value| json!({ "rules": [{
+            "id": "bcbs_high_units",
+            "name": "High units",
+            "severity": "Warning",
+            "condition": { "field": "payer", "op": "exists" },
+            "message": "units",
+            "tests": [{ "name": "fires", "claim": { "payer": "blue cross" }, "expect": expect }]
+        }]});
+
+        let rules = compile_rules_config(&rule(json!(["bcbs_high_units", "missing_patient_name"]))).unwrap();
+        assert_eq!(rules[0].tests[0].claim.payer.as_deref(), Some("blue cross"));
+
+        let errors = compile_rules_config(&rule(json!(["no_such_rule"]))).unwrap_err();
+        assert_eq!(errors[0].path, "rules[0].tests[0].expect");
+    }
+
+    #[test]
+    fn test_empty_config_has_no_rules() {
+        assert!(compile_rules_config(&json!({})).unwrap().is_empty());
+        assert!(compile_rules_config(&Value::Null).unwrap().is_empty());
+    }
+}
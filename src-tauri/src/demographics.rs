@@ -0,0 +1,154 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, NaiveDate};
+use crate::icd10;
+use crate::procedure_codes;
+use crate::types::{CodeAttribute, CodeSystem, Sex};
+
+/// Completed years of age on `date`; `None` when born after it.
+pub fn age_on(date_of_birth: NaiveDate, date: NaiveDate) -> Option<u32> {
+    let mut age = date.year() - date_of_birth.year();
+    if (date.month(), date.day()) < (date_of_birth.month(), date_of_birth.day()) {
+        age -= 1;
+    }
+    u32::try_from(age).ok()
+}
+
+/// Parses a CSV of code attributes with the header
+/// `code_system,code,min_age,max_age,sex,description`. `code_system` is
+/// `icd10` or `procedure`; blank ages and sex mean no restriction. An ICD-10
+/// entry may name a category, which then applies to all its children.
+pub fn parse_attribute_file(contents: &str) -> Result<Vec<CodeAttribute>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.to_ascii_lowercase()).collect();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let system_column = column("code_system").ok_or_else(|| anyhow!("Missing column code_system"))?;
+    let code_column = column("code").ok_or_else(|| anyhow!("Missing column code"))?;
+    let min_age_column = column("min_age");
+    let max_age_column = column("max_age");
+    let sex_column = column("sex");
+    let description_column = column("description");
+
+    let mut attributes = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = index + 2;
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or_default();
+
+        let code_system = match field(Some(system_column)).to_ascii_lowercase().as_str() {
+            "icd10" | "icd-10" | "icd-10-cm" | "diagnosis" => CodeSystem::Icd10,
+            "procedure" | "cpt" | "hcpcs" => CodeSystem::Procedure,
+            value => bail!("Line {}: unknown code system {}", line, value),
+        };
+        let code = match code_system {
+            CodeSystem::Icd10 => icd10::normalize(field(Some(code_column))),
+            CodeSystem::Procedure => field(Some(code_column)).to_ascii_uppercase(),
+        };
+        let valid_code = match code_system {
+            CodeSystem::Icd10 => !code.is_empty() && code.starts_with(|c: char| c.is_ascii_uppercase()),
+            CodeSystem::Procedure => procedure_codes::is_valid_format(&code),
+        };
+        if !valid_code {
+            bail!("Line {}: invalid code {}", line, code);
+        }
+
+        let age = |column: Option<usize>| match field(column) {
+            "" => Ok(None),
+            value => value.parse::<u32>().map(Some).map_err(|_| anyhow!("Line {}: invalid age {}", line, value)),
+        };
+        let min_age = age(min_age_column)?;
+        let max_age = age(max_age_column)?;
+        if let (Some(min), Some(max)) = (min_age, max_age) {
+            if min > max {
+                bail!("Line {}: min_age {} is above max_age {}", line, min, max);
+            }
+        }
+        let sex = match field(sex_column) {
+            "" => None,
+            value => Some(Sex::parse(value).ok_or_else(|| anyhow!("Line {}: invalid sex {}", line, value))?),
+        };
+
+        attributes.push(CodeAttribute {
+            code_system,
+            code,
+            min_age,
+            max_age,
+            sex,
+            description: field(description_column).to_string(),
+        });
+    }
+
+    Ok(attributes)
+}
+
+/// Attributes that apply to `code`: exact matches for procedure codes, the
+/// code or any of its categories for ICD-10.
+pub fn attributes_for<'a>(
+    attributes: &'a [CodeAttribute],
+    code_system: CodeSystem,
+    code: &str,
+) -> impl Iterator<Item = &'a CodeAttribute> {
+    let code = match code_system {
+        CodeSystem::Icd10 => icd10::normalize(code),
+        CodeSystem::Procedure => code.to_string(),
+    };
+    attributes.iter().filter(move |attribute| {
+        attribute.code_system == code_system
+            && match code_system {
+                CodeSystem::Icd10 => code.starts_with(&attribute.code),
+                CodeSystem::Procedure => code == attribute.code,
+            }
+    })
+}
+
+/// Every prefix of a normalized ICD-10 code, for looking up category rows.
+pub fn icd10_prefixes(code: &str) -> Vec<String> {
+    let code = icd10::normalize(code);
+    (1..=code.len()).map(|len| code[..len].to_string()).collect()
+}
+
+pub fn allows_age(attribute: &CodeAttribute, age: u32) -> bool {
+    attribute.min_age.is_none_or(|min| age >= min) && attribute.max_age.is_none_or(|max| age <= max)
+}
+
+pub fn describe_ages(attribute: &CodeAttribute) -> String {
+    match (attribute.min_age, attribute.max_age) {
+        (Some(min), Some(max)) if min == max => format!("age {}", min),
+        (Some(min), Some(max)) => format!("ages {}-{}", min, max),
+        (Some(min), None) => format!("ages {} and over", min),
+        (None, Some(max)) => format!("ages {} and under", max),
+        (None, None) => "all ages".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_on_birthday_boundary() {
+        let dob = NaiveDate::from_ymd_opt(1964, 3, 15).unwrap();
+        assert_eq!(age_on(dob, NaiveDate::from_ymd_opt(2024, 3, 14).unwrap()), Some(59));
+        assert_eq!(age_on(dob, NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()), Some(60));
+        assert_eq!(age_on(dob, NaiveDate::from_ymd_opt(1963, 1, 1).unwrap()), None);
+    }
+
+    #[test]
+    fn test_parses_attributes_and_matches_categories() {
+        let file = "code_system,code,min_age,max_age,sex,description\n\
+                    icd10,O,12,55,F,Maternity diagnosis\n\
+                    procedure,55700,,,M,Prostate biopsy\n";
+        let attributes = parse_attribute_file(file).unwrap();
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[1].sex, Some(Sex::Male));
+
+        assert_eq!(attributes_for(&attributes, CodeSystem::Icd10, "O80").count(), 1);
+        assert_eq!(attributes_for(&attributes, CodeSystem::Icd10, "E11.9").count(), 0);
+        assert_eq!(attributes_for(&attributes, CodeSystem::Procedure, "55700").count(), 1);
+        assert!(!allows_age(&attributes[0], 60));
+        assert!(parse_attribute_file("code_system,code,min_age,max_age\nicd10,P00,5,1\n").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::types::{Claim, ExtractedData};
+
+/// Claim frequency codes (CMS-1500 box 22, UB-04 type of bill third digit)
+/// marking a claim that replaces or voids an earlier submission.
+pub const REPLACEMENT_FREQUENCY_CODE: &str = "7";
+pub const VOID_FREQUENCY_CODE: &str = "8";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Same procedure codes and units.
+    Exact,
+    /// At least one procedure code in common.
+    Partial,
+}
+
+/// Another claim for the same patient, provider and date of service.
+#[derive(Debug, Clone)]
+pub struct DuplicateMatch<'a> {
+    pub claim: &'a Claim,
+    pub kind: MatchKind,
+    pub shared_codes: Vec<String>,
+}
+
+pub fn is_correction(data: &ExtractedData) -> bool {
+    matches!(data.frequency_code.as_deref(), Some(REPLACEMENT_FREQUENCY_CODE | VOID_FREQUENCY_CODE))
+}
+
+fn normalize_reference(reference: &str) -> String {
+    reference.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_ascii_lowercase()
+}
+
+/// Whether a replacement's original-claim reference names `other`, by its
+/// claim id or the name of the file it was read from, ignoring case and
+/// punctuation.
+pub fn refers_to(reference: &str, other: &Claim) -> bool {
+    let reference = normalize_reference(reference);
+    if reference.is_empty() {
+        return false;
+    }
+    let stem = other.filename.rsplit_once('.').map_or(other.filename.as_str(), |(stem, _)| stem);
+    reference == normalize_reference(&other.id.to_string()) || reference == normalize_reference(stem)
+}
+
+/// Whether `data` is a replacement or void that names `other` as its original.
+pub fn corrects(data: &ExtractedData, other: &Claim) -> bool {
+    is_correction(data) && data.original_claim_reference.as_deref().is_some_and(|reference| refers_to(reference, other))
+}
+
+/// Two replacements or voids naming the same original.
+pub fn same_original(a: &ExtractedData, b: &ExtractedData) -> bool {
+    match (&a.original_claim_reference, &b.original_claim_reference) {
+        (Some(a_ref), Some(b_ref)) => {
+            is_correction(a) && is_correction(b) && !normalize_reference(a_ref).is_empty()
+                && normalize_reference(a_ref) == normalize_reference(b_ref)
+        }
+        _ => false,
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .map(|part| part.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Patient ids decide when both claims have one; otherwise the names must
+/// match, ignoring case and punctuation.
+pub fn same_patient(a: &ExtractedData, b: &ExtractedData) -> bool {
+    if let (Some(a), Some(b)) = (&a.patient_id, &b.patient_id) {
+        return a.trim().eq_ignore_ascii_case(b.trim());
+    }
+    match (&a.patient_name, &b.patient_name) {
+        (Some(a), Some(b)) => normalize_name(a) == normalize_name(b),
+        _ => false,
+    }
+}
+
+/// Providers are compared by NPI when both claims have one, then by name.
+/// A claim missing both can't rule the other out.
+pub fn same_provider(a: &ExtractedData, b: &ExtractedData) -> bool {
+    if let (Some(a), Some(b)) = (&a.provider_npi, &b.provider_npi) {
+        return a == b;
+    }
+    match (&a.provider_name, &b.provider_name) {
+        (Some(a), Some(b)) => normalize_name(a) == normalize_name(b),
+        _ => true,
+    }
+}
+
+fn billed_codes(data: &ExtractedData) -> Vec<(String, u32)> {
+    let mut codes: Vec<(String, u32)> = data.billed_services()
+        .into_iter()
+        .map(|service| (service.procedure_code, service.units))
+        .collect();
+    codes.sort();
+    codes
+}
+
+/// Compares `data` against earlier claims and returns those billing the same
+/// patient, provider and date of service with overlapping procedure codes.
+pub fn find_duplicates<'a>(data: &ExtractedData, others: &'a [Claim]) -> Vec<DuplicateMatch<'a>> {
+    let Some(date_of_service) = data.date_of_service().map(|d| d.date_naive()) else {
+        return Vec::new();
+    };
+    let codes = billed_codes(data);
+    if codes.is_empty() {
+        return Vec::new();
+    }
+
+    others
+        .iter()
+        .filter(|other| {
+            same_patient(data, &other.extracted_data)
+                && same_provider(data, &other.extracted_data)
+                && other.extracted_data.date_of_service().map(|d| d.date_naive()) == Some(date_of_service)
+        })
+        .filter_map(|other| {
+            let other_codes = billed_codes(&other.extracted_data);
+            let mut shared_codes: Vec<String> = codes.iter()
+                .filter(|(code, _)| other_codes.iter().any(|(other_code, _)| other_code == code))
+                .map(|(code, _)| code.clone())
+                .collect();
+            shared_codes.dedup();
+            if shared_codes.is_empty() {
+                return None;
+            }
+
+            let kind = if other_codes == codes { MatchKind::Exact } else { MatchKind::Partial };
+            Some(DuplicateMatch { claim: other, kind, shared_codes })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use crate::rule_tests;
+    use crate::types::{ClaimStatus, DateRole, ExtractedDate};
+
+    fn claim(patient_name: &str, codes: &[&str]) -> Claim {
+        let mut claim = rule_tests::fixture_claim(&ExtractedData {
+            patient_name: Some(patient_name.to_string()),
+            provider_npi: Some("1234567893".to_string()),
+            cpt_codes: codes.iter().map(|c| c.to_string()).collect(),
+            dates: vec![ExtractedDate::new(DateRole::ServiceFrom, Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap())],
+            ..Default::default()
+        });
+        claim.status = ClaimStatus::Submitted;
+        claim
+    }
+
+    #[test]
+    fn test_exact_and_partial_matches() {
+        let current = claim("Jane Doe", &["99213", "20610"]);
+        let others = vec![
+            claim("JANE  DOE", &["20610", "99213"]),
+            claim("Jane Doe", &["99213"]),
+            claim("John Doe", &["99213", "20610"]),
+        ];
+
+        let matches = find_duplicates(&current.extracted_data, &others);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].kind, MatchKind::Exact);
+        assert_eq!(matches[1].kind, MatchKind::Partial);
+        assert_eq!(matches[1].shared_codes, ["99213"]);
+    }
+
+    #[test]
+    fn test_different_provider_is_not_a_duplicate() {
+        let current = claim("Jane Doe", &["99213"]);
+        let mut other = claim("Jane Doe", &["99213"]);
+        other.extracted_data.provider_npi = Some("1245319599".to_string());
+        assert!(find_duplicates(&current.extracted_data, &[other]).is_empty());
+    }
+}
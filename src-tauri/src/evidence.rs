@@ -0,0 +1,100 @@
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use crate::types::{ExtractedData, SourceSnippet};
+
+/// Version recorded on findings from the built-in rules.
+pub const BUILTIN_RULES_VERSION: &str = concat!("builtin-", env!("CARGO_PKG_VERSION"));
+
+/// Snippets kept per finding; values such as a common code can appear on
+/// many lines.
+const MAX_SNIPPETS: usize = 3;
+const MAX_SNIPPET_LENGTH: usize = 200;
+
+/// Identifies a custom rule by its definition, so a finding still points
+/// at the exact rule that produced it after the rule is edited.
+pub fn rule_version(definition: &Value) -> String {
+    let digest = Sha256::digest(definition.to_string().as_bytes());
+    format!("sha256-{}", &hex::encode(digest)[..12])
+}
+
+/// A claim-level field as stored, or `None` for unknown fields and the
+/// raw text itself.
+pub fn field_value(data: &ExtractedData, field: &str) -> Option<Value> {
+    if field == "raw_text" {
+        return None;
+    }
+    serde_json::to_value(data).ok()?.get(field).cloned()
+}
+
+/// Text worth looking for in the source: strings of three or more
+/// characters, with ISO dates also tried in the MM/DD/YYYY form claims use.
+fn needles(value: &Value, found: &mut Vec<String>) {
+    match value {
+        Value::String(text) if text.trim().len() >= 3 => {
+            let text = text.trim();
+            let date = NaiveDate::parse_from_str(text.get(..10).unwrap_or(text), "%Y-%m-%d");
+            match date {
+                Ok(date) => found.push(date.format("%m/%d/%Y").to_string()),
+                Err(_) => found.push(text.to_lowercase()),
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| needles(item, found)),
+        Value::Object(map) => map.values().for_each(|item| needles(item, found)),
+        _ => {}
+    }
+}
+
+/// Lines of the source text mentioning any of the values. Pages are split
+/// on form feeds, as pdftotext and tesseract emit them.
+pub fn source_snippets(raw_text: &str, values: &Map<String, Value>) -> Vec<SourceSnippet> {
+    let mut found = Vec::new();
+    values.values().for_each(|value| needles(value, &mut found));
+    if found.is_empty() {
+        return Vec::new();
+    }
+
+    raw_text.split('\x0c')
+        .enumerate()
+        .flat_map(|(page, text)| text.lines().enumerate().map(move |(line, text)| (page + 1, line + 1, text)))
+        .filter(|(_, _, text)| {
+            let text = text.to_lowercase();
+            found.iter().any(|needle| text.contains(needle.as_str()))
+        })
+        .take(MAX_SNIPPETS)
+        .map(|(page, line, text)| SourceSnippet {
+            page,
+            line,
+            text: text.trim().chars().take(MAX_SNIPPET_LENGTH).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_snippets_locate_values_by_page_and_line() {
+        let raw_text = "CMS-1500\nPatient: Jane Doe\n\x0cLine 1 03/15/2024 20610 RT $310.00\nLine 2 03/15/2024 99213";
+        let mut values = Map::new();
+        values.insert("procedure_code".to_string(), json!("20610"));
+        values.insert("date_of_service".to_string(), json!("2024-03-15T00:00:00Z"));
+        values.insert("units".to_string(), json!(3));
+
+        let snippets = source_snippets(raw_text, &values);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!((snippets[0].page, snippets[0].line), (2, 1));
+        assert_eq!(snippets[0].text, "Line 1 03/15/2024 20610 RT $310.00");
+        assert!(source_snippets(raw_text, &Map::new()).is_empty());
+    }
+
+    #[test]
+    fn test_rule_version_changes_with_definition() {
+        let rule = json!({ "id": "prior_auth", "severity": "Warning" });
+        assert_eq!(rule_version(&rule), rule_version(&rule.clone()));
+        assert_ne!(rule_version(&rule), rule_version(&json!({ "id": "prior_auth", "severity": "Critical" })));
+        assert!(rule_version(&rule).starts_with("sha256-"));
+    }
+}
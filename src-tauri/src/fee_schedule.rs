@@ -0,0 +1,105 @@
+use anyhow::{anyhow, bail, Result};
+use crate::procedure_codes;
+use crate::types::{FeeScheduleEntry, FeeSource};
+
+/// A line charge more than this multiple of the chargemaster price is
+/// flagged as a likely keying error.
+pub const CHARGEMASTER_TOLERANCE: f64 = 1.5;
+
+/// Parses a fee schedule CSV. The Medicare physician fee schedule export
+/// (`HCPCS`, `MOD`, `NON-FACILITY PRICE`, ...) and a chargemaster
+/// (`code`, `modifier`, `price`) both work: the first column naming a
+/// price, fee, amount or charge is used.
+pub fn parse_fee_file(contents: &str, source: FeeSource, locality: &str) -> Result<Vec<FeeScheduleEntry>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+
+    let headers: Vec<String> = reader.headers()?
+        .iter()
+        .map(|h| h.to_ascii_lowercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_"))
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+
+    let code_column = column(&["code", "hcpcs", "hcpcs_code", "cpt_code", "cpt", "proc_code"])
+        .ok_or_else(|| anyhow!("No code column found"))?;
+    let modifier_column = column(&["modifier", "mod"]);
+    let amount_column = headers.iter()
+        .position(|h| ["price", "fee", "amount", "charge"].iter().any(|word| h.contains(word)))
+        .ok_or_else(|| anyhow!("No price column found"))?;
+
+    let mut entries = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = index + 2;
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or_default();
+
+        let code = field(Some(code_column)).to_ascii_uppercase();
+        if code.is_empty() {
+            continue;
+        }
+        if !procedure_codes::is_valid_format(&code) {
+            bail!("Line {}: invalid procedure code {}", line, code);
+        }
+        let amount_text = field(Some(amount_column)).trim_start_matches('$').replace(',', "");
+        let amount: f64 = amount_text.parse()
+            .ok()
+            .filter(|amount: &f64| amount.is_finite() && *amount >= 0.0)
+            .ok_or_else(|| anyhow!("Line {}: invalid amount {}", line, amount_text))?;
+
+        entries.push(FeeScheduleEntry {
+            source,
+            locality: locality.to_string(),
+            code,
+            modifier: field(modifier_column).to_ascii_uppercase(),
+            amount,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// The entry for a code and its first matching modifier (e.g. 26 or TC),
+/// falling back to the code's global entry.
+pub fn lookup<'a>(
+    entries: &'a [FeeScheduleEntry],
+    source: FeeSource,
+    code: &str,
+    modifiers: &[String],
+) -> Option<&'a FeeScheduleEntry> {
+    let for_code = || entries.iter().filter(move |entry| entry.source == source && entry.code == code);
+    modifiers.iter()
+        .find_map(|modifier| for_code().find(|entry| entry.modifier == *modifier))
+        .or_else(|| for_code().find(|entry| entry.modifier.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_medicare_and_chargemaster_files() {
+        let medicare = "HCPCS,MOD,NON-FACILITY PRICE,FACILITY PRICE\n\
+                        99213,,$92.35,$66.12\n\
+                        93000,26,8.50,8.50\n";
+        let entries = parse_fee_file(medicare, FeeSource::Medicare, "01112").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].amount, 92.35);
+        assert_eq!(entries[1].modifier, "26");
+
+        let chargemaster = parse_fee_file("code,price\n99213,\"1,150.00\"\n", FeeSource::Chargemaster, "").unwrap();
+        assert_eq!(chargemaster[0].amount, 1150.0);
+        assert!(parse_fee_file("code,price\n99213,abc\n", FeeSource::Chargemaster, "").is_err());
+    }
+
+    #[test]
+    fn test_lookup_prefers_modifier_entry() {
+        let entries = parse_fee_file("hcpcs,mod,price\n93000,,17.00\n93000,26,8.50\n", FeeSource::Medicare, "01112").unwrap();
+        let with_26 = lookup(&entries, FeeSource::Medicare, "93000", &["26".to_string()]).unwrap();
+        assert_eq!(with_26.amount, 8.5);
+        let global = lookup(&entries, FeeSource::Medicare, "93000", &["59".to_string()]).unwrap();
+        assert_eq!(global.amount, 17.0);
+        assert!(lookup(&entries, FeeSource::Chargemaster, "93000", &[]).is_none());
+    }
+}
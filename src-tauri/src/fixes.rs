@@ -0,0 +1,146 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use crate::types::{ExtractedData, FixPatch, ServiceLine};
+
+/// Fields `SetField` may not touch: the OCR text is the source document,
+/// and lines are changed through the line operations.
+const PROTECTED_FIELDS: &[&str] = &["raw_text", "service_lines"];
+
+pub fn describe(patch: &FixPatch) -> String {
+    match patch {
+        FixPatch::SetField { field, value } => format!("Set {} to {}", field, value),
+        FixPatch::AddModifier { line, modifier } => format!("Add modifier {} to line {}", modifier, line),
+        FixPatch::RemoveModifier { line, modifier } => format!("Remove modifier {} from line {}", modifier, line),
+        FixPatch::RemoveLine { line } => format!("Remove line {}", line),
+    }
+}
+
+fn line_mut(data: &mut ExtractedData, line: usize) -> Result<&mut ServiceLine> {
+    let count = data.service_lines.len();
+    line.checked_sub(1)
+        .and_then(|index| data.service_lines.get_mut(index))
+        .ok_or_else(|| anyhow!("Line {} does not exist; the claim has {} service lines", line, count))
+}
+
+/// Rebuilds the claim-level modifier list from the lines.
+fn collect_modifiers(data: &mut ExtractedData) {
+    let mut modifiers: Vec<String> = Vec::new();
+    for modifier in data.service_lines.iter().flat_map(|line| &line.modifiers) {
+        if !modifiers.contains(modifier) {
+            modifiers.push(modifier.clone());
+        }
+    }
+    data.modifiers = modifiers;
+}
+
+/// Applies a patch, keeping the claim-level code, charge and modifier lists
+/// in step with the service lines.
+pub fn apply(data: &mut ExtractedData, patch: &FixPatch) -> Result<()> {
+    match patch {
+        FixPatch::SetField { field, value } => {
+            if PROTECTED_FIELDS.contains(&field.as_str()) {
+                bail!("{} can't be changed by a fix", field);
+            }
+            let mut fields = serde_json::to_value(&*data)?;
+            let slot = fields.get_mut(field.as_str())
+                .ok_or_else(|| anyhow!("Unknown claim field {}", field))?;
+            *slot = value.clone();
+            *data = serde_json::from_value(fields)
+                .map_err(|e| anyhow!("Invalid value for {}: {}", field, e))?;
+        }
+        FixPatch::AddModifier { line, modifier } => {
+            let modifier = modifier.trim().to_ascii_uppercase();
+            let service_line = line_mut(data, *line)?;
+            if !service_line.modifiers.contains(&modifier) {
+                service_line.modifiers.push(modifier);
+            }
+            collect_modifiers(data);
+        }
+        FixPatch::RemoveModifier { line, modifier } => {
+            let service_line = line_mut(data, *line)?;
+            let before = service_line.modifiers.len();
+            service_line.modifiers.retain(|m| !m.eq_ignore_ascii_case(modifier.trim()));
+            if service_line.modifiers.len() == before {
+                bail!("Line {} has no modifier {}", line, modifier);
+            }
+            collect_modifiers(data);
+        }
+        FixPatch::RemoveLine { line } => {
+            line_mut(data, *line)?;
+            let removed = data.service_lines.remove(line - 1);
+            if let Some(index) = data.cpt_codes.iter().position(|code| *code == removed.procedure_code) {
+                data.cpt_codes.remove(index);
+            }
+            if let Some(index) = removed.charge.and_then(|charge| data.charges.iter().position(|c| *c == charge)) {
+                data.charges.remove(index);
+            }
+            collect_modifiers(data);
+        }
+    }
+    Ok(())
+}
+
+/// Checks a `fix` object from a rule definition. Service-line rules may
+/// leave out `line`; it is filled in with the line the rule fired on.
+pub fn parse_rule_fix(value: &Value, default_line: Option<usize>) -> Result<FixPatch> {
+    let mut value = value.clone();
+    if let (Some(line), Some(map)) = (default_line, value.as_object_mut()) {
+        map.entry("line").or_insert_with(|| Value::from(line));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(code: &str, modifiers: &[&str], charge: f64) -> ServiceLine {
+        ServiceLine {
+            procedure_code: code.to_string(),
+            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+            units: 1,
+            charge: Some(charge),
+            date_of_service: None,
+            diagnosis_pointers: vec![],
+        }
+    }
+
+    fn claim_data() -> ExtractedData {
+        ExtractedData {
+            cpt_codes: vec!["99213".to_string(), "20610".to_string(), "20610".to_string()],
+            charges: vec![125.0, 310.0, 310.0],
+            modifiers: vec!["RT".to_string()],
+            service_lines: vec![line("99213", &[], 125.0), line("20610", &["RT"], 310.0), line("20610", &["RT"], 310.0)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_line_patches_keep_claim_lists_in_step() {
+        let mut data = claim_data();
+        apply(&mut data, &FixPatch::AddModifier { line: 1, modifier: "25".to_string() }).unwrap();
+        assert_eq!(data.service_lines[0].modifiers, ["25"]);
+        assert_eq!(data.modifiers, ["25", "RT"]);
+
+        apply(&mut data, &FixPatch::RemoveLine { line: 3 }).unwrap();
+        assert_eq!(data.service_lines.len(), 2);
+        assert_eq!(data.cpt_codes, ["99213", "20610"]);
+        assert_eq!(data.charges, [125.0, 310.0]);
+
+        assert!(apply(&mut data, &FixPatch::RemoveLine { line: 0 }).is_err());
+        assert!(apply(&mut data, &FixPatch::RemoveModifier { line: 2, modifier: "59".to_string() }).is_err());
+    }
+
+    #[test]
+    fn test_set_field_checks_name_and_type() {
+        let mut data = claim_data();
+        let set = |field: &str, value: Value| FixPatch::SetField { field: field.to_string(), value };
+
+        apply(&mut data, &set("total_charge", Value::from(745.0))).unwrap();
+        assert_eq!(data.total_charge, Some(745.0));
+        assert!(apply(&mut data, &set("total_charge", Value::from("lots"))).is_err());
+        assert!(apply(&mut data, &set("no_such_field", Value::Null)).is_err());
+        assert!(apply(&mut data, &set("raw_text", Value::from(""))).is_err());
+        assert_eq!(data.total_charge, Some(745.0));
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Utc};
+use crate::types::Icd10Code;
+
+/// Where a diagnosis code stands for the fiscal year of the date of service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeStatus {
+    Billable,
+    /// A category or subcategory header; a more specific code is required.
+    Header,
+    /// Valid in other fiscal years only: deleted, or not yet effective.
+    NotEffective { fiscal_years: Vec<i32> },
+    Unknown,
+}
+
+/// Codes are stored without the dot, uppercased.
+pub fn normalize(code: &str) -> String {
+    code.trim()
+        .chars()
+        .filter(|c| *c != '.')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Inserts the dot after the three-character category.
+pub fn display(code: &str) -> String {
+    let code = normalize(code);
+    if code.len() > 3 {
+        format!("{}.{}", &code[..3], &code[3..])
+    } else {
+        code
+    }
+}
+
+/// ICD-10-CM fiscal years start on October 1 of the previous calendar year.
+pub fn fiscal_year(date: DateTime<Utc>) -> i32 {
+    if date.month() >= 10 {
+        date.year() + 1
+    } else {
+        date.year()
+    }
+}
+
+/// Parses a CMS release file for one fiscal year. Accepts the order file
+/// (`icd10cm_order_YYYY.txt`: order number, code, header flag, short and
+/// long description), which includes header codes, and the codes file
+/// (`icd10cm_codes_YYYY.txt`: code and description), which lists only
+/// billable codes.
+pub fn parse_release_file(contents: &str, fiscal_year: i32) -> Result<Vec<Icd10Code>> {
+    let mut codes = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let first = fields.next().unwrap_or_default();
+        let (code, billable, description) = if first.chars().all(|c| c.is_ascii_digit()) {
+            let code = fields.next();
+            let flag = fields.next();
+            match (code, flag) {
+                (Some(code), Some(flag @ ("0" | "1"))) => {
+                    // The long description starts at column 78; older files
+                    // without it fall back to the short one.
+                    let description = line.get(77..)
+                        .or_else(|| line.get(16..))
+                        .unwrap_or_default()
+                        .trim();
+                    (code, flag == "1", description)
+                }
+                _ => bail!("Line {}: expected order number, code and header flag", index + 1),
+            }
+        } else {
+            (first, true, line[first.len()..].trim())
+        };
+
+        let code = normalize(code);
+        if code.len() < 3 || code.len() > 7 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            bail!("Line {}: invalid ICD-10-CM code {}", index + 1, code);
+        }
+
+        codes.push(Icd10Code {
+            code,
+            description: description.to_string(),
+            billable,
+            fiscal_year,
+        });
+    }
+
+    Ok(codes)
+}
+
+/// `rows` holds every imported row for the code, across fiscal years.
+pub fn status(rows: &[Icd10Code], fiscal_year: i32) -> CodeStatus {
+    if let Some(row) = rows.iter().find(|row| row.fiscal_year == fiscal_year) {
+        return if row.billable { CodeStatus::Billable } else { CodeStatus::Header };
+    }
+
+    let mut fiscal_years: Vec<i32> = rows.iter().filter(|row| row.billable).map(|row| row.fiscal_year).collect();
+    fiscal_years.sort();
+    fiscal_years.dedup();
+
+    if fiscal_years.is_empty() {
+        CodeStatus::Unknown
+    } else {
+        CodeStatus::NotEffective { fiscal_years }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn order_line(order: u32, code: &str, billable: bool, short: &str, long: &str) -> String {
+        format!("{:05} {:<7} {} {:<60} {}\n", order, code, if billable { 1 } else { 0 }, short, long)
+    }
+
+    #[test]
+    fn test_parses_order_file_header_flags() {
+        let file = [
+            order_line(1, "A00", false, "Cholera", "Cholera"),
+            order_line(2, "A000", true, "Cholera due to Vibrio cholerae 01, biovar cholerae", "Cholera due to Vibrio cholerae 01, biovar cholerae"),
+            order_line(29402, "S72001A", true, "Fracture of unsp part of neck of right femur, init", "Fracture of unspecified part of neck of right femur, initial encounter for closed fracture"),
+        ]
+        .concat();
+
+        let codes = parse_release_file(&file, 2024).unwrap();
+        assert_eq!(codes.len(), 3);
+        assert!(!codes[0].billable);
+        assert!(codes[2].billable);
+        assert_eq!(codes[2].code, "S72001A");
+        assert!(codes[2].description.starts_with("Fracture of unspecified part"));
+    }
+
+    #[test]
+    fn test_parses_codes_file() {
+        let codes = parse_release_file("A000    Cholera due to Vibrio cholerae 01, biovar cholerae\n", 2024).unwrap();
+        assert_eq!(codes[0].code, "A000");
+        assert!(codes[0].billable);
+    }
+
+    #[test]
+    fn test_fiscal_year_starts_in_october() {
+        assert_eq!(fiscal_year(Utc.with_ymd_and_hms(2023, 9, 30, 0, 0, 0).unwrap()), 2023);
+        assert_eq!(fiscal_year(Utc.with_ymd_and_hms(2023, 10, 1, 0, 0, 0).unwrap()), 2024);
+    }
+
+    #[test]
+    fn test_status_by_fiscal_year() {
+        let row = |fiscal_year, billable| Icd10Code {
+            code: "A000".to_string(),
+            description: String::new(),
+            billable,
+            fiscal_year,
+        };
+
+        assert_eq!(status(&[row(2024, true)], 2024), CodeStatus::Billable);
+        assert_eq!(status(&[row(2024, false)], 2024), CodeStatus::Header);
+        assert_eq!(status(&[row(2023, true)], 2024), CodeStatus::NotEffective { fiscal_years: vec![2023] });
+        assert_eq!(status(&[], 2024), CodeStatus::Unknown);
+        assert_eq!(display("s72001a"), "S72.001A");
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+/// Environment variable overriding where the data encryption key is kept.
+/// The key stays out of the database, like the audit signing key, so a copy
+/// of the database alone doesn't carry the key to its own contents.
+pub const KEY_FILE_ENV: &str = "CLAIMSENSE_ENCRYPTION_KEY_FILE";
+const DEFAULT_KEY_FILE: &str = "claimsense.key";
+/// `tauri.bundle.identifier`, which names the app data directory.
+const APP_IDENTIFIER: &str = "com.claimsense.app";
+
+/// What settings show in place of a configured key. Saving it back leaves
+/// the key unchanged.
+pub const REDACTED: &str = "********";
+
+/// The override, else the key file in the app data directory (the one
+/// Tauri's `app_data_dir` resolves to), never the working directory.
+fn key_path() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var(KEY_FILE_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    tauri::api::path::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER).join(DEFAULT_KEY_FILE))
+        .ok_or_else(|| anyhow!("No application data directory to keep the encryption key in"))
+}
+
+pub fn has_key() -> bool {
+    key_path().is_ok_and(|path| path.is_file())
+}
+
+pub fn save_key(key: &str) -> Result<()> {
+    let path = key_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    write_key(&path, key)
+}
+
+/// Writes the key readable by the owner only. A new file is created with
+/// those permissions rather than narrowed after the key is in it, and an
+/// existing one is narrowed before it is written.
+fn write_key(path: &Path, key: &str) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(key.trim().as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_file_is_private() {
+        let path = std::env::temp_dir().join(format!("claimsense-key-{}", uuid::Uuid::new_v4()));
+        write_key(&path, " 00112233 \n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "00112233");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+            // A key file left readable by an older version is narrowed too.
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            write_key(&path, "44556677").unwrap();
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,20 +1,44 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod audit;
+mod auth;
+mod backtest;
 mod commands;
+mod coverage;
 mod database;
+mod declarative_rules;
+mod demographics;
+mod duplicates;
 mod encryption;
+mod evidence;
+mod fee_schedule;
+mod fixes;
+mod icd10;
+mod key_store;
+mod mfa;
+mod modifiers;
+mod mue;
+mod ncci;
+mod npi;
 mod ocr;
 mod parser;
+mod payers;
+mod procedure_codes;
+mod reference_data;
+mod rule_packs;
+mod rule_tests;
 mod rules;
 mod types;
 
+use auth::SessionStore;
 use commands::*;
 use database::Database;
 use std::sync::Mutex;
 
 pub struct AppState {
     pub db: Mutex<Database>,
+    pub sessions: Mutex<SessionStore>,
 }
 
 #[tokio::main]
@@ -22,6 +46,7 @@ async fn main() {
     let db = Database::new().await.expect("Failed to initialize database");
     let app_state = AppState {
         db: Mutex::new(db),
+        sessions: Mutex::new(SessionStore::new()),
     };
 
     tauri::Builder::default()
@@ -37,13 +62,50 @@ async fn main() {
             get_analytics,
             create_user,
             authenticate_user,
+            verify_mfa,
+            logout,
+            begin_mfa_enrollment,
+            confirm_mfa_enrollment,
+            regenerate_recovery_codes,
+            disable_mfa,
+            get_mfa_policy,
+            update_mfa_policy,
             get_users,
             update_user,
             delete_user,
+            unlock_user,
+            unlock_workstation,
             get_audit_logs,
+            verify_audit_log,
+            export_audit_logs,
+            verify_audit_export,
             export_claims,
             get_settings,
-            update_settings
+            update_settings,
+            validate_rules_config,
+            get_rule_configuration,
+            update_rule_configuration,
+            import_icd10_codes,
+            import_procedure_codes,
+            import_ncci_edits,
+            import_mue_limits,
+            import_modifiers,
+            import_fee_schedule,
+            import_coverage_policies,
+            import_code_attributes,
+            get_payers,
+            save_payer,
+            delete_payer,
+            get_rule_packs,
+            save_rule_pack,
+            delete_rule_pack,
+            export_rule_packs,
+            import_rule_packs,
+            run_rule_tests,
+            backtest_rules,
+            apply_fix,
+            apply_fixes_to_queue,
+            get_claim_history
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
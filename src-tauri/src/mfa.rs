@@ -0,0 +1,174 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+const ISSUER: &str = "ClaimsSense";
+const SECRET_BYTES: usize = 20;
+const TIME_STEP_SECONDS: i64 = 30;
+const CODE_DIGITS: u32 = 6;
+/// Steps either side of the current one that are still accepted, to absorb
+/// clock drift between the workstation and the authenticator app.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+
+/// TOTP columns kept on the user row.
+#[derive(Debug, Clone)]
+pub struct MfaCredentials {
+    pub secret: Option<String>,
+    pub enabled: bool,
+    /// Last accepted time step; codes at or before it are rejected as replays.
+    pub last_used_step: Option<i64>,
+}
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+/// The `otpauth://` URI authenticator apps expect; the frontend renders it
+/// as a QR code.
+pub fn provisioning_uri(secret: &str, username: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{label}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = ISSUER,
+        label = percent_encode(username),
+        secret = secret,
+        digits = CODE_DIGITS,
+        period = TIME_STEP_SECONDS,
+    )
+}
+
+/// RFC 4226 HOTP value for a given counter.
+pub fn hotp(secret: &[u8], counter: u64) -> Result<u32> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret)
+        .map_err(|e| anyhow::anyhow!("Invalid TOTP secret: {}", e))?;
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    Ok(binary % 10u32.pow(CODE_DIGITS))
+}
+
+pub fn time_step(at: DateTime<Utc>) -> i64 {
+    at.timestamp().div_euclid(TIME_STEP_SECONDS)
+}
+
+/// Checks a six-digit code against the steps around `now`. Returns the
+/// matching time step so callers can refuse to accept it twice.
+pub fn verify_totp(secret: &str, code: &str, now: DateTime<Utc>) -> Result<Option<i64>> {
+    let code = code.trim();
+    if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let expected: u32 = code.parse()?;
+
+    let secret = BASE32_NOPAD.decode(secret.trim_end_matches('=').as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid TOTP secret: {}", e))?;
+
+    let current = time_step(now);
+    for step in (current - ALLOWED_DRIFT_STEPS)..=(current + ALLOWED_DRIFT_STEPS) {
+        if step >= 0 && hotp(&secret, step as u64)? == expected {
+            return Ok(Some(step));
+        }
+    }
+
+    Ok(None)
+}
+
+/// One-time recovery codes in `xxxxx-xxxxx` form. Only their hashes are stored.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_sha1_vectors() {
+        let vectors = [
+            (59, 287082),
+            (1111111109, 81804),
+            (1111111111, 50471),
+            (1234567890, 5924),
+            (2000000000, 279037),
+        ];
+
+        for (timestamp, expected) in vectors {
+            let at = Utc.timestamp_opt(timestamp, 0).unwrap();
+            assert_eq!(hotp(RFC_SECRET, time_step(at) as u64).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_verify_totp_accepts_adjacent_step_only() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        let at = Utc.timestamp_opt(1111111109, 0).unwrap();
+
+        assert_eq!(verify_totp(&secret, "081804", at).unwrap(), Some(time_step(at)));
+        let later = Utc.timestamp_opt(1111111109 + 30, 0).unwrap();
+        assert!(verify_totp(&secret, "081804", later).unwrap().is_some());
+        let much_later = Utc.timestamp_opt(1111111109 + 90, 0).unwrap();
+        assert!(verify_totp(&secret, "081804", much_later).unwrap().is_none());
+        assert!(verify_totp(&secret, "81804", at).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_recovery_code_hash_ignores_formatting() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let code = &codes[0];
+        assert_eq!(hash_recovery_code(code), hash_recovery_code(&code.replace('-', "").to_uppercase()));
+    }
+
+    #[test]
+    fn test_provisioning_uri_encodes_label() {
+        let uri = provisioning_uri("ABC", "jane doe@clinic");
+        assert!(uri.starts_with("otpauth://totp/ClaimsSense:jane%20doe%40clinic?secret=ABC"));
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, bail, Result};
+use crate::types::{BilledService, Modifier};
+
+/// Commonly used CPT and HCPCS Level II modifiers, seeded into the
+/// reference table on first run. Payer-specific modifiers are imported.
+pub const STANDARD_MODIFIERS: &[(&str, &str)] = &[
+    ("22", "Increased procedural services"),
+    ("23", "Unusual anesthesia"),
+    ("24", "Unrelated E/M service during a postoperative period"),
+    ("25", "Significant, separately identifiable E/M service on the same day"),
+    ("26", "Professional component"),
+    ("27", "Multiple outpatient hospital E/M encounters on the same date"),
+    ("32", "Mandated services"),
+    ("33", "Preventive services"),
+    ("47", "Anesthesia by surgeon"),
+    ("50", "Bilateral procedure"),
+    ("51", "Multiple procedures"),
+    ("52", "Reduced services"),
+    ("53", "Discontinued procedure"),
+    ("54", "Surgical care only"),
+    ("55", "Postoperative management only"),
+    ("56", "Preoperative management only"),
+    ("57", "Decision for surgery"),
+    ("58", "Staged or related procedure during the postoperative period"),
+    ("59", "Distinct procedural service"),
+    ("62", "Two surgeons"),
+    ("63", "Procedure performed on infants less than 4 kg"),
+    ("66", "Surgical team"),
+    ("73", "Discontinued outpatient procedure prior to anesthesia"),
+    ("74", "Discontinued outpatient procedure after anesthesia"),
+    ("76", "Repeat procedure by the same physician"),
+    ("77", "Repeat procedure by another physician"),
+    ("78", "Unplanned return to the operating room during the postoperative period"),
+    ("79", "Unrelated procedure during the postoperative period"),
+    ("80", "Assistant surgeon"),
+    ("81", "Minimum assistant surgeon"),
+    ("82", "Assistant surgeon when a qualified resident is not available"),
+    ("90", "Reference (outside) laboratory"),
+    ("91", "Repeat clinical diagnostic laboratory test"),
+    ("92", "Alternative laboratory platform testing"),
+    ("93", "Synchronous telemedicine service via audio only"),
+    ("95", "Synchronous telemedicine service via audio and video"),
+    ("96", "Habilitative services"),
+    ("97", "Rehabilitative services"),
+    ("99", "Multiple modifiers"),
+    ("AI", "Principal physician of record"),
+    ("AS", "Assistant at surgery by a physician assistant or nurse practitioner"),
+    ("CO", "Outpatient occupational therapy by an assistant"),
+    ("CQ", "Outpatient physical therapy by an assistant"),
+    ("CR", "Catastrophe or disaster related"),
+    ("CS", "Cost-sharing waived"),
+    ("E1", "Upper left eyelid"),
+    ("E2", "Lower left eyelid"),
+    ("E3", "Upper right eyelid"),
+    ("E4", "Lower right eyelid"),
+    ("FA", "Left hand, thumb"),
+    ("F1", "Left hand, second digit"),
+    ("F2", "Left hand, third digit"),
+    ("F3", "Left hand, fourth digit"),
+    ("F4", "Left hand, fifth digit"),
+    ("F5", "Right hand, thumb"),
+    ("F6", "Right hand, second digit"),
+    ("F7", "Right hand, third digit"),
+    ("F8", "Right hand, fourth digit"),
+    ("F9", "Right hand, fifth digit"),
+    ("FS", "Split or shared E/M visit"),
+    ("FT", "Unrelated E/M visit on the same day as another E/M visit"),
+    ("GA", "Waiver of liability statement issued"),
+    ("GC", "Performed in part by a resident under a teaching physician"),
+    ("GE", "Performed by a resident without a teaching physician under the primary care exception"),
+    ("GN", "Services delivered under an outpatient speech-language pathology plan of care"),
+    ("GO", "Services delivered under an outpatient occupational therapy plan of care"),
+    ("GP", "Services delivered under an outpatient physical therapy plan of care"),
+    ("GT", "Via interactive audio and video telecommunication systems"),
+    ("GV", "Attending physician not employed or paid under arrangement by the hospice"),
+    ("GW", "Service not related to the hospice patient's terminal condition"),
+    ("GX", "Notice of liability issued, voluntary under payer policy"),
+    ("GY", "Statutorily excluded or does not meet the definition of a benefit"),
+    ("GZ", "Item or service expected to be denied as not reasonable and necessary"),
+    ("JW", "Drug amount discarded"),
+    ("JZ", "Zero drug amount discarded"),
+    ("KX", "Requirements specified in the medical policy have been met"),
+    ("LC", "Left circumflex coronary artery"),
+    ("LD", "Left anterior descending coronary artery"),
+    ("LM", "Left main coronary artery"),
+    ("LT", "Left side"),
+    ("PD", "Diagnostic or related non-diagnostic item or service within 3 days of admission"),
+    ("PN", "Non-excepted service provided at an off-campus provider-based department"),
+    ("PO", "Excepted service provided at an off-campus provider-based department"),
+    ("Q5", "Service furnished under a reciprocal billing arrangement"),
+    ("Q6", "Service furnished under a fee-for-time compensation arrangement"),
+    ("QK", "Medical direction of two, three or four concurrent anesthesia procedures"),
+    ("QW", "CLIA waived test"),
+    ("QX", "CRNA service with medical direction by a physician"),
+    ("QY", "Medical direction of one CRNA by an anesthesiologist"),
+    ("QZ", "CRNA service without medical direction by a physician"),
+    ("RC", "Right coronary artery"),
+    ("RI", "Ramus intermedius coronary artery"),
+    ("RT", "Right side"),
+    ("SA", "Nurse practitioner rendering service in collaboration with a physician"),
+    ("SG", "Ambulatory surgical center facility service"),
+    ("TA", "Left foot, great toe"),
+    ("T1", "Left foot, second digit"),
+    ("T2", "Left foot, third digit"),
+    ("T3", "Left foot, fourth digit"),
+    ("T4", "Left foot, fifth digit"),
+    ("T5", "Right foot, great toe"),
+    ("T6", "Right foot, second digit"),
+    ("T7", "Right foot, third digit"),
+    ("T8", "Right foot, fourth digit"),
+    ("T9", "Right foot, fifth digit"),
+    ("TC", "Technical component"),
+    ("XE", "Separate encounter"),
+    ("XP", "Separate practitioner"),
+    ("XS", "Separate structure or organ"),
+    ("XU", "Unusual non-overlapping service"),
+];
+
+/// Pairs that contradict each other on a single line.
+const CONFLICTING_PAIRS: &[(&str, &str, &str)] = &[
+    ("LT", "RT", "report a bilateral procedure with modifier 50 instead"),
+    ("50", "LT", "modifier 50 already covers both sides"),
+    ("50", "RT", "modifier 50 already covers both sides"),
+    ("26", "TC", "bill the global service without either modifier instead"),
+    ("59", "XE", "use the X modifier alone"),
+    ("59", "XP", "use the X modifier alone"),
+    ("59", "XS", "use the X modifier alone"),
+    ("59", "XU", "use the X modifier alone"),
+];
+
+/// Anatomic modifiers naming a single site; two from one family on the same
+/// line describe different sites and need separate lines.
+const SITE_FAMILIES: &[&[&str]] = &[
+    &["FA", "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9"],
+    &["TA", "T1", "T2", "T3", "T4", "T5", "T6", "T7", "T8", "T9"],
+    &["E1", "E2", "E3", "E4"],
+    &["LC", "LD", "LM", "RC", "RI"],
+];
+
+pub const DISTINCT_SERVICE_MODIFIERS: &[&str] = &["XE", "XP", "XS", "XU"];
+
+pub fn is_valid_format(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Parses a CSV of `code,description` rows; a header row is optional.
+pub fn parse_modifier_file(contents: &str) -> Result<Vec<Modifier>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+
+    let mut modifiers = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let code = record.get(0).ok_or_else(|| anyhow!("Line {}: missing modifier", index + 1))?.to_ascii_uppercase();
+        if index == 0 && (code == "CODE" || code == "MODIFIER") {
+            continue;
+        }
+        if !is_valid_format(&code) {
+            bail!("Line {}: invalid modifier {}", index + 1, code);
+        }
+
+        modifiers.push(Modifier {
+            code,
+            description: record.get(1).unwrap_or_default().to_string(),
+        });
+    }
+
+    Ok(modifiers)
+}
+
+/// Contradictory modifiers on one service, with the reason for each pair.
+pub fn conflicts(service: &BilledService) -> Vec<(String, String, &'static str)> {
+    let has = |code: &str| service.modifiers.iter().any(|m| m == code);
+    let mut found: Vec<(String, String, &'static str)> = CONFLICTING_PAIRS
+        .iter()
+        .filter(|(a, b, _)| has(a) && has(b))
+        .map(|(a, b, reason)| (a.to_string(), b.to_string(), *reason))
+        .collect();
+
+    for family in SITE_FAMILIES {
+        let sites: Vec<&String> = service.modifiers.iter().filter(|m| family.contains(&m.as_str())).collect();
+        if sites.len() > 1 {
+            found.push((sites[0].clone(), sites[1].clone(), "each site must be reported on its own line"));
+        }
+    }
+
+    found
+}
+
+pub fn is_evaluation_and_management(code: &str) -> bool {
+    code.parse::<u32>().is_ok_and(|n| (99202..=99499).contains(&n))
+}
+
+/// CPT surgery section; these procedures carry a global period, so an E/M
+/// on the same day needs modifier 25.
+pub fn is_surgical_procedure(code: &str) -> bool {
+    code.parse::<u32>().is_ok_and(|n| (10004..=69990).contains(&n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(code: &str, modifiers: &[&str]) -> BilledService {
+        BilledService {
+            procedure_code: code.to_string(),
+            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+            units: 1,
+            date_of_service: None,
+        }
+    }
+
+    #[test]
+    fn test_detects_conflicting_modifiers() {
+        assert_eq!(conflicts(&service("20610", &["LT", "RT"])).len(), 1);
+        assert_eq!(conflicts(&service("26055", &["F1", "F2"])).len(), 1);
+        assert!(conflicts(&service("20610", &["RT", "59"])).is_empty());
+    }
+
+    #[test]
+    fn test_standard_modifiers_are_well_formed() {
+        assert!(STANDARD_MODIFIERS.iter().all(|(code, _)| is_valid_format(code)));
+        assert!(is_evaluation_and_management("99213"));
+        assert!(is_surgical_procedure("20610"));
+        assert!(!is_surgical_procedure("J3301"));
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use crate::procedure_codes;
+use crate::types::{BilledService, MueLimit, NcciSetting};
+
+/// Billed units above a code's MUE value.
+#[derive(Debug, Clone)]
+pub struct MueViolation<'a> {
+    pub limit: &'a MueLimit,
+    pub units: u32,
+    pub date_of_service: Option<NaiveDate>,
+}
+
+/// Parses a CMS MUE table exported as CSV: code, MUE value, MUE
+/// adjudication indicator (e.g. `2 Date of Service Edit: Policy`) and
+/// rationale. Title and copyright lines above the data are skipped.
+pub fn parse_mue_file(contents: &str, setting: NcciSetting) -> Result<Vec<MueLimit>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+
+    let mut limits = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let code = record.get(0).unwrap_or_default();
+        if record.len() < 3 || !procedure_codes::is_valid_format(code) {
+            continue;
+        }
+
+        let line = index + 1;
+        let Ok(mue_value) = record[1].parse::<u32>() else {
+            bail!("Line {}: invalid MUE value {}", line, &record[1]);
+        };
+        let adjudication_indicator = match record[2].chars().next() {
+            Some('1') => 1,
+            Some('2') => 2,
+            Some('3') => 3,
+            _ => bail!("Line {}: invalid MUE adjudication indicator {}", line, &record[2]),
+        };
+
+        limits.push(MueLimit {
+            setting,
+            code: code.to_string(),
+            mue_value,
+            adjudication_indicator,
+            rationale: record.get(3).unwrap_or_default().to_string(),
+        });
+    }
+
+    Ok(limits)
+}
+
+/// Indicator 1 edits apply to each line on its own; indicators 2 and 3
+/// apply to the units of a code summed across the date of service.
+pub fn find_violations<'a>(services: &[BilledService], limits: &'a HashMap<String, MueLimit>) -> Vec<MueViolation<'a>> {
+    let mut violations = Vec::new();
+    let mut per_date: Vec<(&str, Option<NaiveDate>, u32)> = Vec::new();
+
+    for service in services {
+        let Some(limit) = limits.get(&service.procedure_code) else { continue };
+
+        if limit.adjudication_indicator == 1 {
+            if service.units > limit.mue_value {
+                violations.push(MueViolation { limit, units: service.units, date_of_service: service.date_of_service });
+            }
+            continue;
+        }
+
+        match per_date.iter_mut().find(|(code, date, _)| *code == service.procedure_code && *date == service.date_of_service) {
+            Some((_, _, units)) => *units += service.units,
+            None => per_date.push((&service.procedure_code, service.date_of_service, service.units)),
+        }
+    }
+
+    for (code, date_of_service, units) in per_date {
+        let limit = &limits[code];
+        if units > limit.mue_value {
+            violations.push(MueViolation { limit, units, date_of_service });
+        }
+    }
+
+    violations
+}
+
+pub fn describe_indicator(adjudication_indicator: u8) -> &'static str {
+    match adjudication_indicator {
+        1 => "line edit",
+        2 => "date of service edit, policy",
+        _ => "date of service edit, clinical",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(code: &str, units: u32) -> BilledService {
+        BilledService {
+            procedure_code: code.to_string(),
+            modifiers: vec![],
+            units,
+            date_of_service: NaiveDate::from_ymd_opt(2024, 3, 15),
+        }
+    }
+
+    #[test]
+    fn test_parses_cms_mue_csv() {
+        let file = "\"CMS MUE table\"\nHCPCS/CPT Code,Practitioner Services MUE Values,MUE Adjudication Indicator,MUE Rationale\n\
+                    20610,2,2 Date of Service Edit: Policy,Anatomic Consideration\nJ1030,8,3 Date of Service Edit: Clinical,Clinical Data\n";
+        let limits = parse_mue_file(file, NcciSetting::Practitioner).unwrap();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits[0].mue_value, 2);
+        assert_eq!(limits[1].adjudication_indicator, 3);
+    }
+
+    #[test]
+    fn test_line_and_date_of_service_edits() {
+        let limit = |code: &str, mue_value, adjudication_indicator| MueLimit {
+            setting: NcciSetting::Practitioner,
+            code: code.to_string(),
+            mue_value,
+            adjudication_indicator,
+            rationale: String::new(),
+        };
+        let limits: HashMap<String, MueLimit> = [limit("20610", 2, 2), limit("96372", 4, 1)]
+            .into_iter()
+            .map(|l| (l.code.clone(), l))
+            .collect();
+
+        let violations = find_violations(&[service("20610", 2), service("20610", 1)], &limits);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].units, 3);
+
+        assert!(find_violations(&[service("96372", 3), service("96372", 3)], &limits).is_empty());
+        assert_eq!(find_violations(&[service("96372", 12)], &limits).len(), 1);
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use crate::procedure_codes;
+use crate::types::{BilledService, NcciEdit, NcciSetting};
+
+/// NCCI-associated modifiers that mark a column-2 service as distinct when
+/// the edit's modifier indicator is 1.
+pub const BYPASS_MODIFIERS: &[&str] = &["59", "XE", "XS", "XP", "XU"];
+
+/// A column-1/column-2 pair billed on the same date of service.
+#[derive(Debug, Clone)]
+pub struct PtpConflict<'a> {
+    pub edit: &'a NcciEdit,
+    pub date_of_service: Option<NaiveDate>,
+}
+
+/// Parses a CMS PTP edit table exported as tab- or comma-delimited text:
+/// column 1, column 2, "in existence prior to 1996" flag, effective date,
+/// deletion date (`*` for none), modifier indicator, and rationale. Title
+/// and copyright lines above the data are skipped.
+pub fn parse_ptp_file(contents: &str, setting: NcciSetting) -> Result<Vec<NcciEdit>> {
+    let mut edits = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let delimiter = if line.contains('\t') { '\t' } else { ',' };
+        let fields: Vec<&str> = line.split(delimiter).map(|f| f.trim().trim_matches('"')).collect();
+        if fields.len() < 6 || !procedure_codes::is_valid_format(fields[0]) || !procedure_codes::is_valid_format(fields[1]) {
+            continue;
+        }
+
+        let line_number = index + 1;
+        let Some(effective_date) = parse_date(fields[3]) else {
+            bail!("Line {}: invalid effective date {}", line_number, fields[3]);
+        };
+        let deletion_date = match fields[4] {
+            "" | "*" => None,
+            value => match parse_date(value) {
+                Some(date) => Some(date),
+                None => bail!("Line {}: invalid deletion date {}", line_number, value),
+            },
+        };
+        let modifier_indicator = match fields[5] {
+            "0" => 0,
+            "1" => 1,
+            "9" => 9,
+            value => bail!("Line {}: invalid modifier indicator {}", line_number, value),
+        };
+
+        edits.push(NcciEdit {
+            setting,
+            column1: fields[0].to_string(),
+            column2: fields[1].to_string(),
+            effective_date,
+            deletion_date,
+            modifier_indicator,
+            rationale: fields.get(6).map(|r| r.to_string()).unwrap_or_default(),
+        });
+    }
+
+    Ok(edits)
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y%m%d").ok()
+}
+
+/// Without a date of service every edit that has not been deleted applies.
+pub fn is_active(edit: &NcciEdit, date_of_service: Option<NaiveDate>) -> bool {
+    match date_of_service {
+        Some(date) => edit.effective_date <= date && edit.deletion_date.is_none_or(|end| date < end),
+        None => edit.deletion_date.is_none(),
+    }
+}
+
+/// Finds edit pairs billed on the same date. Pairs whose edit allows a
+/// modifier (indicator 1) pass when either service carries a bypass
+/// modifier; indicator 9 edits are inactive.
+pub fn find_conflicts<'a>(services: &[BilledService], edits: &'a [NcciEdit]) -> Vec<PtpConflict<'a>> {
+    let mut conflicts = Vec::new();
+
+    for column1 in services {
+        for column2 in services {
+            if column1.procedure_code == column2.procedure_code || column1.date_of_service != column2.date_of_service {
+                continue;
+            }
+
+            let edit = edits.iter().find(|edit| {
+                edit.column1 == column1.procedure_code
+                    && edit.column2 == column2.procedure_code
+                    && edit.modifier_indicator != 9
+                    && is_active(edit, column1.date_of_service)
+            });
+            let Some(edit) = edit else { continue };
+
+            let bypassed = edit.modifier_indicator == 1
+                && [column1, column2].iter().any(|service| {
+                    service.modifiers.iter().any(|m| BYPASS_MODIFIERS.contains(&m.as_str()))
+                });
+            let already_reported = conflicts.iter().any(|c: &PtpConflict| {
+                std::ptr::eq(c.edit, edit) && c.date_of_service == column1.date_of_service
+            });
+            if !bypassed && !already_reported {
+                conflicts.push(PtpConflict { edit, date_of_service: column1.date_of_service });
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(code: &str, modifiers: &[&str]) -> BilledService {
+        BilledService {
+            procedure_code: code.to_string(),
+            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+            units: 1,
+            date_of_service: NaiveDate::from_ymd_opt(2024, 3, 15),
+        }
+    }
+
+    #[test]
+    fn test_parses_tab_delimited_file() {
+        let file = "CMS NCCI PTP edits\nColumn 1\tColumn 2\t*\tEffective\tDeletion\tModifier\tRationale\n\
+                    29881\t29880\t*\t20040101\t*\t1\tMisuse of column two code with column one code\n\
+                    99213\t36415\t \t20100101\t20120101\t9\tStandards of medical practice\n";
+        let edits = parse_ptp_file(file, NcciSetting::Practitioner).unwrap();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].column1, "29881");
+        assert!(edits[0].deletion_date.is_none());
+        assert_eq!(edits[1].modifier_indicator, 9);
+    }
+
+    #[test]
+    fn test_modifier_indicator_controls_bypass() {
+        let edit = |column2: &str, modifier_indicator| NcciEdit {
+            setting: NcciSetting::Practitioner,
+            column1: "29881".to_string(),
+            column2: column2.to_string(),
+            effective_date: NaiveDate::from_ymd_opt(2004, 1, 1).unwrap(),
+            deletion_date: None,
+            modifier_indicator,
+            rationale: String::new(),
+        };
+        let edits = vec![edit("29880", 1), edit("29877", 0)];
+
+        let conflicts = find_conflicts(&[service("29881", &[]), service("29880", &[])], &edits);
+        assert_eq!(conflicts.len(), 1);
+        assert!(find_conflicts(&[service("29881", &[]), service("29880", &["XS"])], &edits).is_empty());
+        assert_eq!(find_conflicts(&[service("29881", &[]), service("29877", &["59"])], &edits).len(), 1);
+    }
+}
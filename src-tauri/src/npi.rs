@@ -0,0 +1,85 @@
+/// Card issuer prefix the NPI check digit is computed with, per the CMS NPI
+/// standard (ISO 7812 health industry identifier).
+const ISSUER_PREFIX: &str = "80840";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NpiError {
+    #[error("NPI must be 10 digits, found {0} characters")]
+    WrongLength(usize),
+    #[error("NPI must contain only digits")]
+    NonDigit,
+    #[error("NPI must start with 1 or 2")]
+    InvalidPrefix,
+    #[error("NPI check digit should be {expected}, found {found}")]
+    CheckDigit { expected: u32, found: u32 },
+}
+
+/// Luhn check digit for the first nine digits of an NPI, computed over the
+/// `80840` prefix followed by those digits.
+pub fn check_digit(first_nine: &str) -> Option<u32> {
+    let digits: Vec<u32> = ISSUER_PREFIX
+        .chars()
+        .chain(first_nine.chars())
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()?;
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 0 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                d
+            }
+        })
+        .sum();
+
+    Some((10 - sum % 10) % 10)
+}
+
+pub fn validate(npi: &str) -> Result<(), NpiError> {
+    let npi = npi.trim();
+    if npi.len() != 10 {
+        return Err(NpiError::WrongLength(npi.chars().count()));
+    }
+    if !npi.chars().all(|c| c.is_ascii_digit()) {
+        return Err(NpiError::NonDigit);
+    }
+    if !npi.starts_with('1') && !npi.starts_with('2') {
+        return Err(NpiError::InvalidPrefix);
+    }
+
+    let expected = check_digit(&npi[..9]).ok_or(NpiError::NonDigit)?;
+    let found = npi[9..].parse().map_err(|_| NpiError::NonDigit)?;
+    if expected != found {
+        return Err(NpiError::CheckDigit { expected, found });
+    }
+
+    Ok(())
+}
+
+pub fn is_valid(npi: &str) -> bool {
+    validate(npi).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_cms_example_npi() {
+        assert_eq!(check_digit("123456789"), Some(3));
+        assert!(is_valid("1234567893"));
+    }
+
+    #[test]
+    fn test_rejects_bad_check_digit_and_format() {
+        assert_eq!(validate("1234567890"), Err(NpiError::CheckDigit { expected: 3, found: 0 }));
+        assert_eq!(validate("123456789"), Err(NpiError::WrongLength(9)));
+        assert_eq!(validate("12345678a3"), Err(NpiError::NonDigit));
+        assert_eq!(validate("5551234567"), Err(NpiError::InvalidPrefix));
+    }
+}
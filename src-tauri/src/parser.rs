@@ -1,7 +1,9 @@
 use anyhow::Result;
 use regex::Regex;
-use crate::types::ExtractedData;
-use chrono::{DateTime, Utc};
+use crate::icd10;
+use crate::npi;
+use crate::types::{DateRole, ExtractedData, ExtractedDate, ServiceLine, Sex};
+use chrono::{DateTime, NaiveDate, Utc};
 
 pub struct ClaimParser;
 
@@ -12,29 +14,30 @@ impl ClaimParser {
 
     pub async fn parse_text(&self, text: &str) -> Result<ExtractedData> {
         let mut extracted = ExtractedData {
-            payer: None,
-            patient_name: None,
-            patient_id: None,
-            cpt_codes: vec![],
-            modifiers: vec![],
-            charges: vec![],
-            dates: vec![],
-            provider_name: None,
-            provider_npi: None,
-            diagnosis_codes: vec![],
             raw_text: text.to_string(),
+            ..Default::default()
         };
 
-        // Extract CPT codes (5-digit codes)
-        let cpt_regex = Regex::new(r"\b\d{5}\b")?;
-        for mat in cpt_regex.find_iter(text) {
-            extracted.cpt_codes.push(mat.as_str().to_string());
-        }
-
-        // Extract modifiers (2-character codes)
-        let modifier_regex = Regex::new(r"\b[A-Z]{2}\b")?;
-        for mat in modifier_regex.find_iter(text) {
-            extracted.modifiers.push(mat.as_str().to_string());
+        // Procedure codes come from the service lines when there are any,
+        // so a ZIP code in an address isn't billed. Otherwise every CPT
+        // (including Category II/III and PLA) and HCPCS Level II shaped
+        // token is taken; HCPCS-shaped tokens on diagnosis lines are
+        // undotted ICD-10 codes, so those lines only yield CPT codes.
+        extracted.service_lines = self.parse_service_lines(text)?;
+        let diagnosis_line_regex = Regex::new(r"(?i)\b(?:diagnos[ie]s|dx|icd)")?;
+        if extracted.service_lines.is_empty() {
+            let cpt_regex = Regex::new(r"\b(?:\d{4}[0-9FTU]|[A-V]\d{4})\b")?;
+            for line in text.lines() {
+                let diagnosis_line = diagnosis_line_regex.is_match(line);
+                for mat in cpt_regex.find_iter(line) {
+                    if diagnosis_line && !mat.as_str().starts_with(|c: char| c.is_ascii_digit()) {
+                        continue;
+                    }
+                    extracted.cpt_codes.push(mat.as_str().to_string());
+                }
+            }
+        } else {
+            extracted.cpt_codes = extracted.service_lines.iter().map(|line| line.procedure_code.clone()).collect();
         }
 
         // Extract charges (dollar amounts)
@@ -46,34 +49,52 @@ impl ClaimParser {
             }
         }
 
-        // Extract dates (various formats)
-        let date_patterns = vec![
-            r"\b\d{1,2}/\d{1,2}/\d{4}\b",  // MM/DD/YYYY
-            r"\b\d{4}-\d{2}-\d{2}\b",      // YYYY-MM-DD
-            r"\b\d{1,2}-\d{1,2}-\d{4}\b",  // MM-DD-YYYY
-        ];
+        let total_regex = Regex::new(r"(?i)\btotal charges?[:\s]*\$?([\d,]+\.\d{2})")?;
+        extracted.total_charge = total_regex.captures(text)
+            .and_then(|c| c[1].replace(',', "").parse().ok());
 
-        for pattern in date_patterns {
-            let date_regex = Regex::new(pattern)?;
-            for mat in date_regex.find_iter(text) {
-                if let Ok(date) = DateTime::parse_from_rfc3339(&format!("{}T00:00:00Z", mat.as_str())) {
-                    extracted.dates.push(date.with_timezone(&Utc));
-                }
+        // Extract dates (various formats), each labelled by the text
+        // between it and the previous date on its line
+        let date_regex = Regex::new(r"\b(?:\d{1,2}/\d{1,2}/\d{4}|\d{4}-\d{2}-\d{2}|\d{1,2}-\d{1,2}-\d{4})\b")?;
+        let leading_code_regex = Regex::new(r"^\s+(?:\d{4}[0-9FTU]|[A-V]\d{4})\b")?;
+        for line in text.lines() {
+            let mut label_start = 0;
+            let mut previous = None;
+            for mat in date_regex.find_iter(line) {
+                let label = &line[label_start..mat.start()];
+                label_start = mat.end();
+                let Some(date) = parse_date(mat.as_str()) else { continue };
+
+                let role = if label.trim().is_empty() && previous.is_none() && leading_code_regex.is_match(&line[mat.end()..]) {
+                    DateRole::ServiceFrom
+                } else {
+                    date_role(label, previous)
+                };
+                previous = Some(role);
+                extracted.dates.push(ExtractedDate::new(role, date));
             }
         }
+        let first_with_role = |role| extracted.dates_with_role(role).next().map(|d| d.date_naive());
+        let (patient_dob, primary_eob_date) = (first_with_role(DateRole::Birth), first_with_role(DateRole::PrimaryEob));
+        extracted.patient_dob = patient_dob;
+        extracted.primary_eob_date = primary_eob_date;
 
-        // Extract diagnosis codes (ICD-10 format)
-        let icd_regex = Regex::new(r"\b[A-Z]\d{2,3}(?:\.\d+)?\b")?;
-        for mat in icd_regex.find_iter(text) {
-            extracted.diagnosis_codes.push(mat.as_str().to_string());
+        // Extract diagnosis codes (ICD-10-CM format). Dotted codes are taken
+        // anywhere; undotted ones only on diagnosis lines, since they look
+        // like HCPCS codes and plenty of other identifiers.
+        let dotted_icd_regex = Regex::new(r"\b[A-Z][0-9][0-9A-Z]\.[0-9A-Z]{1,4}\b")?;
+        let any_icd_regex = Regex::new(r"\b[A-Z][0-9][0-9A-Z](?:\.?[0-9A-Z]{1,4})?\b")?;
+        for line in text.lines() {
+            let icd_regex = if diagnosis_line_regex.is_match(line) { &any_icd_regex } else { &dotted_icd_regex };
+            for mat in icd_regex.find_iter(line) {
+                let code = icd10::display(mat.as_str());
+                if !extracted.diagnosis_codes.contains(&code) {
+                    extracted.diagnosis_codes.push(code);
+                }
+            }
         }
 
-        // Extract NPI (10-digit number)
-        let npi_regex = Regex::new(r"\b\d{10}\b")?;
-        for mat in npi_regex.find_iter(text) {
-            extracted.provider_npi = Some(mat.as_str().to_string());
-            break; // Take the first NPI found
-        }
+        self.extract_npis(text, &mut extracted)?;
 
         // Simple payer detection based on common keywords
         let payer_keywords = vec![
@@ -88,6 +109,9 @@ impl ClaimParser {
             }
         }
 
+        let plan_regex = Regex::new(r"(?i)\b(?:plan|product)(?: name| type)?:[ \t]*([A-Za-z0-9][A-Za-z0-9 &/-]*[A-Za-z0-9])")?;
+        extracted.plan = plan_regex.captures(text).map(|c| c[1].to_string());
+
         // Extract patient name (simple pattern - first name, last name)
         let name_regex = Regex::new(r"(?i)patient[:\s]+([A-Z][a-z]+)\s+([A-Z][a-z]+)")?;
         if let Some(mat) = name_regex.find(text) {
@@ -99,6 +123,16 @@ impl ClaimParser {
             }
         }
 
+        // Sex only counts when labelled
+        let sex_regex = Regex::new(r"(?i)\b(?:sex|gender)[:\s]+(male|female|m|f)\b")?;
+        extracted.patient_sex = sex_regex.captures(text).and_then(|c| Sex::parse(&c[1]));
+
+        // Resubmission code and original reference (CMS-1500 box 22)
+        let frequency_regex = Regex::new(r"(?i)\b(?:resubmission|frequency)(?: code)?[:#\s]+([1-9])\b")?;
+        extracted.frequency_code = frequency_regex.captures(text).map(|c| c[1].to_string());
+        let original_reference_regex = Regex::new(r"(?i)\boriginal ref(?:erence|\.)?\s*(?:no\.?|number)?[:#\s]+([A-Z0-9-]{4,})")?;
+        extracted.original_claim_reference = original_reference_regex.captures(text).map(|c| c[1].to_string());
+
         // Extract provider name (simple pattern)
         let provider_regex = Regex::new(r"(?i)provider[:\s]+([A-Z][a-z]+(?:\s+[A-Z][a-z]+)*)")?;
         if let Some(mat) = provider_regex.find(text) {
@@ -110,7 +144,214 @@ impl ClaimParser {
             }
         }
 
+        // Modifiers only count when attached to a service line; elsewhere
+        // two capitals are as likely to be a state or a name initial.
+        for line in &extracted.service_lines {
+            for modifier in &line.modifiers {
+                if !extracted.modifiers.contains(modifier) {
+                    extracted.modifiers.push(modifier.clone());
+                }
+            }
+        }
+
         Ok(extracted)
     }
+
+    /// Picks NPIs out of 10-digit numbers. A number labelled "NPI" is kept
+    /// even if its check digit fails, so the rules can flag it as invalid;
+    /// an unlabelled number (an account number) is only taken if it passes
+    /// the check digit, and one labelled as a phone or fax number never is,
+    /// since about one in ten of those passes too. Only a number labelled
+    /// as an NPI (or by its box, 33a or 24J) is assigned to the billing or
+    /// rendering provider.
+    fn extract_npis(&self, text: &str, extracted: &mut ExtractedData) -> Result<()> {
+        let npi_regex = Regex::new(r"\b\d{10}\b")?;
+        let phone_label_regex = Regex::new(r"\b(?:phone|tel|telephone|fax)\b")?;
+        let mut unlabelled = None;
+
+        for line in text.lines() {
+            let mut label_start = 0;
+            for mat in npi_regex.find_iter(line) {
+                let label = line[label_start..mat.start()].to_lowercase();
+                label_start = mat.end();
+
+                let number = mat.as_str().to_string();
+                if phone_label_regex.is_match(&label) || (!label.contains("npi") && !npi::is_valid(&number)) {
+                    continue;
+                }
+
+                let npi_label = ["npi", "33a", "24j"].iter().any(|word| label.contains(word));
+                if npi_label && (label.contains("rendering") || label.contains("24j")) {
+                    extracted.rendering_npi.get_or_insert(number);
+                } else if npi_label && (label.contains("billing") || label.contains("33a")) {
+                    extracted.billing_npi.get_or_insert(number);
+                } else {
+                    unlabelled.get_or_insert(number);
+                }
+            }
+        }
+
+        extracted.provider_npi = extracted.billing_npi.clone()
+            .or(unlabelled)
+            .or_else(|| extracted.rendering_npi.clone());
+
+        Ok(())
+    }
+
+    /// Treats each line of text that starts with a procedure code as a
+    /// service line, e.g. `03/15/2024 99213-25 1 $125.00 AB`, where the
+    /// trailing letters are the line's diagnosis pointers. A line also needs
+    /// a date, a line number or a charge, so an address line starting with a
+    /// ZIP code isn't taken for a procedure.
+    fn parse_service_lines(&self, text: &str) -> Result<Vec<ServiceLine>> {
+        let line_regex = Regex::new(
+            r"^(?:(?P<number>\d{1,2})[.)]?\s+)?(?:(?P<date>\d{1,2}/\d{1,2}/\d{4}|\d{4}-\d{2}-\d{2}|\d{1,2}-\d{1,2}-\d{4})\s+)?(?P<code>\d{4}[0-9FTU]|[A-V]\d{4})\b(?P<modifiers>(?:(?:-[A-Z0-9]{2}|\s+[A-Z][A-Z0-9]|\s+[0-9][A-Z])\b)*)(?:\s+(?P<units>\d{1,3}))?(?:\s+\$(?P<charge>[\d,]+(?:\.\d{2})?))?(?:\s+(?P<pointers>[A-L](?:,?[A-L]){0,3})\b)?"
+        )?;
+        let modifier_regex = Regex::new(r"[A-Z0-9]{2}")?;
+
+        let mut lines = Vec::new();
+        for raw_line in text.lines() {
+            let Some(captures) = line_regex.captures(raw_line.trim()) else {
+                continue;
+            };
+            if ["number", "date", "charge"].iter().all(|name| captures.name(name).is_none()) {
+                continue;
+            }
+
+            lines.push(ServiceLine {
+                procedure_code: captures["code"].to_string(),
+                modifiers: captures.name("modifiers")
+                    .map(|m| modifier_regex.find_iter(m.as_str()).map(|m| m.as_str().to_string()).collect())
+                    .unwrap_or_default(),
+                units: captures.name("units")
+                    .and_then(|u| u.as_str().parse().ok())
+                    .unwrap_or(1),
+                charge: captures.name("charge")
+                    .and_then(|c| c.as_str().replace(',', "").parse().ok()),
+                date_of_service: captures.name("date").and_then(|d| parse_date(d.as_str())),
+                diagnosis_pointers: captures.name("pointers")
+                    .map(|p| p.as_str().chars().filter(char::is_ascii_uppercase).collect())
+                    .unwrap_or_default(),
+            });
+        }
+
+        Ok(lines)
+    }
 }
 
+/// Decides a date's role from the label before it. A bare dash or "to"
+/// after a from date makes a to date.
+fn date_role(label: &str, previous: Option<DateRole>) -> DateRole {
+    let label = label.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| label.contains(word));
+
+    if has(&["birth", "dob", "d.o.b"]) {
+        DateRole::Birth
+    } else if has(&["onset", "illness", "injury", "accident", "lmp"]) {
+        DateRole::Onset
+    } else if has(&["admission", "admit", "hospitalization"]) {
+        DateRole::Admission
+    } else if has(&["signed", "signature"]) {
+        DateRole::Signature
+    } else if has(&["eob", "remittance", "primary paid"]) {
+        DateRole::PrimaryEob
+    } else if previous == Some(DateRole::ServiceFrom)
+        && matches!(label.split_whitespace().last(), Some("-" | "to" | "through" | "thru"))
+    {
+        DateRole::ServiceTo
+    } else if has(&["dos", "date of service", "dates of service", "service date", "from"]) {
+        DateRole::ServiceFrom
+    } else {
+        DateRole::Other
+    }
+}
+
+/// Parses MM/DD/YYYY, YYYY-MM-DD and MM-DD-YYYY dates as midnight UTC.
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    ["%m/%d/%Y", "%Y-%m-%d", "%m-%d-%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_labels_dates_by_role() {
+        let text = "Patient: Jane Doe DOB: 04/12/1958\n\
+                    Dates of service: 03/01/2024 to 03/05/2024\n\
+                    Date of current illness 02/20/2024\n\
+                    03/01/2024 99213 1 $125.00\n\
+                    Signed 03/06/2024\n";
+        let extracted = ClaimParser::new().parse_text(text).await.unwrap();
+
+        let roles: Vec<DateRole> = extracted.dates.iter().map(|d| d.role).collect();
+        assert_eq!(
+            roles,
+            [DateRole::Birth, DateRole::ServiceFrom, DateRole::ServiceTo, DateRole::Onset, DateRole::ServiceFrom, DateRole::Signature],
+        );
+        assert_eq!(extracted.patient_dob, NaiveDate::from_ymd_opt(1958, 4, 12));
+        assert_eq!(extracted.date_of_service().map(|d| d.date_naive()), NaiveDate::from_ymd_opt(2024, 3, 1));
+    }
+
+    #[test]
+    fn test_service_lines_need_a_date_number_or_charge() {
+        let text = "Billing Provider: Lakeside Clinic\n\
+                    62704 Springfield Avenue\n\
+                    90210 12\n\
+                    03/15/2024 99213-25 1 $125.00 AB\n\
+                    2. 20610 RT 1\n\
+                    J1030 2 $48.00\n";
+        let lines = ClaimParser::new().parse_service_lines(text).unwrap();
+
+        let codes: Vec<&str> = lines.iter().map(|line| line.procedure_code.as_str()).collect();
+        assert_eq!(codes, ["99213", "20610", "J1030"]);
+        assert_eq!(lines[0].modifiers, ["25"]);
+        assert_eq!(lines[1].modifiers, ["RT"]);
+        assert_eq!(lines[2].units, 2);
+    }
+
+    #[tokio::test]
+    async fn test_procedure_codes_come_from_service_lines() {
+        let text = "Lakeside Clinic, 12 Main St, Springfield, IL 62704\n\
+                    1. 03/15/2024 99213 1 $125.00 A\n";
+        let extracted = ClaimParser::new().parse_text(text).await.unwrap();
+        assert_eq!(extracted.cpt_codes, ["99213"]);
+
+        // Without service lines any code-shaped token is still taken.
+        let extracted = ClaimParser::new().parse_text("Procedures: 99213, 20610\n").await.unwrap();
+        assert_eq!(extracted.cpt_codes, ["99213", "20610"]);
+    }
+
+    #[test]
+    fn test_npis_skip_phone_numbers_and_follow_labels() {
+        let text = "33 Billing Provider Phone 5551234567\n\
+                    24J Rendering NPI 1245319599 33a Billing NPI 1234567893\n";
+        let mut extracted = ExtractedData::default();
+        ClaimParser::new().extract_npis(text, &mut extracted).unwrap();
+
+        assert_eq!(extracted.billing_npi.as_deref(), Some("1234567893"));
+        assert_eq!(extracted.rendering_npi.as_deref(), Some("1245319599"));
+        assert_eq!(extracted.provider_npi.as_deref(), Some("1234567893"));
+    }
+
+    #[test]
+    fn test_npis_skip_phone_numbers_that_pass_the_check_digit() {
+        // 2125551236 passes the NPI check digit.
+        let text = "Billing Provider Phone: 2125551236\n\
+                    Rendering Provider Fax 2125551236\n\
+                    Billing Provider 1234567893\n\
+                    24J Rendering NPI 1245319599\n";
+        let mut extracted = ExtractedData::default();
+        ClaimParser::new().extract_npis(text, &mut extracted).unwrap();
+
+        assert!(extracted.billing_npi.is_none());
+        assert_eq!(extracted.rendering_npi.as_deref(), Some("1245319599"));
+        // Without an NPI label the number isn't known to be the billing one.
+        assert_eq!(extracted.provider_npi.as_deref(), Some("1234567893"));
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{bail, Result};
+use chrono::{Duration, NaiveDate};
+use crate::types::{ExtractedData, Payer};
+
+/// Payers recognised by the parser, seeded into the registry on first run
+/// as `(id, name, parser keyword, filing limit, secondary filing limit)`.
+/// Medicare's limit is statutory; the others are common
+/// participating-provider limits and should be adjusted to each contract.
+pub const DEFAULT_PAYERS: &[(&str, &str, &str, u32, Option<u32>)] = &[
+    ("medicare", "Medicare", "medicare", 365, None),
+    ("medicaid", "Medicaid", "medicaid", 365, Some(365)),
+    ("aetna", "Aetna", "aetna", 120, Some(180)),
+    ("blue_cross", "Blue Cross", "blue cross", 180, Some(180)),
+    ("cigna", "Cigna", "cigna", 90, Some(90)),
+    ("humana", "Humana", "humana", 180, Some(180)),
+    ("unitedhealthcare", "UnitedHealthcare", "unitedhealth", 90, Some(90)),
+];
+
+/// Days before the deadline at which a claim starts being flagged.
+pub const FILING_WARNING_DAYS: i64 = 30;
+
+/// When a claim must reach the payer, and what the limit counts from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilingDeadline {
+    pub deadline: NaiveDate,
+    pub limit_days: u32,
+    pub basis: &'static str,
+    pub basis_date: NaiveDate,
+}
+
+pub fn default_payers() -> Vec<Payer> {
+    DEFAULT_PAYERS
+        .iter()
+        .map(|(id, name, keyword, filing_limit_days, secondary_filing_limit_days)| Payer {
+            id: id.to_string(),
+            name: name.to_string(),
+            aliases: vec![keyword.to_string()],
+            filing_limit_days: Some(*filing_limit_days),
+            secondary_filing_limit_days: *secondary_filing_limit_days,
+        })
+        .collect()
+}
+
+pub fn validate(payer: &Payer) -> Result<()> {
+    if payer.id.is_empty() || !payer.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+        bail!("Payer id must be lowercase letters, digits and underscores");
+    }
+    if payer.name.trim().is_empty() {
+        bail!("Payer name is required");
+    }
+    if payer.filing_limit_days == Some(0) || payer.secondary_filing_limit_days == Some(0) {
+        bail!("Filing limits must be at least one day");
+    }
+    Ok(())
+}
+
+/// Finds the registry entry for a payer name as extracted from a claim,
+/// matching the name or an alias anywhere in it, ignoring case.
+pub fn find<'a>(payers: &'a [Payer], extracted_name: &str) -> Option<&'a Payer> {
+    let extracted_name = extracted_name.to_lowercase();
+    payers.iter().find(|payer| {
+        std::iter::once(&payer.name)
+            .chain(&payer.aliases)
+            .map(|name| name.trim().to_lowercase())
+            .any(|name| !name.is_empty() && extracted_name.contains(&name))
+    })
+}
+
+/// Secondary claims count from the primary payer's EOB when the payer has
+/// a secondary limit; everything else counts from the date of service.
+pub fn filing_deadline(payer: &Payer, data: &ExtractedData) -> Option<FilingDeadline> {
+    let secondary = data.primary_eob_date.zip(payer.secondary_filing_limit_days);
+    let (basis, basis_date, limit_days) = match secondary {
+        Some((eob_date, limit_days)) => ("primary EOB date", eob_date, limit_days),
+        None => ("date of service", data.date_of_service()?.date_naive(), payer.filing_limit_days?),
+    };
+
+    Some(FilingDeadline {
+        deadline: basis_date + Duration::days(limit_days as i64),
+        limit_days,
+        basis,
+        basis_date,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use crate::types::{DateRole, ExtractedDate};
+
+    #[test]
+    fn test_finds_payer_by_alias() {
+        let payers = default_payers();
+        assert_eq!(find(&payers, "blue cross").map(|p| p.id.as_str()), Some("blue_cross"));
+        assert_eq!(find(&payers, "Medicare Part B").map(|p| p.id.as_str()), Some("medicare"));
+        assert_eq!(find(&payers, "unitedhealth").map(|p| p.id.as_str()), Some("unitedhealthcare"));
+        assert!(find(&payers, "Acme Health").is_none());
+    }
+
+    #[test]
+    fn test_secondary_claims_count_from_primary_eob() {
+        let payers = default_payers();
+        let aetna = find(&payers, "aetna").unwrap();
+        let mut data = ExtractedData {
+            dates: vec![ExtractedDate::new(DateRole::ServiceFrom, Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap())],
+            ..Default::default()
+        };
+
+        let primary = filing_deadline(aetna, &data).unwrap();
+        assert_eq!(primary.deadline, NaiveDate::from_ymd_opt(2024, 5, 9).unwrap());
+
+        data.primary_eob_date = NaiveDate::from_ymd_opt(2024, 3, 1);
+        let secondary = filing_deadline(aetna, &data).unwrap();
+        assert_eq!(secondary.basis, "primary EOB date");
+        assert_eq!(secondary.deadline, NaiveDate::from_ymd_opt(2024, 8, 28).unwrap());
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use crate::types::ProcedureCode;
+
+/// Where a procedure code stands on the date of service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcedureStatus {
+    Active,
+    Deleted { termination_date: NaiveDate },
+    NotYetEffective { effective_date: NaiveDate },
+    Unknown,
+}
+
+/// CPT Category I (five digits), Category II (`F`), Category III (`T`) and
+/// PLA (`U`) codes, or HCPCS Level II codes (a letter and four digits).
+pub fn is_valid_format(code: &str) -> bool {
+    let bytes = code.as_bytes();
+    if bytes.len() != 5 {
+        return false;
+    }
+
+    let cpt = bytes[..4].iter().all(u8::is_ascii_digit)
+        && matches!(bytes[4], b'0'..=b'9' | b'F' | b'T' | b'U');
+    let hcpcs = bytes[0].is_ascii_uppercase() && bytes[1..].iter().all(u8::is_ascii_digit);
+
+    cpt || hcpcs
+}
+
+pub fn code_type(code: &str) -> &'static str {
+    if code.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        "HCPCS"
+    } else {
+        "CPT"
+    }
+}
+
+/// Parses a CSV code list. Column names are matched loosely so both a plain
+/// `code,description,effective_date,termination_date` file and the CMS
+/// HCPCS release (`HCPC`, `LONG DESCRIPTION`, `ADD DT`, `TERM DT`) load.
+pub fn parse_code_file(contents: &str) -> Result<Vec<ProcedureCode>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+
+    let headers: Vec<String> = reader.headers()?
+        .iter()
+        .map(|h| h.to_ascii_lowercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_"))
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+
+    let code_column = column(&["code", "hcpc", "hcpcs", "hcpcs_code", "cpt_code", "cpt"])
+        .ok_or_else(|| anyhow!("No code column found"))?;
+    let description_column = column(&["description", "long_description", "short_description"]);
+    let effective_column = column(&["effective_date", "add_dt", "add_date", "act_eff_dt"]);
+    let termination_column = column(&["termination_date", "term_dt", "term_date"]);
+
+    let mut codes = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = index + 2;
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or_default();
+
+        let code = field(Some(code_column)).to_ascii_uppercase();
+        if code.is_empty() {
+            continue;
+        }
+        if !is_valid_format(&code) {
+            bail!("Line {}: invalid procedure code {}", line, code);
+        }
+
+        let effective_date = match field(effective_column) {
+            "" => None,
+            value => Some(parse_date(value).ok_or_else(|| anyhow!("Line {}: invalid effective date {}", line, value))?),
+        };
+        let termination_date = match field(termination_column) {
+            "" => None,
+            value => Some(parse_date(value).ok_or_else(|| anyhow!("Line {}: invalid termination date {}", line, value))?),
+        };
+
+        codes.push(ProcedureCode {
+            code_type: code_type(&code).to_string(),
+            code,
+            description: field(description_column).to_string(),
+            effective_date,
+            termination_date,
+        });
+    }
+
+    Ok(codes)
+}
+
+/// CMS files use `YYYYMMDD`; hand-made lists tend to use ISO or US dates.
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    ["%Y%m%d", "%Y-%m-%d", "%m/%d/%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// `rows` holds every imported period for the code. Without a date of
+/// service any imported period counts as active.
+pub fn status(rows: &[ProcedureCode], date_of_service: Option<NaiveDate>) -> ProcedureStatus {
+    if rows.is_empty() {
+        return ProcedureStatus::Unknown;
+    }
+    let Some(date) = date_of_service else {
+        return ProcedureStatus::Active;
+    };
+
+    let active = rows.iter().any(|row| {
+        row.effective_date.is_none_or(|start| start <= date) && row.termination_date.is_none_or(|end| date <= end)
+    });
+    if active {
+        return ProcedureStatus::Active;
+    }
+
+    match rows.iter().filter_map(|row| row.effective_date).filter(|&start| start > date).min() {
+        Some(effective_date) => ProcedureStatus::NotYetEffective { effective_date },
+        None => ProcedureStatus::Deleted {
+            termination_date: rows.iter().filter_map(|row| row.termination_date).max().unwrap_or(date),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_formats() {
+        for code in ["99213", "0001F", "0042T", "0001U", "J3301", "G0439"] {
+            assert!(is_valid_format(code), "{}", code);
+        }
+        for code in ["9921", "0001X", "J33O1", "g0439", "992130"] {
+            assert!(!is_valid_format(code), "{}", code);
+        }
+        assert_eq!(code_type("G0439"), "HCPCS");
+    }
+
+    #[test]
+    fn test_parses_cms_hcpcs_columns() {
+        let file = "HCPC,LONG DESCRIPTION,ADD DT,TERM DT\nG0439,Annual wellness visit,20110101,\nJ3301,Triamcinolone acetonide,20000101,\n";
+        let codes = parse_code_file(file).unwrap();
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[0].code_type, "HCPCS");
+        assert_eq!(codes[0].effective_date, NaiveDate::from_ymd_opt(2011, 1, 1));
+        assert!(codes[0].termination_date.is_none());
+    }
+
+    #[test]
+    fn test_status_on_date_of_service() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let rows = vec![ProcedureCode {
+            code: "G0000".to_string(),
+            code_type: "HCPCS".to_string(),
+            description: String::new(),
+            effective_date: Some(date(2020, 1, 1)),
+            termination_date: Some(date(2022, 12, 31)),
+        }];
+
+        assert_eq!(status(&rows, Some(date(2021, 6, 1))), ProcedureStatus::Active);
+        assert_eq!(status(&rows, Some(date(2023, 1, 1))), ProcedureStatus::Deleted { termination_date: date(2022, 12, 31) });
+        assert_eq!(status(&rows, Some(date(2019, 1, 1))), ProcedureStatus::NotYetEffective { effective_date: date(2020, 1, 1) });
+        assert_eq!(status(&[], None), ProcedureStatus::Unknown);
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::{HashMap, HashSet};
+use crate::types::{Claim, CodeAttribute, CoverageRule, FeeScheduleEntry, Icd10Code, MueLimit, NcciEdit, Payer, ProcedureCode};
+
+/// Rows from the imported code sets that one claim's rules need, loaded
+/// before the engine runs so the rules themselves stay synchronous.
+#[derive(Debug, Clone, Default)]
+pub struct ReferenceData {
+    /// Fiscal years with an imported ICD-10-CM table.
+    pub icd10_fiscal_years: Vec<i32>,
+    /// Rows for the claim's diagnosis codes across all fiscal years, keyed
+    /// by normalized code.
+    pub icd10_codes: HashMap<String, Vec<Icd10Code>>,
+    /// Whether any CPT/HCPCS codes have been imported.
+    pub procedure_codes_loaded: bool,
+    /// Imported periods for the claim's procedure codes, keyed by code.
+    pub procedure_codes: HashMap<String, Vec<ProcedureCode>>,
+    /// PTP edits, for the configured setting, between the claim's codes.
+    pub ncci_edits: Vec<NcciEdit>,
+    /// MUE values for the claim's codes, for the configured setting.
+    pub mue_limits: HashMap<String, MueLimit>,
+    /// Every modifier in the reference table.
+    pub modifier_codes: HashSet<String>,
+    /// National and configured-jurisdiction coverage ranges for the claim's
+    /// procedure codes.
+    pub coverage_rules: Vec<CoverageRule>,
+    /// Age and sex restrictions on the claim's diagnosis and procedure
+    /// codes, including category-level ICD-10 rows.
+    pub code_attributes: Vec<CodeAttribute>,
+    /// Other claims for the same patient, checked for duplicates.
+    pub related_claims: Vec<Claim>,
+    /// Registry entry for the claim's payer.
+    pub payer: Option<Payer>,
+    /// Medicare amounts for the configured locality and chargemaster prices
+    /// for the claim's procedure codes.
+    pub fee_schedule: Vec<FeeScheduleEntry>,
+}
+
+/// The part of the reference data that is the same for every claim, so a
+/// batch of claims loads it once.
+#[derive(Debug, Clone, Default)]
+pub struct SharedReferenceData {
+    pub icd10_fiscal_years: Vec<i32>,
+    pub procedure_codes_loaded: bool,
+    pub modifier_codes: HashSet<String>,
+    /// The whole payer registry, matched against each claim's payer name.
+    pub payers: Vec<Payer>,
+}
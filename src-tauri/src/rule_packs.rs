@@ -0,0 +1,244 @@
+use std::collections::HashSet;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::declarative_rules;
+use crate::rules;
+use crate::types::{Payer, RuleOverride, RulePack};
+
+/// Marks a file written by `export`.
+pub const FILE_FORMAT: &str = "claimsense-rule-packs";
+pub const FILE_VERSION: u32 = 1;
+
+/// Rule packs as shared between workspaces.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulePackFile {
+    pub format: String,
+    pub version: u32,
+    pub packs: Vec<RulePack>,
+}
+
+/// A pack with everything it inherits folded in.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedPack {
+    /// Pack ids from the base pack down to the selected one.
+    pub chain: Vec<String>,
+    pub rules: Vec<Value>,
+    pub overrides: Vec<RuleOverride>,
+}
+
+/// Picks the active pack for a claim: one for the payer whose plan appears
+/// in the claim's plan name, else the payer's pack without a plan. Claims
+/// from payers without an active pack get none.
+pub fn select<'a>(packs: &'a [RulePack], payer: Option<&Payer>, plan: Option<&str>) -> Option<&'a RulePack> {
+    let payer = payer?;
+    let for_payer = || packs.iter().filter(|pack| pack.active && pack.payer_id.as_deref() == Some(payer.id.as_str()));
+    let words = |name: &str| format!(" {} ", name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase());
+
+    plan.and_then(|plan| {
+        for_payer().find(|pack| pack.plan.as_deref().is_some_and(|p| words(plan).contains(&words(p))))
+    })
+    .or_else(|| for_payer().find(|pack| pack.plan.is_none()))
+}
+
+/// Follows `extends` to the base pack. Rules and overrides in a pack
+/// replace inherited ones with the same id.
+pub fn resolve(packs: &[RulePack], id: &str) -> Result<ResolvedPack> {
+    let mut chain = Vec::new();
+    let mut next = Some(id);
+    while let Some(id) = next {
+        if chain.iter().any(|pack: &&RulePack| pack.id == id) {
+            bail!("Rule pack {} inherits from itself", id);
+        }
+        let pack = packs.iter()
+            .find(|pack| pack.id == id)
+            .ok_or_else(|| anyhow!("Unknown rule pack {}", id))?;
+        chain.push(pack);
+        next = pack.extends.as_deref();
+    }
+    chain.reverse();
+
+    let mut resolved = ResolvedPack::default();
+    for pack in chain {
+        resolved.chain.push(pack.id.clone());
+        resolved.rules = merge_rules(&resolved.rules, &pack.rules);
+        resolved.overrides = merge_overrides(&resolved.overrides, &pack.overrides);
+    }
+    Ok(resolved)
+}
+
+fn rule_id(rule: &Value) -> Option<&str> {
+    rule.get("id").and_then(Value::as_str)
+}
+
+/// Rules from `layer` replace base rules with the same id in place; new
+/// ones are appended.
+pub fn merge_rules(base: &[Value], layer: &[Value]) -> Vec<Value> {
+    let mut merged = base.to_vec();
+    for rule in layer {
+        match merged.iter_mut().find(|existing| rule_id(existing).is_some() && rule_id(existing) == rule_id(rule)) {
+            Some(existing) => *existing = rule.clone(),
+            None => merged.push(rule.clone()),
+        }
+    }
+    merged
+}
+
+pub fn merge_overrides(base: &[RuleOverride], layer: &[RuleOverride]) -> Vec<RuleOverride> {
+    let mut merged = base.to_vec();
+    for rule_override in layer {
+        match merged.iter_mut().find(|existing| existing.rule_id == rule_override.rule_id) {
+            Some(existing) => *existing = rule_override.clone(),
+            None => merged.push(rule_override.clone()),
+        }
+    }
+    merged
+}
+
+/// The workspace rules config with a pack's rules layered on top.
+pub fn rules_config(workspace: &Value, resolved: &ResolvedPack) -> Value {
+    let workspace_rules = workspace.get("rules").and_then(Value::as_array).cloned().unwrap_or_default();
+    serde_json::json!({ "rules": merge_rules(&workspace_rules, &resolved.rules) })
+}
+
+/// Active packs whose rules change when `id` changes: the pack itself and
+/// every active pack inheriting from it.
+pub fn affected_active_packs<'a>(packs: &'a [RulePack], id: &str) -> Vec<&'a RulePack> {
+    packs.iter()
+        .filter(|pack| pack.active)
+        .filter(|pack| resolve(packs, &pack.id).is_ok_and(|resolved| resolved.chain.iter().any(|link| link == id)))
+        .collect()
+}
+
+/// Checks a pack as it would be saved alongside `others`: a well-formed
+/// id, an existing parent without cycles, one pack per payer and plan,
+/// rules that compile and overrides that name known rules.
+pub fn validate(pack: &RulePack, others: &[RulePack], workspace_rules: &Value) -> Result<()> {
+    if pack.id.is_empty() || !pack.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+        bail!("Rule pack id must be lowercase letters, digits and underscores");
+    }
+    if pack.name.trim().is_empty() {
+        bail!("Rule pack name is required");
+    }
+    if pack.plan.is_some() && pack.payer_id.is_none() {
+        bail!("A plan-specific rule pack needs a payer");
+    }
+
+    let same_scope = others.iter().find(|other| {
+        other.id != pack.id
+            && other.payer_id.is_some()
+            && other.payer_id == pack.payer_id
+            && other.plan.as_deref().map(str::to_lowercase) == pack.plan.as_deref().map(str::to_lowercase)
+    });
+    if let Some(other) = same_scope {
+        bail!("Rule pack {} already covers this payer and plan", other.id);
+    }
+
+    let mut packs: Vec<RulePack> = others.iter().filter(|other| other.id != pack.id).cloned().collect();
+    packs.push(pack.clone());
+    let resolved = resolve(&packs, &pack.id)?;
+
+    let custom_rules = declarative_rules::compile_rules_config(&rules_config(workspace_rules, &resolved))
+        .map_err(|errors| anyhow!("Invalid rules in pack {}:\n{}", pack.id, declarative_rules::format_errors(&errors)))?;
+    rules::validate_overrides(&pack.overrides, &custom_rules)
+}
+
+/// Writes the chosen packs and every pack they inherit from, so the file
+/// can be imported on its own.
+pub fn export(packs: &[RulePack], ids: &[String]) -> Result<RulePackFile> {
+    let mut included: HashSet<String> = HashSet::new();
+    for id in ids {
+        included.extend(resolve(packs, id)?.chain);
+    }
+
+    Ok(RulePackFile {
+        format: FILE_FORMAT.to_string(),
+        version: FILE_VERSION,
+        packs: packs.iter().filter(|pack| included.contains(&pack.id)).cloned().collect(),
+    })
+}
+
+pub fn parse_file(contents: &str) -> Result<Vec<RulePack>> {
+    let file: RulePackFile = serde_json::from_str(contents)?;
+    if file.format != FILE_FORMAT {
+        bail!("Not a rule pack file");
+    }
+    if file.version > FILE_VERSION {
+        bail!("Rule pack file version {} is newer than this version supports", file.version);
+    }
+    Ok(file.packs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pack(id: &str, payer_id: Option<&str>, plan: Option<&str>, extends: Option<&str>, rules: Vec<Value>) -> RulePack {
+        RulePack {
+            id: id.to_string(),
+            name: id.to_string(),
+            payer_id: payer_id.map(String::from),
+            plan: plan.map(String::from),
+            extends: extends.map(String::from),
+            rules,
+            overrides: vec![],
+            updated_at: None,
+            active: true,
+        }
+    }
+
+    fn rule(id: &str, message: &str) -> Value {
+        json!({
+            "id": id,
+            "name": id,
+            "severity": "warning",
+            "condition": { "field": "payer", "op": "exists" },
+            "message": message,
+        })
+    }
+
+    #[test]
+    fn test_selects_plan_pack_then_payer_pack() {
+        let packs = vec![
+            pack("base", None, None, None, vec![]),
+            pack("bcbs", Some("blue_cross"), None, Some("base"), vec![]),
+            pack("bcbs_hmo", Some("blue_cross"), Some("HMO"), Some("bcbs"), vec![]),
+        ];
+        let payer = crate::payers::default_payers().into_iter().find(|p| p.id == "blue_cross").unwrap();
+
+        assert_eq!(select(&packs, Some(&payer), Some("Blue Cross hmo")).map(|p| p.id.as_str()), Some("bcbs_hmo"));
+        assert_eq!(select(&packs, Some(&payer), Some("PPO")).map(|p| p.id.as_str()), Some("bcbs"));
+        assert!(select(&packs, None, None).is_none());
+    }
+
+    #[test]
+    fn test_resolve_layers_rules_and_detects_cycles() {
+        let packs = vec![
+            pack("base", None, None, None, vec![rule("prior_auth", "base"), rule("referral", "base")]),
+            pack("bcbs", Some("blue_cross"), None, Some("base"), vec![rule("prior_auth", "bcbs")]),
+        ];
+        let resolved = resolve(&packs, "bcbs").unwrap();
+        assert_eq!(resolved.chain, ["base", "bcbs"]);
+        assert_eq!(resolved.rules.len(), 2);
+        assert_eq!(resolved.rules[0]["message"], "bcbs");
+
+        let cyclic = vec![pack("a", None, None, Some("b"), vec![]), pack("b", None, None, Some("a"), vec![])];
+        assert!(resolve(&cyclic, "a").is_err());
+    }
+
+    #[test]
+    fn test_export_includes_parents_and_round_trips() {
+        let packs = vec![
+            pack("base", None, None, None, vec![rule("prior_auth", "base")]),
+            pack("bcbs", Some("blue_cross"), None, Some("base"), vec![]),
+            pack("medicare", Some("medicare"), None, None, vec![]),
+        ];
+        let file = export(&packs, &["bcbs".to_string()]).unwrap();
+        let imported = parse_file(&serde_json::to_string(&file).unwrap()).unwrap();
+        let ids: Vec<&str> = imported.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["base", "bcbs"]);
+        assert!(validate(&imported[1], &imported, &json!({ "rules": [] })).is_ok());
+        assert!(parse_file(r#"{"format": "other", "version": 1, "packs": []}"#).is_err());
+    }
+}
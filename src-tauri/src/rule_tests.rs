@@ -0,0 +1,124 @@
+use std::collections::BTreeSet;
+use chrono::{NaiveDate, Utc};
+use uuid::Uuid;
+use crate::declarative_rules::CompiledRule;
+use crate::types::{Claim, ClaimStatus, ExtractedData, QueueType, RuleTest, RuleTestResult, ValidationResult};
+
+/// Every test case in a rule set, with the id of the rule it belongs to.
+pub fn cases(rules: &[CompiledRule]) -> Vec<(String, RuleTest)> {
+    rules.iter()
+        .flat_map(|rule| rule.tests.iter().map(|test| (rule.id.clone(), test.clone())))
+        .collect()
+}
+
+/// Wraps a fixture in an unsaved claim so the engine can run on it.
+pub fn fixture_claim(data: &ExtractedData) -> Claim {
+    Claim {
+        id: Uuid::new_v4(),
+        filename: String::new(),
+        file_path: String::new(),
+        status: ClaimStatus::Processed,
+        extracted_data: data.clone(),
+        validation_results: vec![],
+        queue: QueueType::ApprovedClaims,
+        assigned_to: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        comments: vec![],
+    }
+}
+
+/// The day a fixture is checked as of. Only rules measured against a
+/// date of service use it, so a fixture without one needs none.
+pub fn as_of(test: &RuleTest) -> Option<NaiveDate> {
+    test.as_of.or_else(|| test.claim.date_of_service().map(|date| date.date_naive()))
+}
+
+/// Compares the findings on a fixture with the test's expectations. Only the
+/// rule under test and the expected rules count, so a fixture doesn't have
+/// to be free of unrelated problems.
+pub fn compare(rule_id: &str, test: &RuleTest, findings: &[ValidationResult]) -> RuleTestResult {
+    let expected: BTreeSet<&str> = test.expect.iter().map(String::as_str).collect();
+    let fired: BTreeSet<&str> = findings.iter()
+        .map(|finding| finding.rule_id.as_str())
+        .filter(|id| *id == rule_id || expected.contains(id))
+        .collect();
+
+    let missing: Vec<String> = expected.difference(&fired).map(|id| id.to_string()).collect();
+    let unexpected: Vec<String> = fired.difference(&expected).map(|id| id.to_string()).collect();
+
+    RuleTestResult {
+        rule_id: rule_id.to_string(),
+        test_name: test.name.clone(),
+        passed: missing.is_empty() && unexpected.is_empty(),
+        missing,
+        unexpected,
+    }
+}
+
+/// One line per failing test, or `None` when all passed.
+pub fn describe_failures(results: &[RuleTestResult]) -> Option<String> {
+    let lines: Vec<String> = results.iter()
+        .filter(|result| !result.passed)
+        .map(|result| {
+            let mut problems = Vec::new();
+            if !result.missing.is_empty() {
+                problems.push(format!("expected {}", result.missing.join(", ")));
+            }
+            if !result.unexpected.is_empty() {
+                problems.push(format!("unexpected {}", result.unexpected.join(", ")));
+            }
+            format!("{} / {}: {}", result.rule_id, result.test_name, problems.join("; "))
+        })
+        .collect();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DateRole, ExtractedDate, Severity};
+
+    fn finding(rule_id: &str) -> ValidationResult {
+        ValidationResult::new(rule_id, rule_id, Severity::Warning, String::new())
+    }
+
+    #[test]
+    fn test_compare_ignores_unrelated_findings() {
+        let test = RuleTest {
+            name: "fires with prior auth".to_string(),
+            claim: ExtractedData::default(),
+            expect: vec!["prior_auth".to_string(), "missing_npi".to_string()],
+            as_of: None,
+        };
+
+        let passed = compare("prior_auth", &test, &[finding("prior_auth"), finding("missing_npi"), finding("missing_cpt_codes")]);
+        assert!(passed.passed);
+
+        let failed = compare("prior_auth", &test, &[finding("missing_npi")]);
+        assert_eq!(failed.missing, ["prior_auth"]);
+        assert_eq!(describe_failures(&[failed]).unwrap(), "prior_auth / fires with prior auth: expected prior_auth");
+
+        let quiet = RuleTest { expect: vec![], ..test };
+        assert_eq!(compare("prior_auth", &quiet, &[finding("prior_auth")]).unexpected, ["prior_auth"]);
+    }
+
+    #[test]
+    fn test_fixtures_run_as_of_their_date_of_service() {
+        let date = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 1, 10, 0, 0, 0).unwrap();
+        let test = RuleTest {
+            name: "dated".to_string(),
+            claim: ExtractedData {
+                dates: vec![ExtractedDate::new(DateRole::ServiceFrom, date)],
+                ..Default::default()
+            },
+            expect: vec![],
+            as_of: None,
+        };
+        assert_eq!(as_of(&test), NaiveDate::from_ymd_opt(2024, 1, 10));
+
+        let pinned = RuleTest { as_of: NaiveDate::from_ymd_opt(2024, 3, 20), ..test };
+        assert_eq!(as_of(&pinned), NaiveDate::from_ymd_opt(2024, 3, 20));
+    }
+}
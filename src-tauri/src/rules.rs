@@ -1,143 +1,310 @@
-use anyhow::Result;
-use uuid::Uuid;
-use crate::types::{Claim, ValidationResult, Severity};
+use std::collections::{HashMap, HashSet};
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::coverage;
+use crate::declarative_rules::CompiledRule;
+use crate::demographics;
+use crate::duplicates::{self, MatchKind};
+use crate::evidence;
+use crate::fee_schedule;
+use crate::icd10::{self, CodeStatus};
+use crate::modifiers;
+use crate::mue;
+use crate::ncci;
+use crate::npi;
+use crate::payers;
+use crate::procedure_codes::{self, ProcedureStatus};
+use crate::reference_data::ReferenceData;
+use crate::types::{BilledService, Claim, CodeSystem, DateRole, FeeSource, FixPatch, QueueType, RuleConfiguration, RuleOverride, ServiceLine, ValidationResult, Severity};
 
-pub struct RulesEngine;
+/// Ids of the rules below; custom rules may not reuse them.
+pub const BUILTIN_RULE_IDS: &[&str] = &[
+    "missing_cpt_codes",
+    "invalid_cpt_code",
+    "missing_patient_name",
+    "missing_provider_name",
+    "missing_npi",
+    "invalid_npi",
+    "invalid_diagnosis_code",
+    "truncated_diagnosis_code",
+    "expired_diagnosis_code",
+    "unknown_procedure_code",
+    "deleted_procedure_code",
+    "procedure_code_not_effective",
+    "ncci_ptp_conflict",
+    "mue_exceeded",
+    "invalid_modifier",
+    "conflicting_modifiers",
+    "missing_required_modifier",
+    "modifier_59_overuse",
+    "diagnosis_not_covered",
+    "age_code_conflict",
+    "sex_code_conflict",
+    "duplicate_claim",
+    "timely_filing",
+    "future_date_of_service",
+    "service_before_birth",
+    "service_dates_reversed",
+    "service_span_too_long",
+    "onset_after_service",
+    "service_before_admission",
+    "charge_below_medicare",
+    "charge_above_chargemaster",
+    "total_charge_mismatch",
+    "missing_charges",
+    "missing_dates",
+    "missing_payer",
+    "missing_diagnosis_codes",
+    "duplicate_cpt_code",
+];
+
+/// Longest plausible span between a claim's first and last service dates.
+const MAX_SERVICE_SPAN_DAYS: i64 = 90;
+
+pub struct RulesEngine {
+    custom_rules: Vec<CompiledRule>,
+    configuration: RuleConfiguration,
+    reference: ReferenceData,
+    /// The date deadlines are measured against.
+    as_of: NaiveDate,
+}
 
 impl RulesEngine {
     pub fn new() -> Self {
-        Self
+        Self {
+            custom_rules: Vec::new(),
+            configuration: RuleConfiguration::default(),
+            reference: ReferenceData::default(),
+            as_of: Utc::now().date_naive(),
+        }
+    }
+
+    /// Adds rules defined in `Settings.rules_config`, evaluated after the
+    /// built-in rules.
+    pub fn with_custom_rules(mut self, rules: Vec<CompiledRule>) -> Self {
+        self.custom_rules = rules;
+        self
+    }
+
+    pub fn with_configuration(mut self, configuration: RuleConfiguration) -> Self {
+        self.configuration = configuration;
+        self
+    }
+
+    pub fn with_reference_data(mut self, reference: ReferenceData) -> Self {
+        self.reference = reference;
+        self
+    }
+
+    /// Measures deadlines from `as_of` instead of today, so a stored claim
+    /// can be re-run as it stood when it was received.
+    pub fn with_as_of(mut self, as_of: NaiveDate) -> Self {
+        self.as_of = as_of;
+        self
     }
 
     pub async fn validate_claim(&self, claim: &Claim) -> Result<Vec<ValidationResult>> {
+        let mut results = self.builtin_rules(claim);
+        results.extend(self.diagnosis_code_rules(claim));
+        results.extend(self.procedure_code_rules(claim));
+        results.extend(self.ncci_rules(claim));
+        results.extend(self.mue_rules(claim));
+        results.extend(self.modifier_rules(claim));
+        results.extend(self.coverage_rules(claim));
+        results.extend(self.demographic_rules(claim));
+        results.extend(self.duplicate_claim_rules(claim));
+        results.extend(self.timely_filing_rules(claim));
+        results.extend(self.date_rules(claim));
+        results.extend(self.charge_rules(claim));
+
+        for rule in &self.custom_rules {
+            results.extend(rule.evaluate(&claim.extracted_data));
+        }
+
+        self.attach_evidence(claim, &mut results);
+        Ok(self.apply_configuration(results))
+    }
+
+    /// Completes each finding's evidence: the rule version, the claimed
+    /// value of the finding's field when the rule recorded none, and the
+    /// source lines the values were read from.
+    fn attach_evidence(&self, claim: &Claim, results: &mut [ValidationResult]) {
+        let data = &claim.extracted_data;
+        for result in results {
+            let evidence = &mut result.evidence;
+            evidence.rule_version.get_or_insert_with(|| evidence::BUILTIN_RULES_VERSION.to_string());
+            if evidence.values.is_empty() {
+                if let Some((field, value)) = result.field.as_ref().and_then(|field| Some((field, evidence::field_value(data, field)?))) {
+                    evidence.values.insert(field.clone(), value);
+                }
+            }
+            evidence.source = evidence::source_snippets(&data.raw_text, &evidence.values);
+        }
+    }
+
+    /// Drops findings from disabled rules or below the rule's confidence
+    /// threshold, applies severity overrides, and stamps the version.
+    fn apply_configuration(&self, results: Vec<ValidationResult>) -> Vec<ValidationResult> {
+        let overrides: HashMap<&str, &RuleOverride> = self.configuration.overrides
+            .iter()
+            .map(|o| (o.rule_id.as_str(), o))
+            .collect();
+
+        results
+            .into_iter()
+            .filter_map(|mut result| {
+                if let Some(rule_override) = overrides.get(result.rule_id.as_str()) {
+                    if !rule_override.enabled {
+                        return None;
+                    }
+                    if rule_override.min_confidence.is_some_and(|min| result.confidence < min) {
+                        return None;
+                    }
+                    if let Some(severity) = rule_override.severity {
+                        result.severity = severity;
+                    }
+                }
+                result.config_version = Some(self.configuration.version);
+                Some(result)
+            })
+            .collect()
+    }
+
+    fn builtin_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
         let mut results = Vec::new();
 
         // Rule 1: Check for missing CPT codes
         if claim.extracted_data.cpt_codes.is_empty() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_cpt_codes".to_string(),
-                rule_name: "Missing CPT Codes".to_string(),
-                severity: Severity::Critical,
-                message: "No CPT codes found in the claim".to_string(),
-                field: Some("cpt_codes".to_string()),
-                suggested_fix: Some("Add appropriate CPT codes for the services provided".to_string()),
-                confidence: 1.0,
-            });
-        }
-
-        // Rule 2: Check for invalid CPT codes (basic validation)
+            results.push(
+                ValidationResult::new(
+                    "missing_cpt_codes",
+                    "Missing CPT Codes",
+                    Severity::Critical,
+                    "No CPT codes found in the claim".to_string(),
+                )
+                .with_field("cpt_codes")
+                .with_suggested_fix("Add appropriate CPT codes for the services provided"),
+            );
+        }
+
+        // Rule 2: Check for invalid CPT/HCPCS code formats
         for cpt_code in &claim.extracted_data.cpt_codes {
-            if cpt_code.len() != 5 || !cpt_code.chars().all(|c| c.is_ascii_digit()) {
-                results.push(ValidationResult {
-                    id: Uuid::new_v4(),
-                    rule_id: "invalid_cpt_code".to_string(),
-                    rule_name: "Invalid CPT Code Format".to_string(),
-                    severity: Severity::Critical,
-                    message: format!("Invalid CPT code format: {}", cpt_code),
-                    field: Some("cpt_codes".to_string()),
-                    suggested_fix: Some("Ensure CPT codes are 5-digit numbers".to_string()),
-                    confidence: 1.0,
-                });
+            if !procedure_codes::is_valid_format(cpt_code) {
+                results.push(
+                    ValidationResult::new(
+                        "invalid_cpt_code",
+                        "Invalid CPT Code Format",
+                        Severity::Critical,
+                        format!("Invalid CPT code format: {}", cpt_code),
+                    )
+                    .with_field("cpt_codes")
+                    .with_suggested_fix("Ensure codes are 5-digit CPT codes or HCPCS Level II codes (a letter and four digits)"),
+                );
             }
         }
 
         // Rule 3: Check for missing patient information
         if claim.extracted_data.patient_name.is_none() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_patient_name".to_string(),
-                rule_name: "Missing Patient Name".to_string(),
-                severity: Severity::Critical,
-                message: "Patient name not found in the claim".to_string(),
-                field: Some("patient_name".to_string()),
-                suggested_fix: Some("Add patient name to the claim".to_string()),
-                confidence: 0.9,
-            });
+            results.push(
+                ValidationResult::new(
+                    "missing_patient_name",
+                    "Missing Patient Name",
+                    Severity::Critical,
+                    "Patient name not found in the claim".to_string(),
+                )
+                .with_field("patient_name")
+                .with_suggested_fix("Add patient name to the claim")
+                .with_confidence(0.9),
+            );
         }
 
         // Rule 4: Check for missing provider information
         if claim.extracted_data.provider_name.is_none() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_provider_name".to_string(),
-                rule_name: "Missing Provider Name".to_string(),
-                severity: Severity::Warning,
-                message: "Provider name not found in the claim".to_string(),
-                field: Some("provider_name".to_string()),
-                suggested_fix: Some("Add provider name to the claim".to_string()),
-                confidence: 0.8,
-            });
+            results.push(
+                ValidationResult::new(
+                    "missing_provider_name",
+                    "Missing Provider Name",
+                    Severity::Warning,
+                    "Provider name not found in the claim".to_string(),
+                )
+                .with_field("provider_name")
+                .with_suggested_fix("Add provider name to the claim")
+                .with_confidence(0.8),
+            );
         }
 
         // Rule 5: Check for missing NPI
         if claim.extracted_data.provider_npi.is_none() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_npi".to_string(),
-                rule_name: "Missing Provider NPI".to_string(),
-                severity: Severity::Critical,
-                message: "Provider NPI not found in the claim".to_string(),
-                field: Some("provider_npi".to_string()),
-                suggested_fix: Some("Add valid 10-digit NPI to the claim".to_string()),
-                confidence: 0.9,
-            });
+            results.push(
+                ValidationResult::new(
+                    "missing_npi",
+                    "Missing Provider NPI",
+                    Severity::Critical,
+                    "Provider NPI not found in the claim".to_string(),
+                )
+                .with_field("provider_npi")
+                .with_suggested_fix("Add valid 10-digit NPI to the claim")
+                .with_confidence(0.9),
+            );
         }
 
         // Rule 6: Check for missing charges
         if claim.extracted_data.charges.is_empty() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_charges".to_string(),
-                rule_name: "Missing Charges".to_string(),
-                severity: Severity::Critical,
-                message: "No charges found in the claim".to_string(),
-                field: Some("charges".to_string()),
-                suggested_fix: Some("Add service charges to the claim".to_string()),
-                confidence: 1.0,
-            });
+            results.push(
+                ValidationResult::new(
+                    "missing_charges",
+                    "Missing Charges",
+                    Severity::Critical,
+                    "No charges found in the claim".to_string(),
+                )
+                .with_field("charges")
+                .with_suggested_fix("Add service charges to the claim"),
+            );
         }
 
         // Rule 7: Check for missing dates
-        if claim.extracted_data.dates.is_empty() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_dates".to_string(),
-                rule_name: "Missing Service Dates".to_string(),
-                severity: Severity::Warning,
-                message: "No service dates found in the claim".to_string(),
-                field: Some("dates".to_string()),
-                suggested_fix: Some("Add service dates to the claim".to_string()),
-                confidence: 0.8,
-            });
+        if claim.extracted_data.date_of_service().is_none() {
+            results.push(
+                ValidationResult::new(
+                    "missing_dates",
+                    "Missing Service Dates",
+                    Severity::Warning,
+                    "No service dates found in the claim".to_string(),
+                )
+                .with_field("dates")
+                .with_suggested_fix("Add service dates to the claim")
+                .with_confidence(0.8),
+            );
         }
 
         // Rule 8: Check for missing payer information
         if claim.extracted_data.payer.is_none() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_payer".to_string(),
-                rule_name: "Missing Payer Information".to_string(),
-                severity: Severity::Warning,
-                message: "Payer information not found in the claim".to_string(),
-                field: Some("payer".to_string()),
-                suggested_fix: Some("Add payer information to the claim".to_string()),
-                confidence: 0.7,
-            });
+            results.push(
+                ValidationResult::new(
+                    "missing_payer",
+                    "Missing Payer Information",
+                    Severity::Warning,
+                    "Payer information not found in the claim".to_string(),
+                )
+                .with_field("payer")
+                .with_suggested_fix("Add payer information to the claim")
+                .with_confidence(0.7),
+            );
         }
 
         // Rule 9: Check for missing diagnosis codes
         if claim.extracted_data.diagnosis_codes.is_empty() {
-            results.push(ValidationResult {
-                id: Uuid::new_v4(),
-                rule_id: "missing_diagnosis_codes".to_string(),
-                rule_name: "Missing Diagnosis Codes".to_string(),
-                severity: Severity::Warning,
-                message: "No diagnosis codes found in the claim".to_string(),
-                field: Some("diagnosis_codes".to_string()),
-                suggested_fix: Some("Add appropriate ICD-10 diagnosis codes".to_string()),
-                confidence: 0.8,
-            });
+            results.push(
+                ValidationResult::new(
+                    "missing_diagnosis_codes",
+                    "Missing Diagnosis Codes",
+                    Severity::Warning,
+                    "No diagnosis codes found in the claim".to_string(),
+                )
+                .with_field("diagnosis_codes")
+                .with_suggested_fix("Add appropriate ICD-10 diagnosis codes")
+                .with_confidence(0.8),
+            );
         }
 
         // Rule 10: Check for duplicate CPT codes
@@ -148,20 +315,1299 @@ impl RulesEngine {
 
         for (cpt_code, count) in cpt_counts {
             if count > 1 {
-                results.push(ValidationResult {
-                    id: Uuid::new_v4(),
-                    rule_id: "duplicate_cpt_code".to_string(),
-                    rule_name: "Duplicate CPT Code".to_string(),
-                    severity: Severity::Warning,
-                    message: format!("CPT code {} appears {} times", cpt_code, count),
-                    field: Some("cpt_codes".to_string()),
-                    suggested_fix: Some("Review if duplicate codes are intentional or remove duplicates".to_string()),
-                    confidence: 1.0,
+                let mut result = ValidationResult::new(
+                    "duplicate_cpt_code",
+                    "Duplicate CPT Code",
+                    Severity::Warning,
+                    format!("CPT code {} appears {} times", cpt_code, count),
+                )
+                .with_field("cpt_codes")
+                .with_suggested_fix("Review if duplicate codes are intentional or remove duplicates");
+                if let Some(line) = repeated_line(&claim.extracted_data.service_lines, cpt_code) {
+                    result = result.with_fix(FixPatch::RemoveLine { line });
+                }
+                results.push(result);
+            }
+        }
+
+        // Rule 11: Check NPI format and check digit
+        let data = &claim.extracted_data;
+        let npis = [
+            ("provider_npi", "Provider", &data.provider_npi),
+            ("billing_npi", "Billing", &data.billing_npi),
+            ("rendering_npi", "Rendering", &data.rendering_npi),
+        ];
+        for (field, label, value) in npis {
+            let Some(value) = value else { continue };
+            // provider_npi usually repeats one of the others; report it once
+            if field == "provider_npi" && (data.billing_npi.as_ref() == Some(value) || data.rendering_npi.as_ref() == Some(value)) {
+                continue;
+            }
+            if let Err(e) = npi::validate(value) {
+                results.push(
+                    ValidationResult::new(
+                        "invalid_npi",
+                        "Invalid NPI",
+                        Severity::Critical,
+                        format!("{} NPI {} is invalid: {}", label, value, e),
+                    )
+                    .with_field(field)
+                    .with_suggested_fix("Verify the NPI against NPPES and correct the number"),
+                );
+            }
+        }
+
+        results
+    }
+
+    /// Checks diagnosis codes against the imported ICD-10-CM tables for the
+    /// fiscal year of the date of service. Without a table for that year the
+    /// nearest earlier one (or the newest) is used at reduced confidence.
+    fn diagnosis_code_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let years = &self.reference.icd10_fiscal_years;
+        let Some(&newest) = years.last() else {
+            return vec![];
+        };
+
+        let service_year = claim.extracted_data.date_of_service().map(icd10::fiscal_year);
+        let (fiscal_year, confidence) = match service_year {
+            Some(year) if years.contains(&year) => (year, 1.0),
+            Some(year) => (years.iter().rev().find(|&&y| y < year).copied().unwrap_or(newest), 0.8),
+            None => (newest, 0.8),
+        };
+
+        let mut results = Vec::new();
+        for code in &claim.extracted_data.diagnosis_codes {
+            let rows = self.reference.icd10_codes
+                .get(&icd10::normalize(code))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            let result = match icd10::status(rows, fiscal_year) {
+                CodeStatus::Billable => continue,
+                CodeStatus::Header => ValidationResult::new(
+                    "truncated_diagnosis_code",
+                    "Truncated Diagnosis Code",
+                    Severity::Critical,
+                    format!("Diagnosis code {} is a category header in FY{}, not a billable code", icd10::display(code), fiscal_year),
+                )
+                .with_suggested_fix("Code to the highest level of specificity by adding the missing characters"),
+                CodeStatus::NotEffective { fiscal_years } => {
+                    let message = if fiscal_years.iter().all(|&y| y < fiscal_year) {
+                        format!("Diagnosis code {} was deleted after FY{}", icd10::display(code), fiscal_years[fiscal_years.len() - 1])
+                    } else {
+                        format!("Diagnosis code {} is not effective until FY{}", icd10::display(code), fiscal_years[0])
+                    };
+                    ValidationResult::new("expired_diagnosis_code", "Expired Diagnosis Code", Severity::Critical, message)
+                        .with_suggested_fix("Replace it with the code valid on the date of service")
+                }
+                CodeStatus::Unknown => ValidationResult::new(
+                    "invalid_diagnosis_code",
+                    "Invalid Diagnosis Code",
+                    Severity::Critical,
+                    format!("Diagnosis code {} is not in the ICD-10-CM code set", icd10::display(code)),
+                )
+                .with_suggested_fix("Check the code for typos or OCR errors against the ICD-10-CM index"),
+            };
+
+            let mut result = result
+                .with_field("diagnosis_codes")
+                .with_value("diagnosis_code", code)
+                .with_value("fiscal_year", fiscal_year)
+                .with_confidence(confidence);
+            if !rows.is_empty() {
+                result = result.with_reference("icd10_codes", rows);
+            }
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Checks each procedure code against the imported CPT/HCPCS table on
+    /// its date of service: the line's date where the code was billed on a
+    /// dated service line, otherwise the claim's.
+    fn procedure_code_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        if !self.reference.procedure_codes_loaded {
+            return vec![];
+        }
+
+        let data = &claim.extracted_data;
+        let claim_date = data.date_of_service();
+        let mut billed: Vec<(&str, Option<DateTime<Utc>>)> = Vec::new();
+        for line in &data.service_lines {
+            let entry = (line.procedure_code.as_str(), line.date_of_service.or(claim_date));
+            if !billed.contains(&entry) {
+                billed.push(entry);
+            }
+        }
+        // Claim-level codes only stand in for lines the parser didn't find.
+        if data.service_lines.is_empty() {
+            for code in &data.cpt_codes {
+                if !billed.iter().any(|(billed_code, _)| billed_code == code) {
+                    billed.push((code.as_str(), claim_date));
+                }
+            }
+        }
+
+        let mut results = Vec::new();
+        for (code, date) in billed {
+            if !procedure_codes::is_valid_format(code) {
+                continue; // already reported by invalid_cpt_code
+            }
+            let rows = self.reference.procedure_codes
+                .get(code)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            let result = match procedure_codes::status(rows, date.map(|d| d.date_naive())) {
+                ProcedureStatus::Active => continue,
+                ProcedureStatus::Unknown => ValidationResult::new(
+                    "unknown_procedure_code",
+                    "Unknown Procedure Code",
+                    Severity::Critical,
+                    format!("{} code {} is not in the procedure code table", procedure_codes::code_type(code), code),
+                )
+                .with_suggested_fix("Check the code for typos or OCR errors against the current CPT/HCPCS code set"),
+                ProcedureStatus::Deleted { termination_date } => ValidationResult::new(
+                    "deleted_procedure_code",
+                    "Deleted Procedure Code",
+                    Severity::Critical,
+                    format!("Procedure code {} was terminated after {}", code, termination_date.format("%m/%d/%Y")),
+                )
+                .with_suggested_fix("Replace it with the code valid on the date of service"),
+                ProcedureStatus::NotYetEffective { effective_date } => ValidationResult::new(
+                    "procedure_code_not_effective",
+                    "Procedure Code Not Yet Effective",
+                    Severity::Critical,
+                    format!("Procedure code {} is not effective until {}", code, effective_date.format("%m/%d/%Y")),
+                )
+                .with_suggested_fix("Verify the date of service or use the code valid on that date"),
+            };
+
+            let mut result = result
+                .with_field("cpt_codes")
+                .with_value("procedure_code", code)
+                .with_value("date_of_service", date);
+            if !rows.is_empty() {
+                result = result.with_reference("procedure_codes", rows);
+            }
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Flags NCCI procedure-to-procedure pairs billed on the same date.
+    fn ncci_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let services = claim.extracted_data.billed_services();
+        // Without service lines, modifiers can't be tied to a code
+        let confidence = if claim.extracted_data.service_lines.is_empty() { 0.7 } else { 0.95 };
+
+        ncci::find_conflicts(&services, &self.reference.ncci_edits)
+            .into_iter()
+            .map(|conflict| {
+                let edit = conflict.edit;
+                let date = conflict.date_of_service
+                    .map(|d| format!(" on {}", d.format("%m/%d/%Y")))
+                    .unwrap_or_default();
+                let rationale = if edit.rationale.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", edit.rationale)
+                };
+                let fix = if edit.modifier_indicator == 1 {
+                    format!(
+                        "If {} was a distinct service, append modifier {} to it; otherwise remove it",
+                        edit.column2,
+                        ncci::BYPASS_MODIFIERS.join(", "),
+                    )
+                } else {
+                    format!("Remove {}; no modifier bypasses this edit", edit.column2)
+                };
+
+                ValidationResult::new(
+                    "ncci_ptp_conflict",
+                    "NCCI Procedure-to-Procedure Edit",
+                    Severity::Critical,
+                    format!("{} is bundled into {}{}{}", edit.column2, edit.column1, date, rationale),
+                )
+                .with_field("cpt_codes")
+                .with_suggested_fix(&fix)
+                .with_value("column1_code", &edit.column1)
+                .with_value("column2_code", &edit.column2)
+                .with_value("date_of_service", conflict.date_of_service)
+                .with_reference("ncci_edits", edit)
+                .with_confidence(confidence)
+            })
+            .collect()
+    }
+
+    /// Compares billed units against Medically Unlikely Edits.
+    fn mue_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let services = claim.extracted_data.billed_services();
+
+        mue::find_violations(&services, &self.reference.mue_limits)
+            .into_iter()
+            .map(|violation| {
+                let limit = violation.limit;
+                let date = violation.date_of_service
+                    .map(|d| format!(" on {}", d.format("%m/%d/%Y")))
+                    .unwrap_or_default();
+                let excess = violation.units - limit.mue_value;
+                let fix = match limit.adjudication_indicator {
+                    1 => format!(
+                        "This is a per-line edit: reduce the line to {} units, or if the {} additional units were separately \
+                         performed, report them on another line with an appropriate modifier (e.g. 76, 91, or an anatomic modifier) \
+                         and document medical necessity",
+                        limit.mue_value, excess,
+                    ),
+                    2 => format!(
+                        "Reduce {} to {} units for the date of service; this limit is set by policy and units above it will be denied \
+                         even on appeal",
+                        limit.code, limit.mue_value,
+                    ),
+                    _ => format!(
+                        "Reduce {} to {} units for the date of service, or keep documentation of medical necessity for the {} \
+                         additional units to support an appeal",
+                        limit.code, limit.mue_value, excess,
+                    ),
+                };
+
+                ValidationResult::new(
+                    "mue_exceeded",
+                    "Medically Unlikely Edit Exceeded",
+                    Severity::Critical,
+                    format!(
+                        "{} units of {} billed{}; the MUE is {} ({})",
+                        violation.units, limit.code, date, limit.mue_value, mue::describe_indicator(limit.adjudication_indicator),
+                    ),
+                )
+                .with_field("service_lines")
+                .with_suggested_fix(&fix)
+                .with_value("procedure_code", &limit.code)
+                .with_value("units", violation.units)
+                .with_value("date_of_service", violation.date_of_service)
+                .with_reference("mue_limits", limit)
+            })
+            .collect()
+    }
+
+    fn modifier_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let mut results = Vec::new();
+        if claim.extracted_data.service_lines.is_empty() {
+            return results;
+        }
+        let services = claim.extracted_data.billed_services();
+
+        for (index, service) in services.iter().enumerate() {
+            let line = format!("Line {} ({})", index + 1, service.procedure_code);
+
+            for modifier in &service.modifiers {
+                if !self.reference.modifier_codes.is_empty() && !self.reference.modifier_codes.contains(modifier) {
+                    results.push(
+                        ValidationResult::new(
+                            "invalid_modifier",
+                            "Invalid Modifier",
+                            Severity::Critical,
+                            format!("{}: modifier {} is not a recognized CPT or HCPCS modifier", line, modifier),
+                        )
+                        .with_field("service_lines")
+                        .with_suggested_fix("Correct or remove the modifier"),
+                    );
+                }
+            }
+
+            for (first, second, reason) in modifiers::conflicts(service) {
+                results.push(
+                    ValidationResult::new(
+                        "conflicting_modifiers",
+                        "Conflicting Modifiers",
+                        Severity::Critical,
+                        format!("{}: modifiers {} and {} cannot be reported together", line, first, second),
+                    )
+                    .with_field("service_lines")
+                    .with_suggested_fix(&format!("Remove one of the modifiers; {}", reason)),
+                );
+            }
+
+            let same_day = |other: &&BilledService| !std::ptr::eq(*other, service) && other.date_of_service == service.date_of_service;
+
+            // An E/M visit on the same day as a procedure with a global
+            // period is bundled into it unless marked separately identifiable.
+            // Only the visit notes can say whether it was, so there's no
+            // machine fix: appending 25 blindly would assert it was.
+            let has_procedure_same_day = services.iter()
+                .filter(same_day)
+                .any(|other| modifiers::is_surgical_procedure(&other.procedure_code));
+            if modifiers::is_evaluation_and_management(&service.procedure_code)
+                && has_procedure_same_day
+                && !service.modifiers.iter().any(|m| m == "25" || m == "57")
+            {
+                results.push(
+                    ValidationResult::new(
+                        "missing_required_modifier",
+                        "Missing Required Modifier",
+                        Severity::Warning,
+                        format!("{}: E/M service billed with a procedure on the same day without modifier 25", line),
+                    )
+                    .with_field("service_lines")
+                    .with_suggested_fix("Append modifier 25 if the E/M was significant and separately identifiable, otherwise remove it")
+                    .with_confidence(0.8),
+                );
+            }
+
+            if service.modifiers.iter().any(|m| m == "59") {
+                let other_procedure_same_day = services.iter()
+                    .filter(same_day)
+                    .any(|other| other.procedure_code != service.procedure_code);
+                let message = if !other_procedure_same_day {
+                    Some(format!("{}: modifier 59 used with no other procedure on the same date", line))
+                } else if services.iter().filter(|s| s.modifiers.iter().any(|m| m == "59")).count() > 1 {
+                    Some(format!("{}: modifier 59 is used on several lines of this claim", line))
+                } else {
+                    None
+                };
+
+                if let Some(message) = message {
+                    results.push(
+                        ValidationResult::new("modifier_59_overuse", "Modifier 59 Overuse", Severity::Warning, message)
+                            .with_field("service_lines")
+                            .with_suggested_fix(&format!(
+                                "Use the more specific {} modifier where it applies, and only where an NCCI edit requires it",
+                                modifiers::DISTINCT_SERVICE_MODIFIERS.join(", "),
+                            ))
+                            .with_confidence(0.8),
+                    );
+                }
+            }
+        }
+
+        results
+    }
+
+    /// Checks each line's pointed diagnoses against the LCDs and NCDs that
+    /// cover its procedure code. A line is supported when any of them falls
+    /// in a covered range of any policy in effect on its date of service.
+    fn coverage_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let data = &claim.extracted_data;
+        let mut results = Vec::new();
+        if self.reference.coverage_rules.is_empty() || data.diagnosis_codes.is_empty() {
+            return results;
+        }
+
+        for (index, service) in data.billed_services().iter().enumerate() {
+            let policies: Vec<_> = self.reference.coverage_rules.iter()
+                .filter(|rule| rule.procedure_code == service.procedure_code)
+                .filter(|rule| coverage::is_in_effect(rule, service.date_of_service))
+                .collect();
+            if policies.is_empty() {
+                continue;
+            }
+
+            // Without pointers every diagnosis on the claim is a candidate
+            let pointers = data.service_lines.get(index).map(|line| &line.diagnosis_pointers[..]).unwrap_or_default();
+            let (diagnoses, confidence): (Vec<&String>, f64) = if pointers.is_empty() {
+                (data.diagnosis_codes.iter().collect(), 0.7)
+            } else {
+                let pointed = pointers.iter()
+                    .filter_map(|pointer| (pointer.to_ascii_uppercase() as usize).checked_sub('A' as usize))
+                    .filter_map(|position| data.diagnosis_codes.get(position))
+                    .collect();
+                (pointed, 0.9)
+            };
+            if diagnoses.iter().any(|code| policies.iter().any(|rule| coverage::covers(rule, code))) {
+                continue;
+            }
+
+            let mut policy_names: Vec<String> = Vec::new();
+            for rule in &policies {
+                let name = if rule.title.is_empty() {
+                    format!("{} {}", rule.policy_type, rule.policy_id)
+                } else {
+                    format!("{} {} ({})", rule.policy_type, rule.policy_id, rule.title)
+                };
+                if !policy_names.contains(&name) {
+                    policy_names.push(name);
+                }
+            }
+            let ranges: Vec<String> = policies.iter().map(|rule| coverage::describe_range(rule)).collect();
+            let listed = if ranges.len() > 10 {
+                format!("{} and {} more", ranges[..10].join(", "), ranges.len() - 10)
+            } else {
+                ranges.join(", ")
+            };
+            let diagnosis_list = if diagnoses.is_empty() {
+                "no valid diagnosis pointer".to_string()
+            } else {
+                diagnoses.iter().map(|code| code.as_str()).collect::<Vec<_>>().join(", ")
+            };
+
+            results.push(
+                ValidationResult::new(
+                    "diagnosis_not_covered",
+                    "Diagnosis Does Not Support Medical Necessity",
+                    Severity::Critical,
+                    format!(
+                        "Line {} ({}): {} not covered under {}",
+                        index + 1, service.procedure_code, diagnosis_list, policy_names.join("; "),
+                    ),
+                )
+                .with_field("diagnosis_codes")
+                .with_suggested_fix(&format!(
+                    "If documented, point the line to a covered diagnosis: {}. Otherwise obtain an ABN and append modifier GA",
+                    listed,
+                ))
+                .with_value("line", index + 1)
+                .with_value("procedure_code", &service.procedure_code)
+                .with_value("diagnosis_codes", &diagnoses)
+                .with_value("date_of_service", service.date_of_service)
+                .with_reference("coverage_policies", &policies)
+                .with_confidence(confidence),
+            );
+        }
+
+        results
+    }
+
+    /// Flags diagnosis and procedure codes restricted to an age range or sex
+    /// the patient doesn't match on the date of service.
+    fn demographic_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let data = &claim.extracted_data;
+        let mut results = Vec::new();
+        if self.reference.code_attributes.is_empty() || (data.patient_dob.is_none() && data.patient_sex.is_none()) {
+            return results;
+        }
+
+        let claim_date = data.date_of_service().map(|d| d.date_naive());
+        let mut checks: Vec<(CodeSystem, &str, Option<NaiveDate>, &str)> = data.diagnosis_codes.iter()
+            .map(|code| (CodeSystem::Icd10, code.as_str(), claim_date, "diagnosis_codes"))
+            .collect();
+        let services = data.billed_services();
+        checks.extend(services.iter().map(|service| {
+            (CodeSystem::Procedure, service.procedure_code.as_str(), service.date_of_service.or(claim_date), "service_lines")
+        }));
+
+        let mut reported: HashSet<(&str, &str)> = HashSet::new();
+        for (code_system, code, date, field) in checks {
+            let kind = match code_system {
+                CodeSystem::Icd10 => "Diagnosis",
+                CodeSystem::Procedure => "Procedure",
+            };
+
+            for attribute in demographics::attributes_for(&self.reference.code_attributes, code_system, code) {
+                let label = if attribute.description.is_empty() {
+                    format!("{} {}", kind, code)
+                } else {
+                    format!("{} {} ({})", kind, code, attribute.description)
+                };
+
+                let age = data.patient_dob.zip(date).and_then(|(dob, date)| Some((demographics::age_on(dob, date)?, date)));
+                if let Some((age, date)) = age {
+                    if !demographics::allows_age(attribute, age) && reported.insert(("age_code_conflict", code)) {
+                        results.push(
+                            ValidationResult::new(
+                                "age_code_conflict",
+                                "Code Conflicts With Patient Age",
+                                Severity::Critical,
+                                format!(
+                                    "{} is limited to {}; the patient was {} on {}",
+                                    label, demographics::describe_ages(attribute), age, date.format("%m/%d/%Y"),
+                                ),
+                            )
+                            .with_field(field)
+                            .with_suggested_fix("Verify the patient's date of birth and the code; correct whichever is wrong")
+                            .with_value("code", code)
+                            .with_value("patient_dob", data.patient_dob)
+                            .with_value("patient_age", age)
+                            .with_value("date_of_service", date)
+                            .with_reference("code_attributes", attribute)
+                            .with_confidence(0.85),
+                        );
+                    }
+                }
+
+                if let (Some(required), Some(sex)) = (attribute.sex, data.patient_sex) {
+                    if required != sex && reported.insert(("sex_code_conflict", code)) {
+                        results.push(
+                            ValidationResult::new(
+                                "sex_code_conflict",
+                                "Code Conflicts With Patient Sex",
+                                Severity::Critical,
+                                format!("{} applies only to {} patients; the patient is {}", label, required.as_str(), sex.as_str()),
+                            )
+                            .with_field(field)
+                            .with_suggested_fix("Verify the patient's sex and the code; correct whichever is wrong")
+                            .with_value("code", code)
+                            .with_value("patient_sex", sex)
+                            .with_reference("code_attributes", attribute)
+                            .with_confidence(0.85),
+                        );
+                    }
+                }
+            }
+        }
+
+        results
+    }
+
+    /// Looks for other claims billing the same patient, provider, date of
+    /// service and procedure codes. A replacement or void (frequency 7/8) is
+    /// expected to match the original its reference names and is only noted;
+    /// two corrections of the same original are flagged.
+    fn duplicate_claim_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let data = &claim.extracted_data;
+
+        duplicates::find_duplicates(data, &self.reference.related_claims)
+            .into_iter()
+            .map(|found| {
+                let other = found.claim;
+                let date = data.date_of_service()
+                    .map(|d| d.format("%m/%d/%Y").to_string())
+                    .unwrap_or_default();
+                let codes = found.shared_codes.join(", ");
+                let other_claim = serde_json::json!({
+                    "id": other.id,
+                    "filename": other.filename,
+                    "status": other.status,
+                    "frequency_code": other.extracted_data.frequency_code,
+                    "original_claim_reference": other.extracted_data.original_claim_reference,
                 });
+
+                let correction = if duplicates::corrects(data, other) {
+                    Some((claim, other))
+                } else if duplicates::corrects(&other.extracted_data, claim) {
+                    Some((other, claim))
+                } else {
+                    None
+                };
+                if let Some((replacement, original)) = correction {
+                    let action = if replacement.extracted_data.frequency_code.as_deref() == Some(duplicates::VOID_FREQUENCY_CODE) {
+                        "voids"
+                    } else {
+                        "replaces"
+                    };
+                    return ValidationResult::new(
+                        "duplicate_claim",
+                        "Duplicate Claim",
+                        Severity::Info,
+                        format!(
+                            "Corrected claim {} {} claim {} ({:?}) for date of service {}",
+                            replacement.id, action, original.id, original.status, date,
+                        ),
+                    )
+                    .with_field("frequency_code")
+                    .with_value("frequency_code", &data.frequency_code)
+                    .with_value("original_claim_reference", &data.original_claim_reference)
+                    .with_reference("claims", other_claim)
+                    .with_confidence(0.9);
+                }
+
+                if duplicates::same_original(data, &other.extracted_data) {
+                    return ValidationResult::new(
+                        "duplicate_claim",
+                        "Duplicate Claim",
+                        Severity::Critical,
+                        format!(
+                            "Claim {} ({:?}) also corrects original claim {}; only one replacement or void should be sent",
+                            other.id, other.status, data.original_claim_reference.as_deref().unwrap_or_default(),
+                        ),
+                    )
+                    .with_field("original_claim_reference")
+                    .with_suggested_fix("Withdraw one of the corrections, or void the earlier replacement before sending another")
+                    .with_value("frequency_code", &data.frequency_code)
+                    .with_value("original_claim_reference", &data.original_claim_reference)
+                    .with_reference("claims", other_claim)
+                    .with_confidence(0.9);
+                }
+
+                let (severity, description, confidence) = match found.kind {
+                    MatchKind::Exact => (Severity::Critical, "Duplicate of", 0.95),
+                    MatchKind::Partial => (Severity::Warning, "Overlaps with", 0.75),
+                };
+                ValidationResult::new(
+                    "duplicate_claim",
+                    "Duplicate Claim",
+                    severity,
+                    format!(
+                        "{} claim {} ({:?}): same patient, provider and date of service {}, procedure codes {}",
+                        description, other.id, other.status, date, codes,
+                    ),
+                )
+                .with_field("cpt_codes")
+                .with_suggested_fix(
+                    "Do not resubmit a claim that is still pending or paid. If this claim corrects the earlier one, \
+                     set frequency code 7 (replacement) or 8 (void) with the original claim's reference number",
+                )
+                .with_value("patient_name", &data.patient_name)
+                .with_value("provider_npi", &data.provider_npi)
+                .with_value("date_of_service", data.date_of_service())
+                .with_value("shared_codes", &found.shared_codes)
+                .with_reference("claims", other_claim)
+                .with_confidence(confidence)
+            })
+            .collect()
+    }
+
+    /// Warns as a claim nears its payer's filing limit and goes critical
+    /// once the deadline has passed. A claim already submitted was filed
+    /// when it was sent, so re-running the rules later doesn't flag it.
+    fn timely_filing_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        if claim.status.is_submitted() {
+            return Vec::new();
+        }
+        let Some(payer) = &self.reference.payer else {
+            return Vec::new();
+        };
+        let Some(filing) = payers::filing_deadline(payer, &claim.extracted_data) else {
+            return Vec::new();
+        };
+
+        let days_left = (filing.deadline - self.as_of).num_days();
+        let limit = format!(
+            "{}'s {}-day limit from the {} ({})",
+            payer.name, filing.limit_days, filing.basis, filing.basis_date.format("%m/%d/%Y"),
+        );
+        let deadline = filing.deadline.format("%m/%d/%Y");
+
+        let result = if days_left < 0 {
+            ValidationResult::new(
+                "timely_filing",
+                "Timely Filing Limit",
+                Severity::Critical,
+                format!("Filing deadline {} has passed under {}", deadline, limit),
+            )
+            .with_suggested_fix("Submit only with proof of timely filing (e.g. an earlier clearinghouse acceptance report); otherwise expect a denial")
+        } else if days_left <= payers::FILING_WARNING_DAYS {
+            ValidationResult::new(
+                "timely_filing",
+                "Timely Filing Limit",
+                Severity::Warning,
+                format!("Filing deadline {} is {} days away under {}", deadline, days_left, limit),
+            )
+            .with_suggested_fix(&format!("Submit the claim before {}", deadline))
+        } else {
+            return Vec::new();
+        };
+
+        vec![
+            result
+                .with_field("date_of_service")
+                .with_value("basis", filing.basis)
+                .with_value("basis_date", filing.basis_date)
+                .with_value("deadline", filing.deadline)
+                .with_value("as_of", self.as_of)
+                .with_reference("payers", payer)
+                .with_confidence(0.9),
+        ]
+    }
+
+    /// Sanity checks across the claim's labelled dates.
+    fn date_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let data = &claim.extracted_data;
+        let mut results = Vec::new();
+        let format = |date: NaiveDate| date.format("%m/%d/%Y").to_string();
+
+        let mut service_dates: Vec<NaiveDate> = data.service_dates().iter().map(|d| d.date_naive()).collect();
+        service_dates.sort();
+        service_dates.dedup();
+        let (Some(&first), Some(&last)) = (service_dates.first(), service_dates.last()) else {
+            return results;
+        };
+
+        if last > self.as_of {
+            results.push(
+                ValidationResult::new(
+                    "future_date_of_service",
+                    "Future Date of Service",
+                    Severity::Critical,
+                    format!("Date of service {} is in the future", format(last)),
+                )
+                .with_field("dates")
+                .with_suggested_fix("Correct the date, or hold the claim until the service has been performed"),
+            );
+        }
+
+        if let Some(dob) = data.patient_dob.filter(|dob| first < *dob) {
+            results.push(
+                ValidationResult::new(
+                    "service_before_birth",
+                    "Service Before Date of Birth",
+                    Severity::Critical,
+                    format!("Date of service {} is before the patient's date of birth {}", format(first), format(dob)),
+                )
+                .with_field("dates")
+                .with_suggested_fix("Verify the patient's date of birth and the date of service"),
+            );
+        }
+
+        let from = data.dates_with_role(DateRole::ServiceFrom).map(|d| d.date_naive()).min();
+        let to = data.dates_with_role(DateRole::ServiceTo).map(|d| d.date_naive()).max();
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                results.push(
+                    ValidationResult::new(
+                        "service_dates_reversed",
+                        "Service Dates Reversed",
+                        Severity::Critical,
+                        format!("Service from date {} is after the to date {}", format(from), format(to)),
+                    )
+                    .with_field("dates")
+                    .with_suggested_fix("Swap or correct the from and to dates")
+                    .with_confidence(0.9),
+                );
+            }
+        }
+
+        let span = (last - first).num_days();
+        if span > MAX_SERVICE_SPAN_DAYS {
+            results.push(
+                ValidationResult::new(
+                    "service_span_too_long",
+                    "Implausible Service Date Range",
+                    Severity::Warning,
+                    format!("Service dates span {} days, from {} to {}", span, format(first), format(last)),
+                )
+                .with_field("dates")
+                .with_suggested_fix(&format!(
+                    "Check for a mistyped year; services more than {} days apart belong on separate claims",
+                    MAX_SERVICE_SPAN_DAYS,
+                ))
+                .with_confidence(0.7),
+            );
+        }
+
+        if let Some(onset) = data.dates_with_role(DateRole::Onset).map(|d| d.date_naive()).find(|onset| *onset > last) {
+            results.push(
+                ValidationResult::new(
+                    "onset_after_service",
+                    "Onset After Service",
+                    Severity::Warning,
+                    format!("Onset date {} is after the last date of service {}", format(onset), format(last)),
+                )
+                .with_field("dates")
+                .with_suggested_fix("Correct the date of current illness, injury or pregnancy (box 14)")
+                .with_confidence(0.8),
+            );
+        }
+
+        if let Some(admission) = data.dates_with_role(DateRole::Admission).map(|d| d.date_naive()).min().filter(|a| first < *a) {
+            results.push(
+                ValidationResult::new(
+                    "service_before_admission",
+                    "Service Before Admission",
+                    Severity::Warning,
+                    format!("Date of service {} is before the admission date {}", format(first), format(admission)),
+                )
+                .with_field("dates")
+                .with_suggested_fix("Check the admission date (box 18), or bill pre-admission services as outpatient")
+                .with_confidence(0.7),
+            );
+        }
+
+        results
+    }
+
+    /// Compares line charges to the Medicare fee schedule and chargemaster,
+    /// and the stated total to the sum of the lines.
+    fn charge_rules(&self, claim: &Claim) -> Vec<ValidationResult> {
+        let data = &claim.extracted_data;
+        let mut results = Vec::new();
+
+        for (index, line) in data.service_lines.iter().enumerate() {
+            let Some(charge) = line.charge else { continue };
+            let label = format!("Line {} ({})", index + 1, line.procedure_code);
+            let units = line.units.max(1) as f64;
+            let lookup = |source| fee_schedule::lookup(&self.reference.fee_schedule, source, &line.procedure_code, &line.modifiers);
+
+            if let Some(entry) = lookup(FeeSource::Medicare) {
+                let allowed = entry.amount * units;
+                if charge + 0.005 < allowed {
+                    results.push(
+                        ValidationResult::new(
+                            "charge_below_medicare",
+                            "Charge Below Expected Reimbursement",
+                            Severity::Warning,
+                            format!(
+                                "{}: charge ${:.2} is below the Medicare allowed amount of ${:.2} (locality {}); payment is capped at the charge",
+                                label, charge, allowed, entry.locality,
+                            ),
+                        )
+                        .with_field("charges")
+                        .with_suggested_fix("Check the charge against the chargemaster; it may be missing units or a digit")
+                        .with_value("procedure_code", &line.procedure_code)
+                        .with_value("units", line.units)
+                        .with_value("charge", charge)
+                        .with_reference("fee_schedule", entry)
+                        .with_confidence(0.85),
+                    );
+                }
+            }
+
+            if let Some(entry) = lookup(FeeSource::Chargemaster) {
+                let price = entry.amount * units;
+                if price > 0.0 && charge > price * fee_schedule::CHARGEMASTER_TOLERANCE {
+                    results.push(
+                        ValidationResult::new(
+                            "charge_above_chargemaster",
+                            "Charge Above Chargemaster Price",
+                            Severity::Warning,
+                            format!("{}: charge ${:.2} is {:.1}x the chargemaster price of ${:.2}", label, charge, charge / price, price),
+                        )
+                        .with_field("charges")
+                        .with_suggested_fix("Check for a keying error or extra units")
+                        .with_value("procedure_code", &line.procedure_code)
+                        .with_value("units", line.units)
+                        .with_value("charge", charge)
+                        .with_reference("fee_schedule", entry)
+                        .with_confidence(0.8),
+                    );
+                }
+            }
+        }
+
+        let line_charges: Option<Vec<f64>> = data.service_lines.iter().map(|line| line.charge).collect();
+        if let (Some(total), Some(line_charges)) = (data.total_charge, line_charges.filter(|charges| !charges.is_empty())) {
+            let sum: f64 = line_charges.iter().sum();
+            if (total - sum).abs() > 0.005 {
+                results.push(
+                    ValidationResult::new(
+                        "total_charge_mismatch",
+                        "Total Charge Mismatch",
+                        Severity::Critical,
+                        format!("Total charge ${:.2} doesn't match the sum of line charges ${:.2}", total, sum),
+                    )
+                    .with_field("charges")
+                    .with_suggested_fix("Correct the total charge (box 28) or the line charges")
+                    .with_fix(FixPatch::SetField {
+                        field: "total_charge".to_string(),
+                        value: serde_json::json!((sum * 100.0).round() / 100.0),
+                    })
+                    .with_value("total_charge", total)
+                    .with_value("line_charges", &line_charges)
+                    .with_confidence(0.9),
+                );
             }
         }
 
-        Ok(results)
+        results
+    }
+}
+
+/// The 1-based number of a line repeating an earlier line of `code` in
+/// every detail, which is safe to drop.
+fn repeated_line(lines: &[ServiceLine], code: &str) -> Option<usize> {
+    let same = |a: &ServiceLine, b: &ServiceLine| {
+        a.procedure_code == b.procedure_code
+            && a.modifiers == b.modifiers
+            && a.units == b.units
+            && a.charge == b.charge
+            && a.date_of_service == b.date_of_service
+    };
+    lines.iter()
+        .enumerate()
+        .filter(|(_, line)| line.procedure_code == code)
+        .find(|(index, line)| lines[..*index].iter().any(|earlier| same(earlier, line)))
+        .map(|(index, _)| index + 1)
+}
+
+/// The queue a claim with these findings belongs in.
+pub fn queue_for(results: &[ValidationResult]) -> QueueType {
+    if results.iter().any(|r| matches!(r.severity, Severity::Critical)) {
+        QueueType::CriticalErrors
+    } else if results.iter().any(|r| matches!(r.severity, Severity::Warning)) {
+        QueueType::WarningsOnly
+    } else {
+        QueueType::ApprovedClaims
     }
 }
 
+/// Rejects overrides for unknown rules, duplicate entries and thresholds
+/// outside 0..=1.
+pub fn validate_overrides(overrides: &[RuleOverride], custom_rules: &[CompiledRule]) -> Result<()> {
+    let mut seen = HashSet::new();
+
+    for rule_override in overrides {
+        let rule_id = rule_override.rule_id.as_str();
+        let known = BUILTIN_RULE_IDS.contains(&rule_id)
+            || custom_rules.iter().any(|rule| rule.id == rule_id);
+        if !known {
+            bail!("Unknown rule id: {}", rule_id);
+        }
+        if !seen.insert(rule_id) {
+            bail!("Rule {} is configured more than once", rule_id);
+        }
+        if rule_override.min_confidence.is_some_and(|min| !(0.0..=1.0).contains(&min)) {
+            bail!("Confidence threshold for {} must be between 0 and 1", rule_id);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+    use crate::rule_tests;
+    use crate::types::{ClaimStatus, ExtractedData, ExtractedDate, Icd10Code, ProcedureCode, ServiceLine, Sex};
+
+    fn empty_claim() -> Claim {
+        rule_tests::fixture_claim(&ExtractedData::default())
+    }
+
+    fn service_line(code: &str) -> ServiceLine {
+        ServiceLine {
+            procedure_code: code.to_string(),
+            modifiers: vec![],
+            units: 1,
+            charge: None,
+            date_of_service: None,
+            diagnosis_pointers: vec![],
+        }
+    }
+
+    fn service_date(date: DateTime<Utc>) -> ExtractedDate {
+        ExtractedDate::new(DateRole::ServiceFrom, date)
+    }
+
+    fn rule_override(rule_id: &str) -> RuleOverride {
+        RuleOverride {
+            rule_id: rule_id.to_string(),
+            enabled: true,
+            severity: None,
+            min_confidence: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overrides_disable_downgrade_and_threshold() {
+        let configuration = RuleConfiguration {
+            version: 7,
+            overrides: vec![
+                RuleOverride { enabled: false, ..rule_override("missing_payer") },
+                RuleOverride { severity: Some(Severity::Warning), ..rule_override("missing_npi") },
+                RuleOverride { min_confidence: Some(0.85), ..rule_override("missing_provider_name") },
+            ],
+            ..Default::default()
+        };
+
+        let results = RulesEngine::new()
+            .with_configuration(configuration)
+            .validate_claim(&empty_claim())
+            .await
+            .unwrap();
+
+        assert!(!results.iter().any(|r| r.rule_id == "missing_payer"));
+        assert!(!results.iter().any(|r| r.rule_id == "missing_provider_name"));
+        let npi = results.iter().find(|r| r.rule_id == "missing_npi").unwrap();
+        assert_eq!(npi.severity, Severity::Warning);
+        assert!(results.iter().all(|r| r.config_version == Some(7)));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_npi_reported_once_per_number() {
+        let mut claim = empty_claim();
+        claim.extracted_data.billing_npi = Some("1234567890".to_string());
+        claim.extracted_data.provider_npi = Some("1234567890".to_string());
+        claim.extracted_data.rendering_npi = Some("1234567893".to_string());
+
+        let results = RulesEngine::new().validate_claim(&claim).await.unwrap();
+        let invalid: Vec<_> = results.iter().filter(|r| r.rule_id == "invalid_npi").collect();
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].field.as_deref(), Some("billing_npi"));
+        assert_eq!(invalid[0].severity, Severity::Critical);
+    }
+
+    #[tokio::test]
+    async fn test_diagnosis_codes_checked_against_service_year() {
+        let row = |code: &str, fiscal_year, billable| Icd10Code {
+            code: code.to_string(),
+            description: String::new(),
+            billable,
+            fiscal_year,
+        };
+        let mut reference = ReferenceData {
+            icd10_fiscal_years: vec![2023, 2024],
+            ..Default::default()
+        };
+        reference.icd10_codes.insert("E119".to_string(), vec![row("E119", 2023, true), row("E119", 2024, true)]);
+        reference.icd10_codes.insert("E11".to_string(), vec![row("E11", 2024, false)]);
+        reference.icd10_codes.insert("U071".to_string(), vec![row("U071", 2023, true)]);
+
+        let mut claim = empty_claim();
+        claim.extracted_data.dates = vec![service_date(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap())];
+        claim.extracted_data.diagnosis_codes = ["E11.9", "E11", "U07.1", "Q99.9"].map(String::from).to_vec();
+
+        let results = RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        let rule_ids: Vec<&str> = results.iter()
+            .filter(|r| r.field.as_deref() == Some("diagnosis_codes"))
+            .map(|r| r.rule_id.as_str())
+            .collect();
+        assert_eq!(rule_ids, ["truncated_diagnosis_code", "expired_diagnosis_code", "invalid_diagnosis_code"]);
+    }
+
+    #[tokio::test]
+    async fn test_procedure_codes_checked_on_date_of_service() {
+        let row = |code: &str, effective: Option<(i32, u32, u32)>, terminated: Option<(i32, u32, u32)>| ProcedureCode {
+            code: code.to_string(),
+            code_type: "CPT".to_string(),
+            description: String::new(),
+            effective_date: effective.and_then(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d)),
+            termination_date: terminated.and_then(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d)),
+        };
+        let mut reference = ReferenceData { procedure_codes_loaded: true, ..Default::default() };
+        reference.procedure_codes.insert("99213".to_string(), vec![row("99213", None, None)]);
+        reference.procedure_codes.insert("0001T".to_string(), vec![row("0001T", None, Some((2024, 3, 14)))]);
+        reference.procedure_codes.insert("99215".to_string(), vec![row("99215", None, Some((2024, 3, 15)))]);
+        reference.procedure_codes.insert("0901T".to_string(), vec![row("0901T", Some((2025, 1, 1)), None)]);
+
+        let mut claim = empty_claim();
+        claim.extracted_data.dates = vec![service_date(Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap())];
+        claim.extracted_data.cpt_codes = ["99213", "0001T", "99215", "0901T", "99499"].map(String::from).to_vec();
+
+        let results = RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        let fired: Vec<(&str, &str)> = results.iter()
+            .filter(|r| r.rule_id.contains("procedure_code"))
+            .map(|r| (r.rule_id.as_str(), r.message.as_str()))
+            .collect();
+        assert_eq!(fired, [
+            ("deleted_procedure_code", "Procedure code 0001T was terminated after 03/14/2024"),
+            ("procedure_code_not_effective", "Procedure code 0901T is not effective until 01/01/2025"),
+            ("unknown_procedure_code", "CPT code 99499 is not in the procedure code table"),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_modifier_rules() {
+        let mut claim = empty_claim();
+        claim.extracted_data.service_lines = vec![
+            service_line("99213"),
+            ServiceLine { modifiers: ["LT", "RT", "NY"].map(String::from).to_vec(), ..service_line("20610") },
+        ];
+        let reference = ReferenceData {
+            modifier_codes: ["LT", "RT", "25", "59"].map(String::from).into_iter().collect(),
+            ..Default::default()
+        };
+
+        let results = RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        let fired = |rule_id: &str| results.iter().filter(|r| r.rule_id == rule_id).count();
+        assert_eq!(fired("invalid_modifier"), 1);
+        assert_eq!(fired("conflicting_modifiers"), 1);
+        assert_eq!(fired("missing_required_modifier"), 1);
+        assert_eq!(fired("modifier_59_overuse"), 0);
+        let missing_25 = results.iter().find(|r| r.rule_id == "missing_required_modifier").unwrap();
+        assert!(missing_25.fix.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_line_gets_removal_fix() {
+        let mut claim = empty_claim();
+        claim.extracted_data.cpt_codes = ["20610", "20610", "20610"].map(String::from).to_vec();
+        claim.extracted_data.service_lines = [310.0, 155.0, 310.0]
+            .map(|charge| ServiceLine { charge: Some(charge), ..service_line("20610") })
+            .to_vec();
+
+        let results = RulesEngine::new().validate_claim(&claim).await.unwrap();
+        let duplicate = results.iter().find(|r| r.rule_id == "duplicate_cpt_code").unwrap();
+        assert_eq!(duplicate.fix, Some(FixPatch::RemoveLine { line: 3 }));
+    }
+
+    #[tokio::test]
+    async fn test_pointed_diagnoses_checked_against_coverage_policy() {
+        let mut claim = empty_claim();
+        claim.extracted_data.diagnosis_codes = ["M17.11", "E11.9"].map(String::from).to_vec();
+        claim.extracted_data.service_lines = [("20610", 'A'), ("20610", 'B'), ("99213", 'B')]
+            .map(|(code, pointer)| ServiceLine { diagnosis_pointers: vec![pointer], ..service_line(code) })
+            .to_vec();
+        let reference = ReferenceData {
+            coverage_rules: coverage::parse_policy_file(
+                "policy_id,title,policy_type,jurisdiction,procedure_code,icd10_start,icd10_end\n\
+                 L33252,Knee Injections,LCD,J15,20610,M17.0,M17.9\n"
+            ).unwrap(),
+            ..Default::default()
+        };
+
+        let results = RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        let findings: Vec<_> = results.iter().filter(|r| r.rule_id == "diagnosis_not_covered").collect();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.starts_with("Line 2 (20610): E11.9 not covered under LCD L33252"));
+        assert!(findings[0].suggested_fix.as_deref().unwrap().contains("M17.0-M17.9"));
+
+        let evidence = &findings[0].evidence;
+        assert_eq!(evidence.values["diagnosis_codes"], serde_json::json!(["E11.9"]));
+        let reference = evidence.reference.as_ref().unwrap();
+        assert_eq!(reference.table, "coverage_policies");
+        assert_eq!(reference.row[0]["policy_id"], "L33252");
+        assert_eq!(evidence.rule_version.as_deref(), Some(evidence::BUILTIN_RULES_VERSION));
+    }
+
+    #[tokio::test]
+    async fn test_age_and_sex_conflicts() {
+        let mut claim = empty_claim();
+        claim.extracted_data.patient_dob = NaiveDate::from_ymd_opt(1964, 6, 1);
+        claim.extracted_data.patient_sex = Some(Sex::Female);
+        claim.extracted_data.dates = vec![service_date(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap())];
+        claim.extracted_data.diagnosis_codes = ["O80", "E11.9"].map(String::from).to_vec();
+        claim.extracted_data.cpt_codes = vec!["55700".to_string()];
+        let reference = ReferenceData {
+            code_attributes: demographics::parse_attribute_file(
+                "code_system,code,min_age,max_age,sex,description\n\
+                 icd10,O,12,55,F,Maternity diagnosis\n\
+                 procedure,55700,,,M,Prostate biopsy\n"
+            ).unwrap(),
+            ..Default::default()
+        };
+
+        let results = RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        let age: Vec<_> = results.iter().filter(|r| r.rule_id == "age_code_conflict").collect();
+        assert_eq!(age.len(), 1);
+        assert!(age[0].message.contains("ages 12-55; the patient was 59"));
+        let sex: Vec<_> = results.iter().filter(|r| r.rule_id == "sex_code_conflict").collect();
+        assert_eq!(sex.len(), 1);
+        assert_eq!(sex[0].field.as_deref(), Some("service_lines"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_claims_and_corrections() {
+        let mut claim = empty_claim();
+        claim.extracted_data.patient_id = Some("P1001".to_string());
+        claim.extracted_data.cpt_codes = vec!["99213".to_string()];
+        claim.extracted_data.dates = vec![service_date(Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap())];
+        let mut original = empty_claim();
+        original.status = ClaimStatus::Submitted;
+        original.extracted_data = claim.extracted_data.clone();
+        let original_id = original.id;
+        let reference = ReferenceData { related_claims: vec![original.clone()], ..Default::default() };
+        let engine = RulesEngine::new().with_reference_data(reference);
+
+        let results = engine.validate_claim(&claim).await.unwrap();
+        let duplicate = results.iter().find(|r| r.rule_id == "duplicate_claim").unwrap();
+        assert_eq!(duplicate.severity, Severity::Critical);
+        assert!(duplicate.message.contains("(Submitted)"));
+
+        // A replacement only counts as one when its reference names the original.
+        claim.extracted_data.frequency_code = Some("7".to_string());
+        claim.extracted_data.original_claim_reference = Some("UNRELATED-REF".to_string());
+        let results = engine.validate_claim(&claim).await.unwrap();
+        let unmatched = results.iter().find(|r| r.rule_id == "duplicate_claim").unwrap();
+        assert_eq!(unmatched.severity, Severity::Critical);
+
+        claim.extracted_data.original_claim_reference = Some(original_id.to_string().to_uppercase());
+        let results = engine.validate_claim(&claim).await.unwrap();
+        let correction = results.iter().find(|r| r.rule_id == "duplicate_claim").unwrap();
+        assert_eq!(correction.severity, Severity::Info);
+        assert!(correction.message.contains("replaces"));
+
+        let mut second = claim.clone();
+        second.id = Uuid::new_v4();
+        let reference = ReferenceData { related_claims: vec![original, second], ..Default::default() };
+        let results = RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        let severities: Vec<Severity> = results.iter()
+            .filter(|r| r.rule_id == "duplicate_claim")
+            .map(|r| r.severity)
+            .collect();
+        assert_eq!(severities, [Severity::Info, Severity::Critical]);
+    }
+
+    #[tokio::test]
+    async fn test_timely_filing_warns_then_goes_critical() {
+        let mut claim = empty_claim();
+        claim.extracted_data.dates = vec![service_date(Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap())];
+        let reference = ReferenceData {
+            payer: payers::default_payers().into_iter().find(|p| p.id == "cigna"),
+            ..Default::default()
+        };
+        let filing = |claim: Claim, as_of: NaiveDate| {
+            let mut engine = RulesEngine::new().with_reference_data(reference.clone());
+            engine.as_of = as_of;
+            async move {
+                engine.validate_claim(&claim).await.unwrap()
+                    .into_iter()
+                    .find(|r| r.rule_id == "timely_filing")
+            }
+        };
+
+        assert!(filing(claim.clone(), NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()).await.is_none());
+        let warning = filing(claim.clone(), NaiveDate::from_ymd_opt(2024, 3, 20).unwrap()).await.unwrap();
+        assert_eq!(warning.severity, Severity::Warning);
+        assert!(warning.message.starts_with("Filing deadline 04/09/2024 is 20 days away"));
+        let past = filing(claim.clone(), NaiveDate::from_ymd_opt(2024, 4, 10).unwrap()).await.unwrap();
+        assert_eq!(past.severity, Severity::Critical);
+        assert_eq!(past.evidence.values["basis"], "date of service");
+        assert_eq!(past.evidence.values["basis_date"], "2024-01-10");
+
+        claim.status = ClaimStatus::Paid;
+        assert!(filing(claim, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_date_integrity_rules() {
+        let date = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap();
+        let mut claim = empty_claim();
+        claim.extracted_data.patient_dob = NaiveDate::from_ymd_opt(2024, 3, 10);
+        claim.extracted_data.dates = vec![
+            ExtractedDate::new(DateRole::Birth, date(2024, 3, 10)),
+            ExtractedDate::new(DateRole::ServiceFrom, date(2024, 3, 15)),
+            ExtractedDate::new(DateRole::ServiceTo, date(2024, 3, 1)),
+            ExtractedDate::new(DateRole::Onset, date(2024, 4, 1)),
+        ];
+        let mut engine = RulesEngine::new();
+        engine.as_of = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+
+        let results = engine.validate_claim(&claim).await.unwrap();
+        let fired: Vec<&str> = results.iter().filter(|r| r.field.as_deref() == Some("dates")).map(|r| r.rule_id.as_str()).collect();
+        assert_eq!(fired, ["service_before_birth", "service_dates_reversed", "onset_after_service"]);
+
+        engine.as_of = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let results = engine.validate_claim(&claim).await.unwrap();
+        assert!(results.iter().any(|r| r.rule_id == "future_date_of_service"));
+    }
+
+    #[tokio::test]
+    async fn test_charge_rules() {
+        let mut claim = empty_claim();
+        claim.extracted_data.service_lines = vec![
+            ServiceLine { charge: Some(80.0), ..service_line("99213") },
+            ServiceLine { units: 2, charge: Some(600.0), ..service_line("96372") },
+        ];
+        claim.extracted_data.charges = vec![80.0, 600.0, 700.0];
+        claim.extracted_data.total_charge = Some(700.0);
+        let mut fee_schedule = fee_schedule::parse_fee_file("hcpcs,price\n99213,92.35\n", FeeSource::Medicare, "01112").unwrap();
+        fee_schedule.extend(fee_schedule::parse_fee_file("code,price\n96372,45.00\n", FeeSource::Chargemaster, "").unwrap());
+        let reference = ReferenceData { fee_schedule, ..Default::default() };
+
+        let results = RulesEngine::new().with_reference_data(reference).validate_claim(&claim).await.unwrap();
+        let fired: Vec<&str> = results.iter().filter(|r| r.field.as_deref() == Some("charges")).map(|r| r.rule_id.as_str()).collect();
+        assert_eq!(fired, ["charge_below_medicare", "charge_above_chargemaster", "total_charge_mismatch"]);
+        let above = results.iter().find(|r| r.rule_id == "charge_above_chargemaster").unwrap();
+        assert_eq!(above.message, "Line 2 (96372): charge $600.00 is 6.7x the chargemaster price of $90.00");
+        let mismatch = results.iter().find(|r| r.rule_id == "total_charge_mismatch").unwrap();
+        assert_eq!(mismatch.fix, Some(FixPatch::SetField { field: "total_charge".to_string(), value: serde_json::json!(680.0) }));
+        assert_eq!(above.evidence.reference.as_ref().unwrap().row["amount"], 45.0);
+        assert_eq!(above.evidence.values["units"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_evidence_falls_back_to_field_and_quotes_source() {
+        let mut claim = empty_claim();
+        claim.extracted_data.provider_npi = Some("1234567890".to_string());
+        claim.extracted_data.raw_text = "Billing Provider\nNPI: 1234567890\n".to_string();
+
+        let results = RulesEngine::new().validate_claim(&claim).await.unwrap();
+        let invalid = results.iter().find(|r| r.rule_id == "invalid_npi").unwrap();
+        assert_eq!(invalid.evidence.values["provider_npi"], "1234567890");
+        assert_eq!(invalid.evidence.source.len(), 1);
+        assert_eq!((invalid.evidence.source[0].line, invalid.evidence.source[0].text.as_str()), (2, "NPI: 1234567890"));
+    }
+
+    #[test]
+    fn test_validate_overrides_rejects_unknown_and_duplicate_rules() {
+        assert!(validate_overrides(&[rule_override("missing_payer")], &[]).is_ok());
+        assert!(validate_overrides(&[rule_override("no_such_rule")], &[]).is_err());
+        assert!(validate_overrides(&[rule_override("missing_payer"), rule_override("missing_payer")], &[]).is_err());
+        let bad_threshold = RuleOverride { min_confidence: Some(1.5), ..rule_override("missing_payer") };
+        assert!(validate_overrides(&[bad_threshold], &[]).is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,7 +17,7 @@ pub struct Claim {
     pub comments: Vec<Comment>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ClaimStatus {
     Uploaded,
     Processing,
@@ -29,21 +29,197 @@ pub enum ClaimStatus {
     Paid,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ClaimStatus {
+    /// Claims still being worked; closed claims keep their assignee.
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            ClaimStatus::Uploaded | ClaimStatus::Processing | ClaimStatus::Processed | ClaimStatus::UnderReview
+        )
+    }
+
+    /// Claims sent to the payer, whether or not it has paid them yet.
+    pub fn is_submitted(&self) -> bool {
+        matches!(self, ClaimStatus::Submitted | ClaimStatus::Paid)
+    }
+}
+
+/// What a date on the claim refers to, decided by the label next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateRole {
+    ServiceFrom,
+    ServiceTo,
+    Admission,
+    Birth,
+    Onset,
+    Signature,
+    /// The primary payer's EOB, on secondary claims.
+    PrimaryEob,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredDate")]
+pub struct ExtractedDate {
+    pub role: DateRole,
+    pub date: DateTime<Utc>,
+}
+
+impl ExtractedDate {
+    pub fn new(role: DateRole, date: DateTime<Utc>) -> Self {
+        Self { role, date }
+    }
+}
+
+/// Claims saved before dates were labelled store bare timestamps; those
+/// load as unlabelled dates.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredDate {
+    Labelled { role: DateRole, date: DateTime<Utc> },
+    Bare(DateTime<Utc>),
+}
+
+impl From<StoredDate> for ExtractedDate {
+    fn from(stored: StoredDate) -> Self {
+        match stored {
+            StoredDate::Labelled { role, date } => ExtractedDate { role, date },
+            StoredDate::Bare(date) => ExtractedDate { role: DateRole::Other, date },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExtractedData {
     pub payer: Option<String>,
+    /// Payer plan or product, e.g. `HMO` or `Medicare Advantage`.
+    #[serde(default)]
+    pub plan: Option<String>,
     pub patient_name: Option<String>,
     pub patient_id: Option<String>,
+    #[serde(default)]
+    pub patient_dob: Option<NaiveDate>,
+    #[serde(default)]
+    pub patient_sex: Option<Sex>,
+    #[serde(default)]
     pub cpt_codes: Vec<String>,
+    /// Modifiers found on any service line.
+    #[serde(default)]
     pub modifiers: Vec<String>,
+    #[serde(default)]
     pub charges: Vec<f64>,
-    pub dates: Vec<DateTime<Utc>>,
+    /// Total charge as stated on the claim (CMS-1500 box 28).
+    #[serde(default)]
+    pub total_charge: Option<f64>,
+    #[serde(default)]
+    pub dates: Vec<ExtractedDate>,
     pub provider_name: Option<String>,
+    /// Billing NPI if one was identified, otherwise the best NPI candidate.
     pub provider_npi: Option<String>,
+    /// NPI of the billing provider (CMS-1500 box 33a).
+    #[serde(default)]
+    pub billing_npi: Option<String>,
+    /// NPI of the rendering provider (CMS-1500 box 24J).
+    #[serde(default)]
+    pub rendering_npi: Option<String>,
+    #[serde(default)]
     pub diagnosis_codes: Vec<String>,
+    #[serde(default)]
+    pub service_lines: Vec<ServiceLine>,
+    /// Claim frequency code (CMS-1500 box 22): 1 original, 7 replacement,
+    /// 8 void.
+    #[serde(default)]
+    pub frequency_code: Option<String>,
+    /// Payer reference of the claim a replacement or void applies to.
+    #[serde(default)]
+    pub original_claim_reference: Option<String>,
+    /// Date of the primary payer's EOB, present on secondary claims.
+    #[serde(default)]
+    pub primary_eob_date: Option<NaiveDate>,
+    #[serde(default)]
     pub raw_text: String,
 }
 
+impl ExtractedData {
+    /// The claim-level date of service: the earliest line date, falling back
+    /// to the first service date, then to the first unlabelled date.
+    pub fn date_of_service(&self) -> Option<DateTime<Utc>> {
+        self.service_lines
+            .iter()
+            .filter_map(|line| line.date_of_service)
+            .min()
+            .or_else(|| self.dates_with_role(DateRole::ServiceFrom).next())
+            .or_else(|| self.dates_with_role(DateRole::Other).next())
+    }
+
+    pub fn dates_with_role(&self, role: DateRole) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        self.dates.iter().filter(move |d| d.role == role).map(|d| d.date)
+    }
+
+    /// Every date the claim reports services on: line dates and the
+    /// claim's from/to dates.
+    pub fn service_dates(&self) -> Vec<DateTime<Utc>> {
+        let mut dates: Vec<DateTime<Utc>> = self.service_lines.iter().filter_map(|line| line.date_of_service).collect();
+        dates.extend(self.dates_with_role(DateRole::ServiceFrom));
+        dates.extend(self.dates_with_role(DateRole::ServiceTo));
+        if dates.is_empty() {
+            dates.extend(self.date_of_service());
+        }
+        dates
+    }
+
+    /// Services as billed, one per service line. Claims without parsed
+    /// lines fall back to one unit of each CPT code on the claim date, with
+    /// the claim's modifiers.
+    pub fn billed_services(&self) -> Vec<BilledService> {
+        let claim_date = self.date_of_service().map(|d| d.date_naive());
+
+        if self.service_lines.is_empty() {
+            return self.cpt_codes
+                .iter()
+                .map(|code| BilledService {
+                    procedure_code: code.clone(),
+                    modifiers: self.modifiers.clone(),
+                    units: 1,
+                    date_of_service: claim_date,
+                })
+                .collect();
+        }
+
+        self.service_lines
+            .iter()
+            .map(|line| BilledService {
+                procedure_code: line.procedure_code.clone(),
+                modifiers: line.modifiers.clone(),
+                units: line.units,
+                date_of_service: line.date_of_service.map(|d| d.date_naive()).or(claim_date),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BilledService {
+    pub procedure_code: String,
+    pub modifiers: Vec<String>,
+    pub units: u32,
+    pub date_of_service: Option<NaiveDate>,
+}
+
+/// One billed procedure, as read from a single line of the claim.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceLine {
+    pub procedure_code: String,
+    pub modifiers: Vec<String>,
+    pub units: u32,
+    pub charge: Option<f64>,
+    pub date_of_service: Option<DateTime<Utc>>,
+    /// Box 24E letters (`A`-`L`) pointing into the claim's diagnosis codes.
+    #[serde(default)]
+    pub diagnosis_pointers: Vec<char>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationResult {
     pub id: Uuid,
@@ -54,16 +230,153 @@ pub struct ValidationResult {
     pub field: Option<String>,
     pub suggested_fix: Option<String>,
     pub confidence: f64,
+    /// Rule configuration version in effect when this finding was produced.
+    #[serde(default)]
+    pub config_version: Option<i64>,
+    /// A correction `apply_fix` can make without the reviewer retyping it.
+    #[serde(default)]
+    pub fix: Option<FixPatch>,
+    #[serde(default)]
+    pub evidence: Evidence,
+}
+
+impl ValidationResult {
+    pub fn new(rule_id: &str, rule_name: &str, severity: Severity, message: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            rule_id: rule_id.to_string(),
+            rule_name: rule_name.to_string(),
+            severity,
+            message,
+            field: None,
+            suggested_fix: None,
+            confidence: 1.0,
+            config_version: None,
+            fix: None,
+            evidence: Evidence::default(),
+        }
+    }
+
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+
+    pub fn with_suggested_fix(mut self, suggested_fix: &str) -> Self {
+        self.suggested_fix = Some(suggested_fix.to_string());
+        self
+    }
+
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub fn with_fix(mut self, fix: FixPatch) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    /// Records a claim value the rule looked at.
+    pub fn with_value(mut self, name: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(serde_json::Value::Null);
+        self.evidence.values.insert(name.to_string(), value);
+        self
+    }
+
+    /// Records the reference data row the claim was checked against.
+    pub fn with_reference(mut self, table: &str, row: impl Serialize) -> Self {
+        self.evidence.reference = Some(ReferenceRow {
+            table: table.to_string(),
+            row: serde_json::to_value(row).unwrap_or(serde_json::Value::Null),
+        });
+        self
+    }
+}
+
+/// What a finding was based on, so reviewers can see why it fired and
+/// auditors can reproduce it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Evidence {
+    /// Claim values the rule looked at, by name.
+    #[serde(default)]
+    pub values: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub reference: Option<ReferenceRow>,
+    /// The built-in rule set's version, or a digest of a custom rule's
+    /// definition.
+    #[serde(default)]
+    pub rule_version: Option<String>,
+    /// Where the values appear in the claim's source text.
+    #[serde(default)]
+    pub source: Vec<SourceSnippet>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceRow {
+    /// The reference table, e.g. `ncci_edits` or `coverage_policies`.
+    pub table: String,
+    pub row: serde_json::Value,
+}
+
+/// One line of the claim's source text. Pages and lines are 1-based.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceSnippet {
+    pub page: usize,
+    pub line: usize,
+    pub text: String,
+}
+
+/// A machine-applicable correction. Line numbers are 1-based, as shown to
+/// reviewers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FixPatch {
+    /// Replaces a claim-level field of `ExtractedData`.
+    SetField { field: String, value: serde_json::Value },
+    AddModifier { line: usize, modifier: String },
+    RemoveModifier { line: usize, modifier: String },
+    /// Drops a service line, e.g. one billed twice.
+    RemoveLine { line: usize },
+}
+
+/// Outcome of applying one rule's fixes across a queue.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchFixSummary {
+    pub claims_fixed: usize,
+    pub fixes_applied: usize,
+    pub failures: Vec<BatchFixFailure>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchFixFailure {
+    pub claim_id: Uuid,
+    pub error: String,
+}
+
+/// A change made to a claim's extracted data, with the data as it was
+/// before so the change can be reviewed or undone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimHistoryEntry {
+    pub id: Uuid,
+    pub claim_id: Uuid,
+    pub user_id: Uuid,
+    /// Rule whose finding the change fixed.
+    pub rule_id: Option<String>,
+    pub patch: FixPatch,
+    pub description: String,
+    pub previous_data: ExtractedData,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Severity {
     Critical,
     Warning,
     Info,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum QueueType {
     CriticalErrors,
     WarningsOnly,
@@ -86,9 +399,12 @@ pub struct User {
     pub role: UserRole,
     pub created_at: DateTime<Utc>,
     pub last_login: Option<DateTime<Utc>>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub mfa_enabled: bool,
+    pub is_active: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UserRole {
     BillingCoder,
     Auditor,
@@ -96,6 +412,36 @@ pub enum UserRole {
     LocalAdmin,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthSession {
+    pub token: String,
+    pub user: User,
+    pub expires_at: DateTime<Utc>,
+    pub idle_timeout_minutes: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LoginResult {
+    Authenticated(AuthSession),
+    /// Password accepted; a TOTP or recovery code must be supplied with the
+    /// challenge token before a session is issued.
+    MfaRequired { challenge_token: String },
+    /// The role requires MFA but the user has not enrolled. The session only
+    /// permits MFA enrollment.
+    MfaEnrollmentRequired(AuthSession),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MfaPolicy {
+    pub required_roles: Vec<UserRole>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditLog {
     pub id: Uuid,
@@ -105,6 +451,89 @@ pub struct AuditLog {
     pub resource_id: Option<Uuid>,
     pub details: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Position in the hash chain, assigned when the entry is written.
+    pub sequence: i64,
+    pub prev_hash: String,
+    pub entry_hash: String,
+    pub signature: Option<String>,
+}
+
+impl AuditLog {
+    /// A new, unsealed entry. The chain fields are filled in by the database
+    /// when it is appended.
+    pub fn new(
+        user_id: Uuid,
+        action: &str,
+        resource_type: &str,
+        resource_id: Option<Uuid>,
+        details: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            action: action.to_string(),
+            resource_type: resource_type.to_string(),
+            resource_id,
+            details,
+            created_at: Utc::now(),
+            sequence: 0,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
+            signature: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditLogQuery {
+    pub user_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<Uuid>,
+    /// Matches claim entries whose claim belongs to this patient, by patient
+    /// id or full name.
+    pub patient: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLog>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditExport {
+    pub generated_at: DateTime<Utc>,
+    pub generated_by: Uuid,
+    pub query: AuditLogQuery,
+    pub entries: Vec<AuditLog>,
+    /// SHA-256 over `generated_at`, `generated_by`, `query` and `entries`.
+    pub content_hash: String,
+    /// HMAC-SHA256 of `content_hash` with the audit signing key.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditIssue {
+    pub sequence: i64,
+    pub kind: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub entries_checked: i64,
+    pub head_sequence: i64,
+    pub head_hash: String,
+    pub signed: bool,
+    pub issues: Vec<AuditIssue>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,8 +569,303 @@ pub struct Settings {
     pub ocr_provider: String,
     pub cloud_ocr_enabled: bool,
     pub llm_provider: Option<String>,
+    /// Never stored with the other settings; see `key_store`. Reads return
+    /// a redacted placeholder when a key is configured.
     pub encryption_key: Option<String>,
     pub rules_config: serde_json::Value,
+    /// Which NCCI PTP and MUE tables apply to the claims processed here.
+    #[serde(default)]
+    pub ncci_setting: NcciSetting,
+    /// MAC jurisdiction (e.g. `J15`) whose LCDs apply; national coverage
+    /// determinations apply regardless.
+    #[serde(default)]
+    pub coverage_jurisdiction: Option<String>,
+    /// Medicare fee schedule locality that line charges are compared to.
+    #[serde(default)]
+    pub fee_schedule_locality: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            hipaa_mode: true,
+            ocr_provider: "tesseract".to_string(),
+            cloud_ocr_enabled: false,
+            llm_provider: None,
+            encryption_key: None,
+            rules_config: serde_json::json!({ "rules": [] }),
+            ncci_setting: NcciSetting::default(),
+            coverage_jurisdiction: None,
+            fee_schedule_locality: None,
+        }
+    }
+}
+
+/// Adjusts one rule, built-in or custom, for this workspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleOverride {
+    pub rule_id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub severity: Option<Severity>,
+    /// Findings with a lower confidence are dropped.
+    pub min_confidence: Option<f64>,
+}
+
+/// Custom rules and overrides applied to one payer's claims, or shared by
+/// other packs through `extends`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulePack {
+    pub id: String,
+    pub name: String,
+    /// Registry id of the payer the pack applies to; `None` for a base pack
+    /// that is only used through inheritance.
+    pub payer_id: Option<String>,
+    /// Narrows the pack to one of the payer's plans.
+    #[serde(default)]
+    pub plan: Option<String>,
+    /// Id of the pack this one builds on.
+    #[serde(default)]
+    pub extends: Option<String>,
+    /// Rule definitions in the `Settings.rules_config` format.
+    #[serde(default)]
+    pub rules: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub overrides: Vec<RuleOverride>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Only active packs are applied to claims. A pack can't be activated
+    /// while any of its rule tests fail.
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// A fixture claim attached to a custom rule, listing the rules expected
+/// to fire on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleTest {
+    pub name: String,
+    pub claim: ExtractedData,
+    #[serde(default)]
+    pub expect: Vec<String>,
+    /// Day the rules run as of, for deadline rules; defaults to the
+    /// fixture's date of service.
+    #[serde(default)]
+    pub as_of: Option<NaiveDate>,
+}
+
+/// Outcome of one rule test. Only the rule under test and the expected
+/// rules are compared; other findings on the fixture are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleTestResult {
+    pub rule_id: String,
+    pub test_name: String,
+    pub passed: bool,
+    /// Expected rules that did not fire.
+    pub missing: Vec<String>,
+    /// Rules that fired without being expected.
+    pub unexpected: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Every save creates a new version so findings can be traced back to the
+/// configuration that produced them. Version 0 means nothing has been saved.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleConfiguration {
+    pub version: i64,
+    pub overrides: Vec<RuleOverride>,
+    pub created_by: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// One row of an imported ICD-10-CM release, stored without the dot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Icd10Code {
+    pub code: String,
+    pub description: String,
+    /// False for category and subcategory headers.
+    pub billable: bool,
+    pub fiscal_year: i32,
+}
+
+/// One effective period of a CPT or HCPCS Level II code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcedureCode {
+    pub code: String,
+    /// "CPT" or "HCPCS".
+    pub code_type: String,
+    pub description: String,
+    /// None when the source file gives no start date.
+    pub effective_date: Option<NaiveDate>,
+    pub termination_date: Option<NaiveDate>,
+}
+
+/// NCCI publishes separate PTP and MUE tables for practitioner (CMS-1500)
+/// and outpatient hospital (UB-04) claims.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NcciSetting {
+    #[default]
+    Practitioner,
+    Hospital,
+}
+
+impl NcciSetting {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NcciSetting::Practitioner => "practitioner",
+            NcciSetting::Hospital => "hospital",
+        }
+    }
+}
+
+/// One procedure-to-procedure edit: `column2` is not separately payable
+/// with `column1` on the same date of service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NcciEdit {
+    pub setting: NcciSetting,
+    pub column1: String,
+    pub column2: String,
+    pub effective_date: NaiveDate,
+    pub deletion_date: Option<NaiveDate>,
+    /// 0: no modifier allowed, 1: modifier allowed, 9: not applicable.
+    pub modifier_indicator: u8,
+    pub rationale: String,
+}
+
+/// Medically Unlikely Edit: the most units of a code normally billed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MueLimit {
+    pub setting: NcciSetting,
+    pub code: String,
+    pub mue_value: u32,
+    /// 1: per line, 2: per date of service (policy), 3: per date of
+    /// service (clinical, appealable with documentation).
+    pub adjudication_indicator: u8,
+    pub rationale: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Modifier {
+    pub code: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sex {
+    Male,
+    Female,
+}
+
+impl Sex {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sex::Male => "male",
+            Sex::Female => "female",
+        }
+    }
+
+    /// Accepts `M`/`F` as well as the full words, in any case.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "m" | "male" => Some(Sex::Male),
+            "f" | "female" => Some(Sex::Female),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeSystem {
+    Icd10,
+    Procedure,
+}
+
+impl CodeSystem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CodeSystem::Icd10 => "icd10",
+            CodeSystem::Procedure => "procedure",
+        }
+    }
+}
+
+/// Age and sex restrictions on a diagnosis or procedure code, such as
+/// newborn, maternity or sex-specific codes. ICD-10 entries may name a
+/// category and then cover its children.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeAttribute {
+    pub code_system: CodeSystem,
+    pub code: String,
+    pub min_age: Option<u32>,
+    pub max_age: Option<u32>,
+    pub sex: Option<Sex>,
+    pub description: String,
+}
+
+/// A payer in the registry, matched to claims by name or alias.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payer {
+    pub id: String,
+    pub name: String,
+    /// Lowercase names the payer appears under on claims.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Days from the date of service to submit the claim.
+    pub filing_limit_days: Option<u32>,
+    /// Days from the primary payer's EOB to submit a secondary claim.
+    pub secondary_filing_limit_days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSource {
+    /// Medicare physician fee schedule allowed amounts, by locality.
+    Medicare,
+    /// The practice's own list prices.
+    Chargemaster,
+}
+
+impl FeeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeeSource::Medicare => "medicare",
+            FeeSource::Chargemaster => "chargemaster",
+        }
+    }
+}
+
+/// One per-unit amount for a code; `modifier` is empty for the global
+/// service and `locality` is empty for the chargemaster.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeScheduleEntry {
+    pub source: FeeSource,
+    pub locality: String,
+    pub code: String,
+    pub modifier: String,
+    pub amount: f64,
+}
+
+/// One covered ICD-10 range of an LCD or NCD for one procedure code.
+/// Codes are stored normalized; the range includes children of `icd10_end`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageRule {
+    pub policy_id: String,
+    pub title: String,
+    /// `LCD` or `NCD`.
+    pub policy_type: String,
+    /// MAC jurisdiction for LCDs; `None` for national policies.
+    pub jurisdiction: Option<String>,
+    pub procedure_code: String,
+    pub icd10_start: String,
+    pub icd10_end: String,
+    pub effective_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -153,3 +877,17 @@ pub struct UploadProgress {
     pub error: Option<String>,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bare_timestamps_load_as_unlabelled_dates() {
+        let dates: Vec<ExtractedDate> = serde_json::from_str(
+            r#"["2024-01-15T00:00:00Z", {"role": "birth", "date": "1958-04-12T00:00:00Z"}]"#
+        ).unwrap();
+        assert_eq!(dates[0].role, DateRole::Other);
+        assert_eq!(dates[1].role, DateRole::Birth);
+    }
+}
@@ -15,6 +15,7 @@ import {
 } from 'lucide-react'
 import { useAuthStore } from '../stores/authStore'
 import { useDemoStore } from '../stores/demoStore'
+import { invokeWithSession } from '../lib/api'
 
 interface DashboardLayoutProps {
   children: React.ReactNode
@@ -37,6 +38,11 @@ const DashboardLayout: React.FC<DashboardLayoutProps> = ({ children }) => {
   ]
 
   const handleLogout = async () => {
+    try {
+      await invokeWithSession('logout')
+    } catch (error) {
+      console.error('Logout error:', error)
+    }
     logout()
   }
 
@@ -0,0 +1,21 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { useAuthStore } from '../stores/authStore'
+
+// Errors the backend returns when the session is unknown (e.g. after a
+// restart) or has timed out.
+const SESSION_ERRORS = ['Invalid session', 'Session expired']
+
+// Every backend command checks the caller's session, so the token from
+// login is sent along with the command's own arguments. A command refused
+// for a dead session sends the user back to sign in.
+export async function invokeWithSession<T>(command: string, args: Record<string, unknown> = {}): Promise<T> {
+  const { sessionToken, expireSession } = useAuthStore.getState()
+  try {
+    return await invoke<T>(command, { ...args, sessionToken })
+  } catch (err) {
+    if (typeof err === 'string' && SESSION_ERRORS.includes(err)) {
+      expireSession()
+    }
+    throw err
+  }
+}
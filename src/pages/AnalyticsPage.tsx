@@ -1,5 +1,5 @@
 import React, { useState, useEffect } from 'react'
-import { invoke } from '@tauri-apps/api/tauri'
+import { invokeWithSession } from '../lib/api'
 import { 
   BarChart3, 
   TrendingUp, 
@@ -57,7 +57,7 @@ const AnalyticsPage: React.FC = () => {
   const loadAnalytics = async () => {
     setLoading(true)
     try {
-      const result = await invoke<Analytics>('get_analytics')
+      const result = await invokeWithSession<Analytics>('get_analytics')
       setAnalytics(result)
     } catch (error) {
       console.error('Error loading analytics:', error)
@@ -68,7 +68,7 @@ const AnalyticsPage: React.FC = () => {
 
   const exportReport = async () => {
     try {
-      await invoke('export_claims', { 
+      await invokeWithSession('export_claims', { 
         claimIds: [], 
         format: 'pdf' 
       })
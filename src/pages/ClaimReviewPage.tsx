@@ -1,6 +1,6 @@
 import React, { useState, useEffect } from 'react'
 import { useParams, useNavigate } from 'react-router-dom'
-import { invoke } from '@tauri-apps/api/tauri'
+import { invokeWithSession } from '../lib/api'
 import { 
   ArrowLeft, 
   Save, 
@@ -32,7 +32,7 @@ const ClaimReviewPage: React.FC = () => {
   const loadClaim = async (claimId: string) => {
     setLoading(true)
     try {
-      const result = await invoke<Claim>('get_claim_by_id', { claimId })
+      const result = await invokeWithSession<Claim>('get_claim_by_id', { claimId })
       setClaim(result)
     } catch (error) {
       console.error('Error loading claim:', error)
@@ -46,7 +46,7 @@ const ClaimReviewPage: React.FC = () => {
 
     setSaving(true)
     try {
-      await invoke('update_claim', { claim })
+      await invokeWithSession('update_claim', { claim })
       updateClaim(claim)
       // Show success message
     } catch (error) {
@@ -60,7 +60,7 @@ const ClaimReviewPage: React.FC = () => {
     if (!claim) return
 
     try {
-      const results = await invoke<ValidationResult[]>('run_rules', { claimId: claim.id })
+      const results = await invokeWithSession<ValidationResult[]>('run_rules', { claimId: claim.id })
       setClaim(prev => prev ? { ...prev, validation_results: results } : null)
     } catch (error) {
       console.error('Error running validation:', error)
@@ -386,6 +386,30 @@ const ClaimReviewPage: React.FC = () => {
                           <p className="text-sm text-gray-700">{result.suggested_fix}</p>
                         </div>
                       )}
+                      {result.evidence && (
+                        <details className="mt-2 text-sm text-gray-700">
+                          <summary className="cursor-pointer font-medium text-gray-900">Why did this fire?</summary>
+                          {Object.entries(result.evidence.values).map(([name, value]) => (
+                            <p key={name} className="mt-1">
+                              <span className="font-medium">{name}:</span> {JSON.stringify(value)}
+                            </p>
+                          ))}
+                          {result.evidence.reference && (
+                            <p className="mt-1">
+                              <span className="font-medium">{result.evidence.reference.table}:</span>{' '}
+                              <code className="text-xs">{JSON.stringify(result.evidence.reference.row)}</code>
+                            </p>
+                          )}
+                          {result.evidence.source.map((snippet) => (
+                            <p key={`${snippet.page}-${snippet.line}`} className="mt-1 font-mono text-xs">
+                              p.{snippet.page} l.{snippet.line}: {snippet.text}
+                            </p>
+                          ))}
+                          {result.evidence.rule_version && (
+                            <p className="mt-1 text-xs text-gray-500">Rule version {result.evidence.rule_version}</p>
+                          )}
+                        </details>
+                      )}
                       <div className="mt-2 text-xs text-gray-500">
                         Confidence: {Math.round(result.confidence * 100)}%
                       </div>
@@ -1,11 +1,17 @@
 import React, { useState } from 'react'
-import { useAuthStore } from '../stores/authStore'
-import { supabase } from '../lib/supabase'
+import { invoke } from '@tauri-apps/api/tauri'
+import { useAuthStore, AuthSession, LoginResult } from '../stores/authStore'
+
+interface MfaEnrollment {
+  secret: string
+  provisioning_uri: string
+}
 
 const LoginPage: React.FC = () => {
   const [isLogin, setIsLogin] = useState(true)
+  const { login, user: previousUser, sessionExpired } = useAuthStore()
   const [formData, setFormData] = useState({
-    username: '',
+    username: previousUser?.username ?? '',
     email: '',
     password: '',
     role: 'BillingCoder'
@@ -13,8 +19,12 @@ const LoginPage: React.FC = () => {
   const [hipaaMode, setHipaaMode] = useState(true)
   const [loading, setLoading] = useState(false)
   const [error, setError] = useState('')
-  
-  const { login } = useAuthStore()
+  const [code, setCode] = useState('')
+  const [challengeToken, setChallengeToken] = useState<string | null>(null)
+  const [pendingSession, setPendingSession] = useState<AuthSession | null>(null)
+  const [enrollment, setEnrollment] = useState<MfaEnrollment | null>(null)
+  const [recoveryCodes, setRecoveryCodes] = useState<string[] | null>(null)
+  const secondFactor = challengeToken !== null || pendingSession !== null
 
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault()
@@ -22,70 +32,54 @@ const LoginPage: React.FC = () => {
     setError('')
 
     try {
-      if (isLogin) {
-        const { data, error } = await supabase.auth.signInWithPassword({
-          email: formData.email || formData.username,
-          password: formData.password
-        })
+      if (pendingSession && recoveryCodes) {
+        login(pendingSession.user, pendingSession.token)
+        return
+      }
 
-        if (error) {
-          setError(error.message)
-          return
-        }
+      if (challengeToken) {
+        const session = await invoke<AuthSession>('verify_mfa', { challengeToken, code })
+        login(session.user, session.token)
+        return
+      }
 
-        if (data.user) {
-          const { data: profile } = await supabase
-            .from('profiles')
-            .select('*')
-            .eq('id', data.user.id)
-            .maybeSingle()
+      if (pendingSession) {
+        const codes = await invoke<string[]>('confirm_mfa_enrollment', {
+          sessionToken: pendingSession.token,
+          code
+        })
+        setRecoveryCodes(codes)
+        return
+      }
 
-          if (profile) {
-            login({
-              id: profile.id,
-              username: profile.username,
-              email: profile.email,
-              role: profile.role,
-              created_at: profile.created_at,
-              last_login: profile.last_login
-            })
-          }
-        }
-      } else {
-        const { data, error } = await supabase.auth.signUp({
+      if (!isLogin) {
+        // Only the first account can be created from here; later accounts
+        // are added by an administrator on the Users page.
+        await invoke('create_user', {
+          sessionToken: null,
+          username: formData.username,
           email: formData.email,
           password: formData.password,
-          options: {
-            data: {
-              username: formData.username,
-              role: formData.role
-            }
-          }
+          role: formData.role
         })
+      }
 
-        if (error) {
-          setError(error.message)
-          return
-        }
-
-        if (data.user) {
-          const { data: profile } = await supabase
-            .from('profiles')
-            .select('*')
-            .eq('id', data.user.id)
-            .maybeSingle()
+      const result = await invoke<LoginResult | null>('authenticate_user', {
+        username: formData.username,
+        password: formData.password
+      })
 
-          if (profile) {
-            login({
-              id: profile.id,
-              username: profile.username,
-              email: profile.email,
-              role: profile.role,
-              created_at: profile.created_at,
-              last_login: profile.last_login
-            })
-          }
-        }
+      if (!result) {
+        setError('Invalid username or password')
+      } else if ('Authenticated' in result) {
+        login(result.Authenticated.user, result.Authenticated.token)
+      } else if ('MfaRequired' in result) {
+        setChallengeToken(result.MfaRequired.challenge_token)
+      } else {
+        const session = result.MfaEnrollmentRequired
+        const details = await invoke<MfaEnrollment>('begin_mfa_enrollment', { sessionToken: session.token })
+        setPendingSession(session)
+        setEnrollment(details)
       }
     } catch (err) {
       console.error('Authentication error:', err)
@@ -143,84 +137,129 @@ const LoginPage: React.FC = () => {
           </div>
 
           <form className="space-y-6" onSubmit={handleSubmit}>
-            {!isLogin && (
-              <div>
-                <label htmlFor="email" className="block text-sm font-medium text-gray-700">
-                  Email address
-                </label>
-                <div className="mt-1">
-                  <input
-                    id="email"
-                    name="email"
-                    type="email"
-                    autoComplete="email"
-                    required={!isLogin}
-                    className="input"
-                    value={formData.email}
-                    onChange={handleInputChange}
-                  />
+            {secondFactor ? (
+              <>
+                {enrollment && !recoveryCodes && (
+                  <div className="text-sm text-gray-700 space-y-2">
+                    <p>Your role requires two-step verification. Add this key to your authenticator app, then enter the code it shows.</p>
+                    <p className="font-mono text-xs break-all bg-gray-50 p-2 rounded">{enrollment.secret}</p>
+                    <p className="text-xs text-gray-500 break-all">{enrollment.provisioning_uri}</p>
+                  </div>
+                )}
+                {recoveryCodes ? (
+                  <div className="text-sm text-gray-700 space-y-2">
+                    <p>Save these recovery codes somewhere safe. Each can be used once if you lose your authenticator; they won't be shown again.</p>
+                    <ul className="font-mono text-xs grid grid-cols-2 gap-1 bg-gray-50 p-2 rounded">
+                      {recoveryCodes.map((recoveryCode) => (
+                        <li key={recoveryCode}>{recoveryCode}</li>
+                      ))}
+                    </ul>
+                  </div>
+                ) : (
+                  <div>
+                    <label htmlFor="code" className="block text-sm font-medium text-gray-700">
+                      Verification code
+                    </label>
+                    <div className="mt-1">
+                      <input
+                        id="code"
+                        name="code"
+                        type="text"
+                        autoComplete="one-time-code"
+                        required
+                        className="input"
+                        value={code}
+                        onChange={(e) => setCode(e.target.value)}
+                      />
+                    </div>
+                    {challengeToken && (
+                      <p className="mt-1 text-xs text-gray-500">Enter the code from your authenticator app or a recovery code.</p>
+                    )}
+                  </div>
+                )}
+              </>
+            ) : (
+              <>
+                {!isLogin && (
+                  <div>
+                    <label htmlFor="email" className="block text-sm font-medium text-gray-700">
+                      Email address
+                    </label>
+                    <div className="mt-1">
+                      <input
+                        id="email"
+                        name="email"
+                        type="email"
+                        autoComplete="email"
+                        required={!isLogin}
+                        className="input"
+                        value={formData.email}
+                        onChange={handleInputChange}
+                      />
+                    </div>
+                  </div>
+                )}
+
+                <div>
+                  <label htmlFor="username" className="block text-sm font-medium text-gray-700">
+                    Username
+                  </label>
+                  <div className="mt-1">
+                    <input
+                      id="username"
+                      name="username"
+                      type="text"
+                      autoComplete="username"
+                      required
+                      className="input"
+                      value={formData.username}
+                      onChange={handleInputChange}
+                    />
+                  </div>
                 </div>
-              </div>
-            )}
 
-            <div>
-              <label htmlFor="username" className="block text-sm font-medium text-gray-700">
-                Username
-              </label>
-              <div className="mt-1">
-                <input
-                  id="username"
-                  name="username"
-                  type="text"
-                  autoComplete="username"
-                  required
-                  className="input"
-                  value={formData.username}
-                  onChange={handleInputChange}
-                />
-              </div>
-            </div>
+                {!isLogin && (
+                  <div>
+                    <label htmlFor="role" className="block text-sm font-medium text-gray-700">
+                      Role
+                    </label>
+                    <div className="mt-1">
+                      <select
+                        id="role"
+                        name="role"
+                        className="input"
+                        value={formData.role}
+                        onChange={handleInputChange}
+                      >
+                        <option value="BillingCoder">Billing Coder</option>
+                        <option value="Auditor">Auditor / QA</option>
+                        <option value="BillingManager">Billing Manager</option>
+                        <option value="LocalAdmin">Local Admin</option>
+                      </select>
+                    </div>
+                  </div>
+                )}
 
-            {!isLogin && (
-              <div>
-                <label htmlFor="role" className="block text-sm font-medium text-gray-700">
-                  Role
-                </label>
-                <div className="mt-1">
-                  <select
-                    id="role"
-                    name="role"
-                    className="input"
-                    value={formData.role}
-                    onChange={handleInputChange}
-                  >
-                    <option value="BillingCoder">Billing Coder</option>
-                    <option value="Auditor">Auditor / QA</option>
-                    <option value="BillingManager">Billing Manager</option>
-                    <option value="LocalAdmin">Local Admin</option>
-                  </select>
+                <div>
+                  <label htmlFor="password" className="block text-sm font-medium text-gray-700">
+                    Password
+                  </label>
+                  <div className="mt-1">
+                    <input
+                      id="password"
+                      name="password"
+                      type="password"
+                      autoComplete="current-password"
+                      required
+                      className="input"
+                      value={formData.password}
+                      onChange={handleInputChange}
+                    />
+                  </div>
                 </div>
-              </div>
+              </>
             )}
 
-            <div>
-              <label htmlFor="password" className="block text-sm font-medium text-gray-700">
-                Password
-              </label>
-              <div className="mt-1">
-                <input
-                  id="password"
-                  name="password"
-                  type="password"
-                  autoComplete="current-password"
-                  required
-                  className="input"
-                  value={formData.password}
-                  onChange={handleInputChange}
-                />
-              </div>
-            </div>
-
             <div className="flex items-center">
               <input
                 id="hipaa-mode"
@@ -235,6 +274,12 @@ const LoginPage: React.FC = () => {
               </label>
             </div>
 
+            {sessionExpired && !error && !secondFactor && (
+              <div className="rounded-md bg-warning-50 p-4">
+                <div className="text-sm text-warning-700">Your session has ended. Please sign in again.</div>
+              </div>
+            )}
+
             {error && (
               <div className="rounded-md bg-error-50 p-4">
                 <div className="text-sm text-error-700">{error}</div>
@@ -247,7 +292,7 @@ const LoginPage: React.FC = () => {
                 disabled={loading}
                 className="w-full btn btn-primary disabled:opacity-50 disabled:cursor-not-allowed"
               >
-                {loading ? 'Please wait...' : (isLogin ? 'Sign In' : 'Create Account')}
+                {loading ? 'Please wait...' : secondFactor ? (recoveryCodes ? 'Continue' : 'Verify') : (isLogin ? 'Sign In' : 'Create Account')}
               </button>
             </div>
           </form>
@@ -1,5 +1,5 @@
 import React, { useState, useEffect } from 'react'
-import { invoke } from '@tauri-apps/api/tauri'
+import { invokeWithSession } from '../lib/api'
 import { 
   Shield, 
   Eye, 
@@ -44,7 +44,7 @@ const SettingsPage: React.FC = () => {
   const loadSettings = async () => {
     setLoading(true)
     try {
-      const result = await invoke<Settings>('get_settings')
+      const result = await invokeWithSession<Settings>('get_settings')
       setSettings(result)
     } catch (error) {
       console.error('Error loading settings:', error)
@@ -56,7 +56,7 @@ const SettingsPage: React.FC = () => {
   const saveSettings = async () => {
     setSaving(true)
     try {
-      await invoke('update_settings', { settings })
+      await invokeWithSession('update_settings', { settings })
       // Show success message
     } catch (error) {
       console.error('Error saving settings:', error)
@@ -1,5 +1,5 @@
 import React, { useState, useEffect } from 'react'
-import { invoke } from '@tauri-apps/api/tauri'
+import { invokeWithSession } from '../lib/api'
 import { 
   Plus, 
   Edit, 
@@ -42,7 +42,7 @@ const UsersPage: React.FC = () => {
   const loadUsers = async () => {
     setLoading(true)
     try {
-      const result = await invoke<User[]>('get_users')
+      const result = await invokeWithSession<User[]>('get_users')
       setUsers(result)
     } catch (error) {
       console.error('Error loading users:', error)
@@ -54,7 +54,7 @@ const UsersPage: React.FC = () => {
   const handleCreateUser = async (e: React.FormEvent) => {
     e.preventDefault()
     try {
-      await invoke('create_user', {
+      await invokeWithSession('create_user', {
         username: formData.username,
         email: formData.email,
         password: formData.password,
@@ -71,7 +71,7 @@ const UsersPage: React.FC = () => {
   const handleDeleteUser = async (userId: string) => {
     if (window.confirm('Are you sure you want to delete this user?')) {
       try {
-        await invoke('delete_user', { userId })
+        await invokeWithSession('delete_user', { userId })
         loadUsers()
       } catch (error) {
         console.error('Error deleting user:', error)
@@ -79,6 +79,15 @@ const UsersPage: React.FC = () => {
     }
   }
 
+  const handleUnlockWorkstation = async () => {
+    try {
+      const cleared = await invokeWithSession<number>('unlock_workstation')
+      window.alert(`Cleared ${cleared} failed login attempts for this workstation.`)
+    } catch (error) {
+      console.error('Error unlocking workstation:', error)
+    }
+  }
+
   const getRoleIcon = (role: string) => {
     switch (role) {
       case 'LocalAdmin':
@@ -135,13 +144,21 @@ const UsersPage: React.FC = () => {
           </p>
         </div>
         
-        <button
-          onClick={() => setShowCreateModal(true)}
-          className="btn btn-primary"
-        >
-          <Plus className="h-4 w-4 mr-2" />
-          Add User
-        </button>
+        <div className="flex space-x-2">
+          <button
+            onClick={handleUnlockWorkstation}
+            className="btn btn-secondary"
+          >
+            Unlock Workstation
+          </button>
+          <button
+            onClick={() => setShowCreateModal(true)}
+            className="btn btn-primary"
+          >
+            <Plus className="h-4 w-4 mr-2" />
+            Add User
+          </button>
+        </div>
       </div>
 
       {/* Filters */}
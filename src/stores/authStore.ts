@@ -8,25 +8,50 @@ export interface User {
   role: 'BillingCoder' | 'Auditor' | 'BillingManager' | 'LocalAdmin'
   created_at: string
   last_login?: string
+  mfa_enabled?: boolean
+  is_active?: boolean
 }
 
+export interface AuthSession {
+  token: string
+  user: User
+  expires_at: string
+  idle_timeout_minutes: number
+}
+
+export type LoginResult =
+  | { Authenticated: AuthSession }
+  | { MfaRequired: { challenge_token: string } }
+  | { MfaEnrollmentRequired: AuthSession }
+
 interface AuthState {
   user: User | null
+  sessionToken: string | null
   isAuthenticated: boolean
-  login: (user: User) => void
+  sessionExpired: boolean
+  login: (user: User, sessionToken: string) => void
   logout: () => void
+  expireSession: () => void
 }
 
 export const useAuthStore = create<AuthState>()(
   persist(
     (set) => ({
       user: null,
+      sessionToken: null,
       isAuthenticated: false,
-      login: (user: User) => set({ user, isAuthenticated: true }),
-      logout: () => set({ user: null, isAuthenticated: false }),
+      sessionExpired: false,
+      login: (user: User, sessionToken: string) => set({ user, sessionToken, isAuthenticated: true, sessionExpired: false }),
+      logout: () => set({ user: null, sessionToken: null, isAuthenticated: false, sessionExpired: false }),
+      // Keeps the user so the sign-in form can offer their username again.
+      expireSession: () => set({ sessionToken: null, isAuthenticated: false, sessionExpired: true }),
     }),
     {
       name: 'auth-storage',
+      // Sessions only live in the backend's memory, so a stored token is
+      // useless after a restart, and a bearer token shouldn't sit in plain
+      // storage. Only the user is kept; signing in starts every launch.
+      partialize: (state) => ({ user: state.user }),
     }
   )
 )
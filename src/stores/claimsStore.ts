@@ -14,14 +14,30 @@ export interface Claim {
   comments: Comment[]
 }
 
+export type DateRole =
+  | 'service_from'
+  | 'service_to'
+  | 'admission'
+  | 'birth'
+  | 'onset'
+  | 'signature'
+  | 'primary_eob'
+  | 'other'
+
+export interface ExtractedDate {
+  role: DateRole
+  date: string
+}
+
 export interface ExtractedData {
   payer?: string
+  plan?: string
   patient_name?: string
   patient_id?: string
   cpt_codes: string[]
   modifiers: string[]
   charges: number[]
-  dates: string[]
+  dates: ExtractedDate[]
   provider_name?: string
   provider_npi?: string
   diagnosis_codes: string[]
@@ -37,8 +53,23 @@ export interface ValidationResult {
   field?: string
   suggested_fix?: string
   confidence: number
+  fix?: FixPatch
+  evidence?: Evidence
 }
 
+export interface Evidence {
+  values: Record<string, unknown>
+  reference?: { table: string; row: unknown }
+  rule_version?: string
+  source: { page: number; line: number; text: string }[]
+}
+
+export type FixPatch =
+  | { op: 'set_field'; field: string; value: unknown }
+  | { op: 'add_modifier'; line: number; modifier: string }
+  | { op: 'remove_modifier'; line: number; modifier: string }
+  | { op: 'remove_line'; line: number }
+
 export interface Comment {
   id: string
   user_id: string
@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/tauri'
+import { invokeWithSession } from '../lib/api'
 
 interface SampleClaim {
   filename: string
@@ -127,17 +127,17 @@ export async function loadSampleData(): Promise<void> {
       }
 
       // Upload the claim
-      await invoke('upload_files', { 
+      await invokeWithSession('upload_files', { 
         filePaths: [mockClaim.file_path] 
       })
 
       // Run OCR processing
-      await invoke('start_ocr', { 
+      await invokeWithSession('start_ocr', { 
         claimId: mockClaim.id 
       })
 
       // Run validation rules
-      await invoke('run_rules', { 
+      await invokeWithSession('run_rules', { 
         claimId: mockClaim.id 
       })
 
@@ -184,7 +184,7 @@ export async function createSampleUsers(): Promise<void> {
 
     for (const user of sampleUsers) {
       try {
-        await invoke('create_user', user)
+        await invokeWithSession('create_user', user)
         console.log(`Created user: ${user.username}`)
       } catch (error) {
         console.log(`User ${user.username} may already exist`)